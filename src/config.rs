@@ -0,0 +1,498 @@
+//! Persisting state between sessions.
+
+use crate::{
+    rename::Pipeline,
+    style::{Highlight, Theme},
+};
+use directories::ProjectDirs;
+use iced::keyboard::Modifiers;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    fmt,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// The state of the app that is restored when it is reopened.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// The paths of every entry in the list.
+    pub paths: Vec<PathBuf>,
+    /// The rules that were applied most recently.
+    pub pipeline: Pipeline,
+    /// The directory that files were most recently chosen to be moved into when renamed.
+    pub target_dir: Option<PathBuf>,
+}
+
+impl Session {
+    const FILE_NAME: &'static str = "session.json";
+
+    /// Loads the [`Session`] saved by a previous run of the app. If there isn't one, or it can't be read,
+    /// an empty [`Session`] is returned instead.
+    pub fn load() -> Self {
+        load(Self::FILE_NAME).unwrap_or_default()
+    }
+
+    /// Saves this [`Session`] so that it can be loaded by a later run of the app.
+    pub fn save(&self) -> io::Result<()> {
+        save(Self::FILE_NAME, self)
+    }
+}
+
+/// The user's choices of how the app looks and behaves, which are saved as soon as they're changed.
+///
+/// Settings that aren't in the saved file are given their defaults, and settings in the saved file that
+/// aren't recognised are ignored, so that preferences can be shared between versions of the app.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    /// The theme that the app is styled with.
+    pub theme: Theme,
+    /// The colour behind the parts of names that match the find text.
+    pub highlight: Highlight,
+    /// Whether the full path of each entry is shown rather than only its file name.
+    pub show_full_paths: bool,
+    /// Whether paths too long to fit in the list are shortened, with the full path shown on hover.
+    pub shorten_paths: bool,
+    /// Whether the size and modification time of each entry are shown.
+    pub show_details: bool,
+    /// Whether the preview of each new name highlights the characters that renaming inserts and removes.
+    pub show_diff: bool,
+    /// Whether the list is shown with short, evenly sized rows, to fit more entries.
+    pub compact_rows: bool,
+    /// Whether names too long for the list wrap onto several lines rather than being cut off.
+    pub wrap_names: bool,
+    /// Whether the filter only matches text of the same case.
+    pub filter_case_sensitive: bool,
+    /// Whether pressing Delete in the list moves the selected files to the trash, rather than only
+    /// removing them from the list.
+    pub delete_to_trash: bool,
+    /// Which modifier has to be held with the Delete key to delete the selected entries.
+    pub delete_key: DeleteKey,
+    /// The most entries that can be removed at once without asking for confirmation.
+    pub confirm_removal_threshold: usize,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            theme: Theme::default(),
+            highlight: Highlight::default(),
+            show_full_paths: false,
+            shorten_paths: false,
+            show_details: false,
+            show_diff: false,
+            compact_rows: false,
+            wrap_names: false,
+            filter_case_sensitive: false,
+            delete_to_trash: false,
+            delete_key: DeleteKey::default(),
+            confirm_removal_threshold: 10,
+        }
+    }
+}
+
+impl Preferences {
+    const FILE_NAME: &'static str = "preferences.json";
+
+    /// Loads the saved [`Preferences`], if there are any and they can be read.
+    pub fn load() -> Option<Self> {
+        load(Self::FILE_NAME)
+    }
+
+    /// Loads the preferences that older versions of the app saved in the [`Session`], before they had a
+    /// file of their own. Those that weren't saved there, or all of them if the session can't be read,
+    /// are given their defaults.
+    pub fn load_from_session() -> Self {
+        load::<SessionPreferences>(Session::FILE_NAME)
+            .map(Self::from)
+            .unwrap_or_default()
+    }
+
+    /// Saves these [`Preferences`] so that they can be loaded by a later run of the app.
+    pub fn save(&self) -> io::Result<()> {
+        save(Self::FILE_NAME, self)
+    }
+}
+
+/// The preferences that used to be saved in the [`Session`], as described in
+/// [`Preferences::load_from_session`].
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct SessionPreferences {
+    theme: Theme,
+    highlight: Highlight,
+    show_full_paths: bool,
+    show_details: bool,
+}
+
+impl From<SessionPreferences> for Preferences {
+    fn from(old: SessionPreferences) -> Self {
+        Self {
+            theme: old.theme,
+            highlight: old.highlight,
+            show_full_paths: old.show_full_paths,
+            show_details: old.show_details,
+            ..Self::default()
+        }
+    }
+}
+
+/// The combination of keys that deletes the selected entries in the list.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeleteKey {
+    /// The Delete key on its own.
+    #[default]
+    Plain,
+    /// The Delete key while holding Shift.
+    Shift,
+    /// The Delete key while holding Ctrl, or Cmd on macOS.
+    Command,
+}
+
+impl DeleteKey {
+    /// Every [`DeleteKey`], in the order they should be displayed.
+    pub const ALL: [DeleteKey; 3] = [DeleteKey::Plain, DeleteKey::Shift, DeleteKey::Command];
+
+    /// The modifiers that have to be held with the Delete key.
+    pub fn modifiers(&self) -> Modifiers {
+        match self {
+            DeleteKey::Plain => Modifiers::empty(),
+            DeleteKey::Shift => Modifiers::SHIFT,
+            DeleteKey::Command => Modifiers::COMMAND,
+        }
+    }
+}
+
+impl fmt::Display for DeleteKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DeleteKey::Plain => "Delete",
+            DeleteKey::Shift => "Shift+Delete",
+            DeleteKey::Command if cfg!(target_os = "macos") => "Cmd+Delete",
+            DeleteKey::Command => "Ctrl+Delete",
+        })
+    }
+}
+
+/// A [`Pipeline`] saved under a name, so that it can be reused later.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Preset {
+    /// The name that the [`Preset`] is listed under.
+    pub name: String,
+    /// The saved [`Pipeline`]. Presets used to hold a single rule, which is loaded as a pipeline of just
+    /// that rule.
+    #[serde(alias = "rule")]
+    pub pipeline: Pipeline,
+}
+
+impl Preset {
+    const FILE_NAME: &'static str = "presets.json";
+
+    /// Loads every saved [`Preset`]. If there aren't any, or they can't be read, an empty list is returned
+    /// instead.
+    pub fn load_all() -> Vec<Self> {
+        load(Self::FILE_NAME).unwrap_or_default()
+    }
+
+    /// Saves the given [`Preset`]s, replacing any that were previously saved.
+    pub fn save_all(presets: &[Self]) -> io::Result<()> {
+        save(Self::FILE_NAME, &presets)
+    }
+}
+
+/// A [`Pipeline`] and the settings it's applied with, saved to a file of the user's choosing so that it
+/// can be shared, and applied to other files later.
+///
+/// Plans saved by other versions of the app are loaded as well as they can be: anything that's missing is
+/// given its default, and anything that isn't recognised is ignored.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedPlan {
+    /// The version of the format that the plan was saved in.
+    version: u32,
+    /// The name that the plan was saved under.
+    pub name: String,
+    /// The rules that the plan applies.
+    pub pipeline: Pipeline,
+    /// Whether files are moved into `target_dir` when they're renamed.
+    pub move_to_dir: bool,
+    /// The directory that files are moved into, if they're being moved.
+    pub target_dir: Option<PathBuf>,
+}
+
+impl SavedPlan {
+    /// The version of the format that plans are saved in by this version of the app.
+    const VERSION: u32 = 1;
+
+    /// Creates a [`SavedPlan`] with the given name, for saving the given rules and settings.
+    pub fn new(
+        name: String,
+        pipeline: Pipeline,
+        move_to_dir: bool,
+        target_dir: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            version: Self::VERSION,
+            name,
+            pipeline,
+            move_to_dir,
+            target_dir,
+        }
+    }
+
+    /// Loads the [`SavedPlan`] in the file at the given path.
+    pub fn load_from(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        serde_json::from_reader(BufReader::new(file)).map_err(io::Error::from)
+    }
+
+    /// Saves this [`SavedPlan`] to the file at the given path, replacing it if it already exists.
+    pub fn save_to(&self, path: &Path) -> io::Result<()> {
+        write_json(path, self)
+    }
+
+    /// Whether this plan was saved by a different version of the app, so it might not have loaded
+    /// exactly as it was saved.
+    pub fn is_other_version(&self) -> bool {
+        self.version != Self::VERSION
+    }
+}
+
+/// The files and folders that were most recently opened.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Recent {
+    /// The opened paths, most recent first.
+    pub paths: Vec<PathBuf>,
+    /// The most paths that are remembered.
+    pub limit: usize,
+}
+
+impl Default for Recent {
+    fn default() -> Self {
+        Self {
+            paths: Vec::new(),
+            limit: 10,
+        }
+    }
+}
+
+impl Recent {
+    const FILE_NAME: &'static str = "recent.json";
+
+    /// Loads the [`Recent`] paths saved by a previous run of the app. If there aren't any, or they can't
+    /// be read, an empty [`Recent`] is returned instead. Paths that no longer exist are forgotten, so that
+    /// they don't need checking every time the list is shown.
+    pub fn load() -> Self {
+        let mut recent: Self = load(Self::FILE_NAME).unwrap_or_default();
+        recent.paths.retain(|p| p.exists());
+        recent
+    }
+
+    /// Saves these [`Recent`] paths so that they can be loaded by a later run of the app.
+    pub fn save(&self) -> io::Result<()> {
+        save(Self::FILE_NAME, self)
+    }
+
+    /// Remembers the given paths as the most recently opened, in the order they were opened, so that the
+    /// last of them ends up at the front. Paths that were already remembered are moved rather than listed
+    /// twice. Paths that no longer exist, and any beyond the limit, are forgotten.
+    pub fn add(&mut self, paths: impl IntoIterator<Item = PathBuf>) {
+        let mut added: Vec<_> = paths.into_iter().collect();
+        added.reverse();
+        let mut seen = HashSet::new();
+        added.retain(|p| seen.insert(p.clone()));
+
+        self.paths.retain(|p| !seen.contains(p) && p.exists());
+        self.paths.splice(0..0, added);
+        self.paths.truncate(self.limit);
+    }
+}
+
+/// The directory that all persisted state is kept in, if the platform has one.
+fn config_dir() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "Mass Renamer").map(|dirs| dirs.config_dir().to_path_buf())
+}
+
+fn load<T: for<'de> Deserialize<'de>>(file_name: &str) -> Option<T> {
+    let file = File::open(config_dir()?.join(file_name)).ok()?;
+    serde_json::from_reader(BufReader::new(file)).ok()
+}
+
+fn save<T: Serialize>(file_name: &str, value: &T) -> io::Result<()> {
+    let dir = config_dir().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "no config directory is available")
+    })?;
+    fs::create_dir_all(&dir)?;
+    write_json(&dir.join(file_name), value)
+}
+
+/// Writes the given value to the file at the given path as JSON, replacing it if it already exists.
+///
+/// The value is written to a temporary file beside it first, which is then moved over it, so that the
+/// file is never left half written if writing fails partway through.
+fn write_json<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    let mut temporary_name = path.file_name().map(OsString::from).unwrap_or_default();
+    temporary_name.push(".tmp");
+    let temporary = path.with_file_name(temporary_name);
+
+    let written = File::create(&temporary).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, value)?;
+        writer.flush()
+    });
+    match written.and_then(|()| fs::rename(&temporary, path)) {
+        Ok(()) => Ok(()),
+        Err(error) => {
+            let _ = fs::remove_file(&temporary);
+            Err(error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rename::{CaseMode, Rule};
+
+    /// A pipeline of two rules with most of their settings changed from the defaults.
+    fn pipeline() -> Pipeline {
+        let mut first = Rule::default();
+        first.replace = "$2-$1".to_owned();
+        first.prefix = "new_".to_owned();
+        first.case = CaseMode::Title;
+        first.number = true;
+        first.set_find(r"(\w+) (\w+)".to_owned());
+        first.set_regex(true);
+        first.numbering.base = "photo".to_owned();
+        first.numbering.width = 4;
+        let mut second = Rule::default();
+        second.suffix = "_done".to_owned();
+
+        let mut pipeline = Pipeline::default();
+        pipeline.rules_mut()[0] = first;
+        pipeline.insert(1, second);
+        pipeline
+    }
+
+    #[test]
+    fn presets_survive_a_round_trip() {
+        let presets = vec![
+            Preset {
+                name: "Tidy".to_owned(),
+                pipeline: pipeline(),
+            },
+            Preset {
+                name: "Empty".to_owned(),
+                pipeline: Pipeline::default(),
+            },
+        ];
+
+        let saved = serde_json::to_string(&presets).unwrap();
+        let loaded: Vec<Preset> = serde_json::from_str(&saved).unwrap();
+        assert_eq!(serde_json::to_string(&loaded).unwrap(), saved);
+
+        assert_eq!(loaded[0].name, "Tidy");
+        let rules = loaded[0].pipeline.rules();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].find(), r"(\w+) (\w+)");
+        assert!(rules[0].is_regex());
+        assert_eq!(rules[0].numbering.width, 4);
+        assert_eq!(rules[1].suffix, "_done");
+        assert_eq!(
+            loaded[0].pipeline.apply("hello world.txt", 0, None),
+            "new_Photo0001_done.txt"
+        );
+    }
+
+    #[test]
+    fn presets_of_a_single_rule_are_loaded_as_a_pipeline() {
+        let saved = r#"[{"name": "Old", "rule": {"find": "a", "replace": "b", "prefix": "x"}}]"#;
+        let loaded: Vec<Preset> = serde_json::from_str(saved).unwrap();
+        assert_eq!(loaded[0].name, "Old");
+        let rules = loaded[0].pipeline.rules();
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].find(), "a");
+        assert_eq!(rules[0].replace, "b");
+        assert_eq!(
+            loaded[0].pipeline.apply("banana.txt", 0, None),
+            "xbbnbnb.txt"
+        );
+    }
+
+    #[test]
+    fn recent_paths_are_most_recent_first_without_repeats() {
+        let dir = std::env::temp_dir().join(format!("mass_renamer_recent_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let paths: Vec<_> = ["a", "b", "c", "d"].iter().map(|n| dir.join(n)).collect();
+        for path in &paths {
+            File::create(path).unwrap();
+        }
+
+        let order =
+            |indices: &[usize]| -> Vec<_> { indices.iter().map(|&i| paths[i].clone()).collect() };
+
+        let mut recent = Recent {
+            limit: 3,
+            ..Recent::default()
+        };
+        recent.add([paths[0].clone()]);
+        recent.add([paths[1].clone(), paths[2].clone(), paths[1].clone()]);
+        assert_eq!(recent.paths, order(&[1, 2, 0]));
+
+        // Adding a path that's already there moves it to the front, and the oldest past the limit goes
+        recent.add([paths[0].clone(), paths[3].clone()]);
+        assert_eq!(recent.paths, order(&[3, 0, 1]));
+
+        // Paths that have gone are forgotten
+        std::fs::remove_file(&paths[0]).unwrap();
+        recent.add([paths[2].clone()]);
+        assert_eq!(recent.paths, order(&[2, 3, 1]));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn saved_plans_replace_the_file_in_full() {
+        let dir = std::env::temp_dir().join(format!("mass_renamer_save_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("plan.json");
+        fs::write(&path, "x".repeat(100_000)).unwrap();
+
+        let plan = SavedPlan::new("Photos".to_owned(), pipeline(), false, None);
+        plan.save_to(&path).unwrap();
+        let loaded = SavedPlan::load_from(&path).unwrap();
+        assert_eq!(loaded.name, "Photos");
+        assert_eq!(loaded.pipeline.rules().len(), 2);
+        // Nothing is left behind beside it
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn preferences_are_carried_over_from_old_sessions() {
+        let saved = r#"{
+            "paths": ["a.txt"],
+            "theme": "Dark",
+            "show_full_paths": true,
+            "show_details": true
+        }"#;
+        let old: SessionPreferences = serde_json::from_str(saved).unwrap();
+        let preferences = Preferences::from(old);
+        assert_eq!(preferences.theme, Theme::Dark);
+        assert_eq!(preferences.highlight, Highlight::default());
+        assert!(preferences.show_full_paths);
+        assert!(preferences.show_details);
+        assert_eq!(preferences.confirm_removal_threshold, 10);
+
+        // Sessions saved since still load, without the preferences
+        let session: Session = serde_json::from_str(saved).unwrap();
+        assert_eq!(session.paths, [PathBuf::from("a.txt")]);
+    }
+}
@@ -0,0 +1,217 @@
+//! Comparing names character by character, to show what renaming changes.
+
+/// How a span of characters differs between an old name and a new one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    /// The characters are in both names.
+    Kept,
+    /// The characters are only in the old name.
+    Removed,
+    /// The characters are only in the new name.
+    Inserted,
+}
+
+/// The most characters that [`diff`] compares from each name, not counting the characters that both names
+/// start and end with. Comparing takes time and memory in proportion to the product of the lengths, so
+/// longer names aren't compared at all.
+pub const MAX_DIFF_LENGTH: usize = 512;
+
+/// Finds the fewest characters to remove from `old` and insert into it to make `new`, as spans of
+/// consecutive characters that changed in the same way, in the order they appear. Removed spans come
+/// before the inserted spans that replace them.
+///
+/// The kept, removed and inserted spans together spell out both names: leaving out the inserted spans
+/// gives `old`, and leaving out the removed spans gives `new`.
+///
+/// Characters that both names start or end with are always kept, and only the rest is compared. There
+/// isn't a diff if what's left of either name is longer than [`MAX_DIFF_LENGTH`].
+pub fn diff(old: &str, new: &str) -> Option<Vec<(Change, String)>> {
+    let old: Vec<char> = old.chars().collect();
+    let new: Vec<char> = new.chars().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(o, n)| o == n).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(o, n)| o == n)
+        .count();
+    let (old_middle, new_middle) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+    if old_middle.len() > MAX_DIFF_LENGTH || new_middle.len() > MAX_DIFF_LENGTH {
+        return None;
+    }
+
+    let mut spans: Vec<(Change, String)> = Vec::new();
+    let mut push = |change: Change, c: char| match spans.last_mut() {
+        Some((last, span)) if *last == change => span.push(c),
+        _ => spans.push((change, c.to_string())),
+    };
+
+    for &c in &old[..prefix] {
+        push(Change::Kept, c);
+    }
+    for (change, c) in diff_chars(old_middle, new_middle) {
+        push(change, c);
+    }
+    for &c in &old[old.len() - suffix..] {
+        push(Change::Kept, c);
+    }
+
+    Some(spans)
+}
+
+/// Finds the fewest characters to remove from `old` and insert into it to make `new`, one character at a
+/// time, as [`diff`] describes.
+fn diff_chars(old: &[char], new: &[char]) -> Vec<(Change, char)> {
+    // The length of the longest common subsequence of every pair of suffixes
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            changes.push((Change::Kept, old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lengths[i + 1][j] >= lengths[i][j + 1]) {
+            changes.push((Change::Removed, old[i]));
+            i += 1;
+        } else {
+            changes.push((Change::Inserted, new[j]));
+            j += 1;
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The given name as spelled out by the spans, leaving out the ones that changed the other way.
+    fn spell(spans: &[(Change, String)], skipped: Change) -> String {
+        spans
+            .iter()
+            .filter(|(change, _)| *change != skipped)
+            .map(|(_, span)| span.as_str())
+            .collect()
+    }
+
+    fn spans(old: &str, new: &str) -> Vec<(Change, String)> {
+        let spans = diff(old, new).unwrap();
+        assert_eq!(spell(&spans, Change::Inserted), old);
+        assert_eq!(spell(&spans, Change::Removed), new);
+        spans
+    }
+
+    fn span(change: Change, text: &str) -> (Change, String) {
+        (change, text.to_owned())
+    }
+
+    #[test]
+    fn identical_names_are_kept() {
+        assert_eq!(
+            spans("name.txt", "name.txt"),
+            [span(Change::Kept, "name.txt")]
+        );
+        assert!(spans("", "").is_empty());
+    }
+
+    #[test]
+    fn changes_in_the_middle_keep_both_ends() {
+        assert_eq!(
+            spans("photo_old_1.jpg", "photo_new_1.jpg"),
+            [
+                span(Change::Kept, "photo_"),
+                span(Change::Removed, "old"),
+                span(Change::Inserted, "new"),
+                span(Change::Kept, "_1.jpg"),
+            ]
+        );
+    }
+
+    #[test]
+    fn insertions_and_removals_at_the_ends() {
+        assert_eq!(
+            spans("name.txt", "old name.txt"),
+            [
+                span(Change::Inserted, "old "),
+                span(Change::Kept, "name.txt")
+            ]
+        );
+        assert_eq!(
+            spans("name.txt.bak", "name.txt"),
+            [
+                span(Change::Kept, "name.txt"),
+                span(Change::Removed, ".bak")
+            ]
+        );
+        assert_eq!(spans("", "new"), [span(Change::Inserted, "new")]);
+    }
+
+    #[test]
+    fn repeated_characters_are_not_counted_twice() {
+        // The prefix and suffix overlap here, so the suffix has to stop where the prefix ends
+        assert_eq!(
+            spans("aaa", "aa"),
+            [span(Change::Kept, "aa"), span(Change::Removed, "a")]
+        );
+        spans("abab", "ab");
+        spans("ab", "abab");
+    }
+
+    #[test]
+    fn scattered_changes_are_minimal() {
+        assert_eq!(
+            spans("a1b2c", "a3b4c"),
+            [
+                span(Change::Kept, "a"),
+                span(Change::Removed, "1"),
+                span(Change::Inserted, "3"),
+                span(Change::Kept, "b"),
+                span(Change::Removed, "2"),
+                span(Change::Inserted, "4"),
+                span(Change::Kept, "c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn multibyte_characters_are_compared_whole() {
+        assert_eq!(
+            spans("café.txt", "cafe\u{301}.txt"),
+            [
+                span(Change::Kept, "caf"),
+                span(Change::Removed, "é"),
+                span(Change::Inserted, "e\u{301}"),
+                span(Change::Kept, ".txt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn long_names_are_compared_only_where_they_differ() {
+        let long = "a".repeat(MAX_DIFF_LENGTH * 4);
+        let old = format!("{}x{}", long, long);
+        let new = format!("{}yz{}", long, long);
+        assert_eq!(spans(&old, &new).len(), 4);
+
+        let old = format!("x{}", long);
+        let new = format!("y{}x", long);
+        assert_eq!(diff(&old, &new), None);
+        assert_eq!(diff(&long, ""), None);
+    }
+}
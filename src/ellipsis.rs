@@ -0,0 +1,218 @@
+//! Shorten text with an ellipsis to fit the width that it's laid out in.
+//!
+//! An [`Ellipsized`] widget measures its text with the renderer each time it's laid out, and builds its
+//! content from as much of the end of the text as fits, so the text is only shortened when there isn't
+//! room for all of it.
+
+use std::{borrow::Cow, cell::RefCell};
+
+use iced::{mouse, Length, Point, Rectangle};
+use iced_native::{
+    event,
+    layout::{Limits, Node},
+    overlay, renderer,
+    text::Renderer,
+    Clipboard, Element, Event, Layout, Shell, Widget,
+};
+
+/// A widget that shows the end of some text, replacing as much of its start as needed with an ellipsis
+/// for it to fit the available width. Some of the end of the text is always kept whole, even if that
+/// means it doesn't fit.
+///
+/// The content is built from the shortened text by a function, so that it can be styled however is
+/// needed. The text is measured at the renderer's default size and font, so the content should show it
+/// in those.
+pub struct Ellipsized<'a, M, R> {
+    text: &'a str,
+    kept: &'a str,
+    view: Box<dyn Fn(Cow<'a, str>) -> Element<'a, M, R> + 'a>,
+    /// The content built from the text as it was shortened at the last layout.
+    content: RefCell<Option<Element<'a, M, R>>>,
+}
+
+impl<'a, M, R: Renderer> Ellipsized<'a, M, R> {
+    /// Creates an [`Ellipsized`] widget that shows the given text, always keeping `kept` whole at the end
+    /// of it, with content built by the given function from the text as it was shortened. The text is
+    /// only [`Cow::Owned`] when it was shortened.
+    pub fn new(
+        text: &'a str,
+        kept: &'a str,
+        view: impl Fn(Cow<'a, str>) -> Element<'a, M, R> + 'a,
+    ) -> Self {
+        Self {
+            text,
+            kept,
+            view: Box::new(view),
+            content: RefCell::new(None),
+        }
+    }
+}
+
+impl<M, R: Renderer> Widget<M, R> for Ellipsized<'_, M, R> {
+    fn width(&self) -> Length {
+        Length::Fill
+    }
+
+    fn height(&self) -> Length {
+        Length::Shrink
+    }
+
+    fn layout(&self, renderer: &R, limits: &Limits) -> Node {
+        let max_width = limits.width(Length::Fill).max().width;
+        let size = renderer.default_size();
+        let text = ellipsize(self.text, self.kept, |text| {
+            renderer.measure_width(text, size, Default::default()) <= max_width
+        });
+
+        let content = (self.view)(text);
+        let node = content.layout(renderer, limits);
+        *self.content.borrow_mut() = Some(content);
+        node
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut R,
+        style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+    ) {
+        if let Some(content) = &*self.content.borrow() {
+            content.draw(renderer, style, layout, cursor_position, viewport);
+        }
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        renderer: &R,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, M>,
+    ) -> event::Status {
+        match self.content.get_mut() {
+            Some(content) => {
+                content.on_event(event, layout, cursor_position, renderer, clipboard, shell)
+            }
+            None => event::Status::Ignored,
+        }
+    }
+
+    fn mouse_interaction(
+        &self,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+        renderer: &R,
+    ) -> mouse::Interaction {
+        self.content
+            .borrow()
+            .as_ref()
+            .map_or_else(Default::default, |content| {
+                content.mouse_interaction(layout, cursor_position, viewport, renderer)
+            })
+    }
+
+    fn overlay(&mut self, layout: Layout<'_>, renderer: &R) -> Option<overlay::Element<'_, M, R>> {
+        self.content.get_mut().as_mut()?.overlay(layout, renderer)
+    }
+}
+
+impl<'a, M: 'a, R: 'a + Renderer> From<Ellipsized<'a, M, R>> for Element<'a, M, R> {
+    fn from(e: Ellipsized<'a, M, R>) -> Self {
+        Element::new(e)
+    }
+}
+
+/// Shortens the given text by replacing as much of its start with an ellipsis as is needed for it to fit,
+/// as decided by `fits`. The given text at the end, `kept`, is never shortened, even if that means the
+/// text doesn't fit.
+pub fn ellipsize<'a>(text: &'a str, kept: &str, fits: impl Fn(&str) -> bool) -> Cow<'a, str> {
+    if fits(text) {
+        return Cow::Borrowed(text);
+    }
+
+    // The starts of each character that the shortened text could begin at, from the longest to the
+    // shortest, which is searched for the longest that fits
+    let min_start = text.len().saturating_sub(kept.len());
+    let starts: Vec<_> = text
+        .char_indices()
+        .map(|(i, _)| i)
+        .skip(1)
+        .take_while(|&i| i <= min_start)
+        .collect();
+    let shortest = match starts.last() {
+        Some(&start) => start,
+        None => return Cow::Borrowed(text),
+    };
+
+    let shorten = |start: usize| format!("…{}", &text[start..]);
+    let fitting = starts.partition_point(|&start| !fits(&shorten(start)));
+    Cow::Owned(shorten(starts.get(fitting).copied().unwrap_or(shortest)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::Size;
+    use iced_native::{renderer::Null, widget::Space};
+
+    /// Whether the given text is at most the given number of characters long.
+    fn at_most(chars: usize) -> impl Fn(&str) -> bool {
+        move |text| text.chars().count() <= chars
+    }
+
+    #[test]
+    fn text_that_fits_is_kept_whole() {
+        let text = "/home/user/photos/beach.jpg";
+        assert!(matches!(ellipsize(text, "beach.jpg", at_most(27)), Cow::Borrowed(t) if t == text));
+    }
+
+    #[test]
+    fn as_much_of_the_end_as_fits_is_kept() {
+        let text = "/home/user/photos/beach.jpg";
+        assert_eq!(
+            ellipsize(text, "beach.jpg", at_most(20)),
+            "…er/photos/beach.jpg"
+        );
+        assert_eq!(ellipsize(text, "beach.jpg", at_most(12)), "…s/beach.jpg");
+    }
+
+    #[test]
+    fn the_kept_end_is_never_shortened() {
+        let text = "/home/user/photos/a very long name.jpg";
+        assert_eq!(
+            ellipsize(text, "a very long name.jpg", at_most(10)),
+            "…a very long name.jpg"
+        );
+        assert_eq!(
+            ellipsize(text, "/home/user/photos/a very long name.jpg", at_most(10)),
+            text
+        );
+    }
+
+    #[test]
+    fn characters_are_never_split() {
+        let text = "/ホーム/写真/浜辺.jpg";
+        let shortened = ellipsize(text, "浜辺.jpg", at_most(10));
+        assert_eq!(shortened, "…写真/浜辺.jpg");
+    }
+
+    #[test]
+    fn content_is_built_from_the_text_when_laid_out() {
+        let built = RefCell::new(Vec::new());
+        let ellipsized: Ellipsized<(), Null> = Ellipsized::new("/a/b.txt", "b.txt", |text| {
+            built.borrow_mut().push(text.into_owned());
+            Space::with_width(Length::Units(10)).into()
+        });
+        assert!(built.borrow().is_empty());
+
+        let limits = Limits::new(Size::ZERO, Size::new(100.0, 20.0));
+        let node = ellipsized.layout(&Null::new(), &limits);
+        // The null renderer measures everything as taking no space, so nothing is shortened
+        assert_eq!(*built.borrow(), ["/a/b.txt"]);
+        assert_eq!(node.bounds().width, 10.0);
+    }
+}
@@ -0,0 +1,91 @@
+//! Writing the list of files out for review or scripting.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// Writes the given `(path, target)` pairs to the file at the given path.
+///
+/// If the file has a `csv` extension, each pair is written as a row with the path, the current file name
+/// and the file name it would be renamed to. Otherwise, each path is written on its own line.
+pub fn export_list(path: &Path, entries: &[(PathBuf, PathBuf)]) -> io::Result<()> {
+    let is_csv = path
+        .extension()
+        .map_or(false, |e| e.eq_ignore_ascii_case("csv"));
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    if is_csv {
+        writeln!(writer, "path,original_name,new_name")?;
+        for (path, target) in entries {
+            writeln!(
+                writer,
+                "{},{},{}",
+                csv_field(&path.to_string_lossy()),
+                csv_field(&file_name(path)),
+                csv_field(&file_name(target)),
+            )?;
+        }
+    } else {
+        for (path, _) in entries {
+            writeln!(writer, "{}", path.to_string_lossy())?;
+        }
+    }
+
+    writer.flush()
+}
+
+/// Joins the given paths into text for copying to the clipboard, with each path on its own line. If
+/// `full_paths` isn't set, only the file name of each path is included.
+pub fn copy_text<'a>(paths: impl IntoIterator<Item = &'a Path>, full_paths: bool) -> String {
+    paths
+        .into_iter()
+        .map(|path| {
+            if full_paths {
+                path.to_string_lossy()
+            } else {
+                file_name(path)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn file_name(path: &Path) -> std::borrow::Cow<'_, str> {
+    path.file_name().unwrap_or_default().to_string_lossy()
+}
+
+/// Quotes the given CSV field if it contains anything that would otherwise be misread.
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copied_paths_are_joined_by_lines() {
+        let paths = [
+            Path::new("photos/a.jpg"),
+            Path::new("photos/b.jpg"),
+            Path::new("notes.txt"),
+        ];
+        assert_eq!(
+            copy_text(paths, true),
+            "photos/a.jpg\nphotos/b.jpg\nnotes.txt"
+        );
+        assert_eq!(copy_text(paths, false), "a.jpg\nb.jpg\nnotes.txt");
+    }
+
+    #[test]
+    fn copying_one_path_has_no_trailing_newline() {
+        assert_eq!(copy_text([Path::new("photos/a.jpg")], false), "a.jpg");
+        assert_eq!(copy_text([], true), "");
+    }
+}
@@ -1,16 +1,37 @@
 //! Display a vertical list of selectable widgets.
 //!
 //! A [`ListBox`] has some local [`State`].
+//!
+//! Only the elements that are within the visible viewport are drawn or receive mouse and touch events,
+//! so the cost of drawing and clicking stays proportional to the number of visible elements rather than
+//! the total number of elements. Keyboard events still go to every element, and keyboard selection works
+//! regardless of visibility.
+//!
+//! The positions of the elements are remembered from the last layout, sorted from top to bottom, so the
+//! elements within the viewport and the element under the pointer are found by binary search in
+//! `O(log n)` time, rather than by measuring every element. This works for elements of any height, not
+//! only for lists where every element is as tall as the others.
+//!
+//! Drawing a viewport's worth of elements therefore takes the same time however long the list is.
+//! Laying the list out still takes `O(n)` time, as every element is measured, and so does moving the
+//! pointer over it, as every element is visited to skip the ones out of view.
+
+use std::{
+    cell::{Cell, RefCell},
+    ops::Range,
+    time::{Duration, Instant},
+};
 
 use iced::{
     keyboard::{self, KeyCode},
-    mouse, Alignment, Color, Length, Point, Rectangle,
+    mouse, Alignment, Color, Length, Point, Rectangle, Size,
 };
 use iced_native::{
-    event,
+    alignment, event,
     layout::{flex, Limits, Node},
     overlay,
-    renderer::{self, Quad, Renderer},
+    renderer::{self, Quad},
+    text::{self, Renderer},
     touch, Clipboard, Element, Event, Layout, Padding, Shell, Widget,
 };
 
@@ -24,26 +45,72 @@ pub struct ListBox<'a, M, R> {
     height: Length,
     padding: Padding,
     spacing: f32,
+    row_height: Option<f32>,
+    wrap: bool,
     align_items: Alignment,
     children: Vec<Element<'a, M, R>>,
-    on_delete: Box<dyn Fn(Vec<bool>) -> M>,
+    flagged: Vec<bool>,
+    malformed: Vec<bool>,
+    on_delete: Box<dyn Fn(Vec<usize>) -> M>,
+    delete_modifiers: keyboard::Modifiers,
+    on_scroll: Option<Box<dyn Fn(f32) -> M>>,
+    on_reorder: Option<Box<dyn Fn(Vec<usize>) -> M>>,
+    drag_handles: bool,
+    on_activate: Option<Box<dyn Fn(usize) -> M>>,
+    on_edit: Option<Box<dyn Fn(usize) -> M>>,
+    on_copy: Option<Box<dyn Fn(Vec<usize>) -> M>>,
+    on_paste: Option<Box<dyn Fn(String) -> M>>,
+    on_context: Option<Box<dyn Fn(usize, Point) -> M>>,
+    on_remove: Option<Box<dyn Fn(usize) -> M>>,
+    checked: Vec<bool>,
+    on_check: Option<Box<dyn Fn(usize) -> M>>,
+    on_select: Option<Box<dyn Fn(Vec<bool>) -> M>>,
+    empty_text: Option<String>,
 }
 
+/// The longest time between two clicks on the same element for them to count as a double-click.
+const DOUBLE_CLICK_THRESHOLD: Duration = Duration::from_millis(400);
+
+/// The width of the zone at the right of each element that holds its remove button.
+const REMOVE_ZONE_WIDTH: u16 = 24;
+
+/// The width of the zone at the left of each element that holds its drag handle.
+const HANDLE_ZONE_WIDTH: u16 = 16;
+
+/// The width of each line of a drag handle.
+const HANDLE_LINE_WIDTH: f32 = 8.0;
+
+/// The width of the zone at the left of each element that holds its checkbox.
+const CHECK_ZONE_WIDTH: u16 = 24;
+
+/// The width and height of each checkbox.
+const CHECKBOX_SIZE: f32 = 12.0;
+
 impl<'a, M, R: Renderer> ListBox<'a, M, R> {
     /// Creates an empty [`ListBox`] with the given [`State`], and a function that produces a message when
-    /// the user wants to delete the currently selected elements.
-    pub fn new(state: &'a mut State, on_delete: impl Fn(Vec<bool>) -> M + 'static) -> Self {
+    /// the user wants to delete the currently selected elements. The function is given the indices of
+    /// those elements, in ascending order.
+    pub fn new(state: &'a mut State, on_delete: impl Fn(Vec<usize>) -> M + 'static) -> Self {
         Self::with_children(state, Vec::new(), on_delete)
     }
 
     /// Creates a [`ListBox`] with the given [`State`], child elements and function that produces a message when
-    /// the user wants to delete the currently selected elements.
+    /// the user wants to delete the currently selected elements. The function is given the indices of
+    /// those elements, in ascending order.
+    ///
+    /// The selection of the [`State`] is resized to match the children, so any elements past the end of
+    /// the selection are unselected, and any selection past the last child is dropped.
     pub fn with_children(
         state: &'a mut State,
         children: Vec<Element<'a, M, R>>,
-        on_delete: impl Fn(Vec<bool>) -> M + 'static,
+        on_delete: impl Fn(Vec<usize>) -> M + 'static,
     ) -> Self {
         state.selected_children.resize(children.len(), false);
+        // Forget the most recent selection if its element is gone, such as after a longer selection was
+        // given to `State::set_selection`, so that it's never used out of bounds
+        let len = children.len();
+        state.most_recently_selected = state.most_recently_selected.filter(|&i| i < len);
+        state.cursor = state.cursor.filter(|&i| i < len);
         Self {
             state,
             style: Style::light(false).into(),
@@ -53,9 +120,27 @@ impl<'a, M, R: Renderer> ListBox<'a, M, R> {
             height: Length::Shrink,
             padding: 0.into(),
             spacing: 0.0,
+            row_height: None,
+            wrap: false,
             align_items: Alignment::Start,
             children,
+            flagged: Vec::new(),
+            malformed: Vec::new(),
             on_delete: Box::new(on_delete),
+            delete_modifiers: keyboard::Modifiers::empty(),
+            on_scroll: None,
+            on_reorder: None,
+            drag_handles: false,
+            on_activate: None,
+            on_edit: None,
+            on_copy: None,
+            on_paste: None,
+            on_context: None,
+            on_remove: None,
+            checked: Vec::new(),
+            on_check: None,
+            on_select: None,
+            empty_text: None,
         }
     }
 
@@ -95,9 +180,31 @@ impl<'a, M, R: Renderer> ListBox<'a, M, R> {
         self
     }
 
-    /// Sets the vertical spacing _between_ elements for the contents of the [`ListBox`].
-    pub fn spacing(mut self, units: u16) -> Self {
-        self.spacing = units as f32;
+    /// Sets the vertical spacing _between_ elements for the contents of the [`ListBox`], in pixels. The
+    /// spacing may be fractional, such as for high-DPI displays.
+    pub fn spacing(mut self, spacing: f32) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Sets a fixed height for every element of the [`ListBox`], in pixels, rather than each being as tall
+    /// as its contents. Elements are centred vertically within their rows, and contents taller than the
+    /// row are limited to its height, unless the [`ListBox`] [wraps](Self::wrap).
+    ///
+    /// Fixing the height lets dense lists fit more rows, such as with a small height and no spacing for a
+    /// compact list.
+    pub fn row_height(mut self, row_height: Option<f32>) -> Self {
+        self.row_height = row_height;
+        self
+    }
+
+    /// Sets whether elements can be taller than the [`row_height`](Self::row_height), so that contents
+    /// too long for the width of the [`ListBox`] can wrap onto several lines. Rows that grow are still
+    /// covered by their background and selection.
+    ///
+    /// Without wrapping, contents wider than the [`ListBox`] are cut off at its edges.
+    pub fn wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
         self
     }
 
@@ -107,6 +214,158 @@ impl<'a, M, R: Renderer> ListBox<'a, M, R> {
         self
     }
 
+    /// Sets the text to show in place of the elements when there aren't any. The [`ListBox`] is made at
+    /// least tall enough to fit the text.
+    pub fn empty_text(mut self, empty_text: impl Into<String>) -> Self {
+        self.empty_text = Some(empty_text.into());
+        self
+    }
+
+    /// Marks which elements should be drawn with the [`flagged_background`](Style::flagged_background),
+    /// such as to indicate that something is wrong with them. Elements without a corresponding value are
+    /// not flagged.
+    pub fn flagged(mut self, flagged: Vec<bool>) -> Self {
+        self.flagged = flagged;
+        self
+    }
+
+    /// Marks which elements represent something malformed, and should be drawn with the
+    /// [`malformed_text_color`](Style::malformed_text_color). Elements without a corresponding value are
+    /// not malformed.
+    pub fn malformed(mut self, malformed: Vec<bool>) -> Self {
+        self.malformed = malformed;
+        self
+    }
+
+    /// Sets a function that produces a message when keyboard navigation moves the selection to an element
+    /// that isn't fully visible. The function is given how far the [`ListBox`] should be scrolled to show
+    /// that element, as described in [`State::scroll_offset_to`].
+    pub fn on_scroll(mut self, on_scroll: impl Fn(f32) -> M + 'static) -> Self {
+        self.on_scroll = Some(Box::new(on_scroll));
+        self
+    }
+
+    /// Sets the modifiers that have to be held while pressing Delete for the selected elements to be
+    /// deleted, such as Shift to make deleting them harder to do by accident. Other modifiers can be held
+    /// as well. By default, pressing Delete on its own deletes them.
+    pub fn delete_modifiers(mut self, delete_modifiers: keyboard::Modifiers) -> Self {
+        self.delete_modifiers = delete_modifiers;
+        self
+    }
+
+    /// Sets a function that produces a message when the user drags an element to a new position, which
+    /// enables dragging elements. The function is given the new order of the elements, as the index that
+    /// each element had before it was moved. The selection is moved along with the elements.
+    pub fn on_reorder(mut self, on_reorder: impl Fn(Vec<usize>) -> M + 'static) -> Self {
+        self.on_reorder = Some(Box::new(on_reorder));
+        self
+    }
+
+    /// Sets whether each element has a handle at its left to drag it by, when dragging elements is enabled
+    /// with [`on_reorder`](Self::on_reorder). With handles, elements can only be dragged by their handle,
+    /// so that pressing anywhere else on an element only selects it.
+    ///
+    /// The handles are drawn in a zone to the left of the padding, and of any checkboxes, which widens the
+    /// [`ListBox`] to fit. Without [`on_reorder`](Self::on_reorder), no room is reserved for them.
+    pub fn drag_handles(mut self, drag_handles: bool) -> Self {
+        self.drag_handles = drag_handles;
+        self
+    }
+
+    /// Sets a function that produces a message when the user double-clicks an element, such as to open it.
+    /// The function is given the index of that element.
+    pub fn on_activate(mut self, on_activate: impl Fn(usize) -> M + 'static) -> Self {
+        self.on_activate = Some(Box::new(on_activate));
+        self
+    }
+
+    /// Sets a function that produces a message when the user presses F2 while exactly one element is
+    /// selected, such as to rename it. The function is given the index of that element.
+    pub fn on_edit(mut self, on_edit: impl Fn(usize) -> M + 'static) -> Self {
+        self.on_edit = Some(Box::new(on_edit));
+        self
+    }
+
+    /// Sets a function that produces a message when the user presses Ctrl+C while any elements are
+    /// selected, such as to copy them to the clipboard. The function is given the indices of those
+    /// elements, in ascending order.
+    pub fn on_copy(mut self, on_copy: impl Fn(Vec<usize>) -> M + 'static) -> Self {
+        self.on_copy = Some(Box::new(on_copy));
+        self
+    }
+
+    /// Sets a function that produces a message when the user presses Ctrl+V while the clipboard holds
+    /// some text, such as to add elements from it. The function is given the contents of the clipboard.
+    pub fn on_paste(mut self, on_paste: impl Fn(String) -> M + 'static) -> Self {
+        self.on_paste = Some(Box::new(on_paste));
+        self
+    }
+
+    /// Sets a function that produces a message when the user right-clicks an element, such as to open a
+    /// context menu. The function is given the index of that element and the position of the cursor.
+    ///
+    /// Right-clicking an unselected element selects only that element first, whereas right-clicking a
+    /// selected element keeps the current selection.
+    pub fn on_context(mut self, on_context: impl Fn(usize, Point) -> M + 'static) -> Self {
+        self.on_context = Some(Box::new(on_context));
+        self
+    }
+
+    /// Sets a function that produces a message when the user clicks the remove button of an element, which
+    /// enables those buttons. The function is given the index of that element, and its selection is
+    /// removed along with it.
+    ///
+    /// The buttons are drawn in a zone to the right of the padding, which widens the [`ListBox`] to fit.
+    pub fn on_remove(mut self, on_remove: impl Fn(usize) -> M + 'static) -> Self {
+        self.on_remove = Some(Box::new(on_remove));
+        self
+    }
+
+    /// Shows a checkbox for each element, which is checked if the element at the same index in `checked`
+    /// is true, and sets a function that produces a message when the user clicks one. The function is
+    /// given the index of that element. Clicking a checkbox doesn't change the selection, so that the
+    /// checkboxes can mark elements independently of it.
+    ///
+    /// The checkboxes are drawn in a zone to the left of the padding, which widens the [`ListBox`] to fit.
+    pub fn on_check(mut self, checked: Vec<bool>, on_check: impl Fn(usize) -> M + 'static) -> Self {
+        self.checked = checked;
+        self.on_check = Some(Box::new(on_check));
+        self
+    }
+
+    /// Sets a function that produces a message whenever the selection changes, such as from clicking or
+    /// keyboard navigation. Events that leave the selection as it was, such as clicking an element that
+    /// is already the only one selected, don't produce a message.
+    ///
+    /// The function is given a copy of which elements are now selected, so the message owns it and
+    /// doesn't follow any later changes to the selection.
+    pub fn on_select(mut self, on_select: impl Fn(Vec<bool>) -> M + 'static) -> Self {
+        self.on_select = Some(Box::new(on_select));
+        self
+    }
+
+    /// Selects the elements where the given mask is true, as [`State::set_selection`] does, but only if the
+    /// [`State`] has never been laid out, so that a freshly built list comes up with those elements
+    /// selected while the lists built after it keep whatever the user has selected since.
+    ///
+    /// The mask is resized to match the children, so elements past its end aren't selected, and entries
+    /// past the last child are ignored. Children added after this is called aren't selected.
+    pub fn initial_selection(self, mut mask: Vec<bool>) -> Self {
+        if !self.state.laid_out.get() {
+            mask.resize(self.children.len(), false);
+            self.state.set_selection(mask);
+        }
+        self
+    }
+
+    /// Selects the elements whose indices the given function returns true for, such as every file with a
+    /// certain extension, but only if the [`State`] has never been laid out, as with
+    /// [`initial_selection`](Self::initial_selection).
+    pub fn select_where(self, predicate: impl Fn(usize) -> bool) -> Self {
+        let mask = (0..self.children.len()).map(predicate).collect();
+        self.initial_selection(mask)
+    }
+
     /// Adds an element to the [`ListBox`].
     pub fn push(mut self, child: impl Into<Element<'a, M, R>>) -> Self {
         self.children.push(child.into());
@@ -115,6 +374,363 @@ impl<'a, M, R: Renderer> ListBox<'a, M, R> {
     }
 }
 
+impl<M, R: Renderer> ListBox<'_, M, R> {
+    /// The width of the zone reserved for drag handles, which is zero when they aren't shown.
+    fn handle_width(&self) -> u16 {
+        if self.drag_handles && self.on_reorder.is_some() {
+            HANDLE_ZONE_WIDTH
+        } else {
+            0
+        }
+    }
+
+    /// Handles an event, as described in [`Widget::on_event`].
+    fn handle_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        renderer: &R,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, M>,
+    ) -> event::Status {
+        let bounds = layout.bounds();
+        let viewport = self.state.viewport.get();
+        let is_pointer_event = matches!(event, Event::Mouse(_) | Event::Touch(_));
+        let is_left_press = matches!(
+            event,
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+        );
+        let is_right_press = matches!(
+            event,
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right))
+        );
+        let handle_width = self.handle_width();
+
+        // When the list is shorter than what it's shown in, such as a scrollable, the empty space below it
+        // can be pressed to start a marquee, as otherwise every element covers the whole width of the list
+        let empty_space = viewport.map(|viewport| Rectangle {
+            y: bounds.y + bounds.height,
+            height: (viewport.y + viewport.height - (bounds.y + bounds.height)).max(0.0),
+            ..bounds
+        });
+        let visible = self.state.visible_rows(bounds, viewport);
+        let hovered = self
+            .state
+            .row_at(bounds, cursor_position)
+            .filter(|i| visible.contains(i));
+
+        // With handles, only pressing on a handle starts dragging
+        let starts_drag = is_left_press
+            && self.on_reorder.is_some()
+            && (handle_width == 0
+                || hovered
+                    .and_then(|i| self.state.row(bounds, i))
+                    .map_or(false, |row| {
+                        handle_zone(row, handle_width).contains(cursor_position)
+                    }));
+
+        // Checkboxes and remove buttons take priority over selecting, and are outside of every child
+        if let Some(on_check) = self.on_check.as_ref().filter(|_| is_left_press) {
+            let checked = hovered.filter(|&i| {
+                self.state.row(bounds, i).map_or(false, |row| {
+                    check_zone(row, handle_width).contains(cursor_position)
+                })
+            });
+
+            if let Some(i) = checked {
+                shell.publish(on_check(i));
+                return event::Status::Captured;
+            }
+        }
+
+        if let Some(on_remove) = self.on_remove.as_ref().filter(|_| is_left_press) {
+            let removed = hovered.filter(|&i| {
+                self.state
+                    .row(bounds, i)
+                    .map_or(false, |row| remove_zone(row).contains(cursor_position))
+            });
+
+            if let Some(i) = removed {
+                self.state.remove(i);
+                shell.publish(on_remove(i));
+                return event::Status::Captured;
+            }
+        }
+
+        let mut iter = self
+            .children
+            .iter_mut()
+            .zip(layout.children())
+            .enumerate()
+            .map(|(i, (child, layout))| {
+                // Elements outside of the viewport can't be under the pointer
+                let status = if is_pointer_event && !visible.contains(&i) {
+                    event::Status::Ignored
+                } else {
+                    child.on_event(
+                        event.clone(),
+                        layout,
+                        cursor_position,
+                        renderer,
+                        clipboard,
+                        shell,
+                    )
+                };
+
+                (status, layout)
+            });
+
+        if let Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) = event {
+            self.state.modifiers = modifiers;
+        }
+
+        match event {
+            Event::Keyboard(keyboard::Event::KeyPressed { key_code, .. }) => iter
+                .find_map(|(s, _)| matches!(s, event::Status::Captured).then(|| s))
+                .unwrap_or_else(|| {
+                    if !self.state.is_selected {
+                        return event::Status::Ignored;
+                    }
+
+                    match key_code {
+                        // There's nothing to delete without a selection
+                        KeyCode::Delete
+                            if self.state.selection_count() == 0
+                                || !self.state.modifiers.contains(self.delete_modifiers) =>
+                        {
+                            return event::Status::Ignored
+                        }
+                        KeyCode::Delete => {
+                            shell.publish((self.on_delete)(self.state.remove_selected()));
+                        }
+                        KeyCode::Up
+                        | KeyCode::Down
+                        | KeyCode::Home
+                        | KeyCode::End
+                        | KeyCode::PageUp
+                        | KeyCode::PageDown => {
+                            match key_code {
+                                KeyCode::Up | KeyCode::Down => {
+                                    self.state.select_adjacent(key_code == KeyCode::Down)
+                                }
+                                KeyCode::Home => self.state.select_clamped(0),
+                                KeyCode::End => self.state.select_clamped(usize::MAX),
+                                _ => self.state.select_page(key_code == KeyCode::PageDown),
+                            }
+                            if let Some(on_scroll) = &self.on_scroll {
+                                if let Some(offset) = self
+                                    .state
+                                    .cursor
+                                    .and_then(|i| self.state.scroll_offset_to(i))
+                                {
+                                    shell.publish(on_scroll(offset));
+                                }
+                            }
+                        }
+                        KeyCode::A if self.state.modifiers.command() => self.state.select_all(),
+                        KeyCode::C if self.state.modifiers.command() => {
+                            match (&self.on_copy, self.state.selected_indices()) {
+                                (Some(on_copy), indices) if !indices.is_empty() => {
+                                    shell.publish(on_copy(indices))
+                                }
+                                _ => return event::Status::Ignored,
+                            }
+                        }
+                        KeyCode::V if self.state.modifiers.command() => {
+                            match (&self.on_paste, clipboard.read()) {
+                                (Some(on_paste), Some(contents)) => {
+                                    shell.publish(on_paste(contents))
+                                }
+                                _ => return event::Status::Ignored,
+                            }
+                        }
+                        KeyCode::I if self.state.modifiers.command() => {
+                            self.state.invert_selection()
+                        }
+                        KeyCode::Escape => self.state.deselect_all(),
+                        KeyCode::F2 => match (&self.on_edit, &self.state.selected_indices()[..]) {
+                            (Some(on_edit), &[index]) => shell.publish(on_edit(index)),
+                            _ => return event::Status::Ignored,
+                        },
+                        _ => return event::Status::Ignored,
+                    }
+
+                    event::Status::Captured
+                }),
+            Event::Mouse(mouse::Event::ButtonPressed(_))
+            | Event::Touch(touch::Event::FingerPressed { .. }) => iter
+                .enumerate()
+                .filter_map(|(i, (s, _))| match s {
+                    event::Status::Ignored => (hovered == Some(i)).then(|| Some(i)),
+                    event::Status::Captured => Some(None),
+                })
+                .collect::<Option<Vec<_>>>()
+                .map_or(event::Status::Captured, |v| {
+                    self.state.is_selected = bounds.contains(cursor_position)
+                        || (is_left_press
+                            && empty_space.map_or(false, |s| s.contains(cursor_position)));
+                    let status = v.into_iter().fold(event::Status::Ignored, |_, i| {
+                        if is_right_press {
+                            if !self.state.selected_children[i] {
+                                self.state.select_only(i);
+                            }
+                            if let Some(on_context) = &self.on_context {
+                                shell.publish(on_context(i, cursor_position));
+                            }
+                            return event::Status::Captured;
+                        }
+
+                        self.state.select(i);
+                        if starts_drag {
+                            self.state.drag = Some(Drag { from: i, to: None });
+                        }
+                        if is_left_press {
+                            let now = Instant::now();
+                            if is_double_click(self.state.last_click, i, now) {
+                                // A third click starts counting again rather than being another double-click
+                                self.state.last_click = None;
+                                if let Some(on_activate) = &self.on_activate {
+                                    shell.publish(on_activate(i));
+                                }
+                            } else {
+                                self.state.last_click = Some((i, now));
+                            }
+                        }
+                        event::Status::Captured
+                    });
+
+                    if status == event::Status::Ignored && is_left_press && self.state.is_selected {
+                        // Pressing outside of every element, such as in the padding or the empty space
+                        // below the list, starts selecting with a dragged rectangle
+                        let base = if self.state.modifiers.shift() {
+                            self.state.selected_children.clone()
+                        } else {
+                            vec![false; self.state.selected_children.len()]
+                        };
+                        self.state.replace_selection(base.clone());
+                        self.state.marquee = Some(Marquee {
+                            origin: cursor_position,
+                            current: cursor_position,
+                            base,
+                        });
+                        return event::Status::Captured;
+                    }
+
+                    status
+                }),
+            Event::Mouse(mouse::Event::CursorMoved { .. }) if self.state.marquee.is_some() => {
+                let status = iter
+                    .find_map(|(s, _)| matches!(s, event::Status::Captured).then(|| s))
+                    .unwrap_or(event::Status::Ignored);
+
+                if let Some(marquee) = &mut self.state.marquee {
+                    marquee.current = cursor_position;
+                    let area = marquee.area();
+                    let selection = layout
+                        .children()
+                        .zip(&marquee.base)
+                        .map(|(l, &selected)| {
+                            selected
+                                || selection_bounds(
+                                    self.spacing,
+                                    self.row_height,
+                                    bounds,
+                                    l.bounds(),
+                                )
+                                .intersection(&area)
+                                .is_some()
+                        })
+                        .collect();
+                    self.state.replace_selection(selection);
+                }
+
+                status
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+                if self.state.marquee.is_some() =>
+            {
+                self.state.marquee = None;
+                event::Status::Captured
+            }
+            Event::Mouse(mouse::Event::CursorMoved { .. }) if self.state.drag.is_some() => {
+                let status = iter
+                    .find_map(|(s, _)| matches!(s, event::Status::Captured).then(|| s))
+                    .unwrap_or(event::Status::Ignored);
+
+                if let Some(drag) = &mut self.state.drag {
+                    drag.to = Some(drop_index(layout, cursor_position.y));
+                }
+
+                status
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+                if self.state.drag.is_some() =>
+            {
+                let status = iter
+                    .find_map(|(s, _)| matches!(s, event::Status::Captured).then(|| s))
+                    .unwrap_or(event::Status::Ignored);
+
+                match (self.state.drag.take(), &self.on_reorder) {
+                    // Dropping an element just before or after itself doesn't move it
+                    (Some(Drag { from, to: Some(to) }), Some(on_reorder))
+                        if to != from && to != from + 1 =>
+                    {
+                        shell.publish(on_reorder(self.state.reorder(from, to)));
+                        event::Status::Captured
+                    }
+                    _ => status,
+                }
+            }
+            _ => iter
+                .find_map(|(s, _)| matches!(s, event::Status::Captured).then(|| s))
+                .unwrap_or(event::Status::Ignored),
+        }
+    }
+
+    /// Lays out the elements one after another in rows of the given height, as
+    /// [`flex::resolve`] would lay them out if each were exactly that tall.
+    fn layout_rows(
+        &self,
+        renderer: &R,
+        limits: &Limits,
+        padding: Padding,
+        row_height: f32,
+    ) -> Node {
+        let limits = limits.pad(padding);
+        // Wrapped contents may need more lines than fit in a row, so rows grow to fit them
+        let max_height = if self.wrap { f32::INFINITY } else { row_height };
+        let child_limits = Limits::new(Size::ZERO, Size::new(limits.max().width, max_height));
+        let mut nodes: Vec<_> = self
+            .children
+            .iter()
+            .map(|child| child.layout(renderer, &child_limits))
+            .collect();
+        let heights: Vec<_> = nodes
+            .iter()
+            .map(|n| n.size().height.max(row_height))
+            .collect();
+
+        let count = nodes.len() as f32;
+        let width = nodes.iter().map(|n| n.size().width).fold(0.0, f32::max);
+        let height = heights.iter().sum::<f32>() + (self.spacing * (count - 1.0).max(0.0));
+        let size = limits.resolve(Size::new(width, height));
+
+        let mut y = f32::from(padding.top);
+        for (node, &height) in nodes.iter_mut().zip(&heights) {
+            node.move_to(Point::new(f32::from(padding.left), y));
+            node.align(
+                self.align_items,
+                Alignment::Center,
+                Size::new(size.width, height),
+            );
+            y += height + self.spacing;
+        }
+
+        Node::with_children(size.pad(padding), nodes)
+    }
+}
+
 impl<M, R: Renderer> Widget<M, R> for ListBox<'_, M, R> {
     fn width(&self) -> Length {
         self.width
@@ -125,19 +741,51 @@ impl<M, R: Renderer> Widget<M, R> for ListBox<'_, M, R> {
     }
 
     fn layout(&self, renderer: &R, limits: &Limits) -> Node {
-        flex::resolve(
-            flex::Axis::Vertical,
-            renderer,
-            &limits
-                .max_width(self.max_width)
-                .max_height(self.max_height)
-                .width(self.width)
-                .height(self.height),
-            self.padding,
-            self.spacing,
-            self.align_items,
-            &self.children,
-        )
+        self.state.laid_out.set(true);
+        let mut padding = self.padding;
+        if self.on_remove.is_some() {
+            padding.right += REMOVE_ZONE_WIDTH;
+        }
+        if self.on_check.is_some() {
+            padding.left += CHECK_ZONE_WIDTH;
+        }
+        padding.left += self.handle_width();
+
+        let limits = limits
+            .max_width(self.max_width)
+            .max_height(self.max_height)
+            .width(self.width)
+            .height(self.height);
+
+        // Leave room for the placeholder text, which isn't a child so flex layout doesn't account for it
+        if self.children.is_empty() && self.empty_text.is_some() {
+            self.state.row_bounds.borrow_mut().clear();
+            let height = f32::from(renderer.default_size() + padding.top + padding.bottom);
+            return Node::new(limits.resolve(Size::new(0.0, height)));
+        }
+
+        let node = match self.row_height {
+            Some(row_height) => self.layout_rows(renderer, &limits, padding, row_height),
+            None => flex::resolve(
+                flex::Axis::Vertical,
+                renderer,
+                &limits,
+                padding,
+                self.spacing,
+                self.align_items,
+                &self.children,
+            ),
+        };
+
+        *self.state.row_bounds.borrow_mut() = node
+            .children()
+            .iter()
+            .map(|child| {
+                selection_bounds(self.spacing, self.row_height, node.bounds(), child.bounds())
+            })
+            .collect();
+
+        node
     }
 
     fn draw(
@@ -150,24 +798,67 @@ impl<M, R: Renderer> Widget<M, R> for ListBox<'_, M, R> {
     ) {
         let style = self.style.style();
         let bounds = layout.bounds();
+        let handle_width = self.handle_width();
 
-        // Base background
+        // Base background, outlined more prominently while focused so that it's clear where key presses go
+        let (border_width, border_color) = if self.state.is_selected {
+            (style.border_width.max(1.0), style.focus_border_color)
+        } else {
+            (style.border_width, style.border_color)
+        };
         renderer.fill_quad(
             Quad {
                 bounds,
                 border_radius: 0.0,
-                border_width: 0.0,
-                border_color: Color::TRANSPARENT,
+                border_width,
+                border_color,
             },
             style.background,
         );
 
-        for (i, (child, child_layout)) in self.children.iter().zip(layout.children()).enumerate() {
+        self.state.bounds.set(Some(bounds));
+        self.state.viewport.set(Some(*viewport));
+
+        if let (true, Some(empty_text)) = (self.children.is_empty(), &self.empty_text) {
+            // Placeholder text, dimmed so that it isn't mistaken for an element
+            let color = style.text_color.unwrap_or(renderer_style.text_color);
+            renderer.fill_text(text::Text {
+                content: empty_text,
+                bounds: Rectangle {
+                    x: bounds.center_x(),
+                    y: bounds.center_y(),
+                    ..bounds
+                },
+                size: f32::from(renderer.default_size()),
+                color: Color {
+                    a: color.a * 0.5,
+                    ..color
+                },
+                font: Default::default(),
+                horizontal_alignment: alignment::Horizontal::Center,
+                vertical_alignment: alignment::Vertical::Center,
+            });
+        }
+
+        let visible = self.state.visible_rows(bounds, Some(*viewport));
+        for (i, (child, child_layout)) in self
+            .children
+            .iter()
+            .zip(layout.children())
+            .enumerate()
+            .skip(visible.start)
+            .take(visible.len())
+        {
+            let background_bounds =
+                selection_bounds(self.spacing, self.row_height, bounds, child_layout.bounds());
+
             let mut renderer_style = renderer::Style {
                 text_color: style.text_color.unwrap_or(renderer_style.text_color),
             };
 
-            let background_bounds = selection_bounds(self.spacing, bounds, child_layout.bounds());
+            if self.malformed.get(i).copied().unwrap_or(false) {
+                renderer_style.text_color = style.malformed_text_color;
+            }
 
             if self.state.selected_children[i] {
                 // Selected elements
@@ -175,8 +866,8 @@ impl<M, R: Renderer> Widget<M, R> for ListBox<'_, M, R> {
                     Quad {
                         bounds: background_bounds,
                         border_radius: 0.0,
-                        border_width: 0.0,
-                        border_color: Color::TRANSPARENT,
+                        border_width: style.selected_border_width,
+                        border_color: style.selected_border_color,
                     },
                     style.selected_background,
                 );
@@ -184,9 +875,19 @@ impl<M, R: Renderer> Widget<M, R> for ListBox<'_, M, R> {
                 if let Some(colour) = style.selected_text_color {
                     renderer_style.text_color = colour;
                 }
+            } else if self.flagged.get(i).copied().unwrap_or(false) {
+                // Flagged elements
+                renderer.fill_quad(
+                    Quad {
+                        bounds: background_bounds,
+                        border_radius: 0.0,
+                        border_width: 0.0,
+                        border_color: Color::TRANSPARENT,
+                    },
+                    style.flagged_background,
+                );
             } else if let Some(background) = style.stripe_background {
-                // Every second element has the stripe colour
-                if i % 2 == 1 {
+                if style.is_striped(i) {
                     renderer.fill_quad(
                         Quad {
                             bounds: background_bounds,
@@ -199,95 +900,193 @@ impl<M, R: Renderer> Widget<M, R> for ListBox<'_, M, R> {
                 }
             }
 
-            // Children
-            child.draw(
-                renderer,
-                &renderer_style,
-                child_layout,
-                cursor_position,
-                viewport,
-            );
-        }
-    }
+            // Hovered elements, drawn translucently over the stripe or flag so that those still show through
+            if let Some(background) = style.hover_background {
+                if !self.state.selected_children[i] && background_bounds.contains(cursor_position) {
+                    renderer.fill_quad(
+                        Quad {
+                            bounds: background_bounds,
+                            border_radius: 0.0,
+                            border_width: 0.0,
+                            border_color: Color::TRANSPARENT,
+                        },
+                        background,
+                    );
+                }
+            }
 
-    fn on_event(
-        &mut self,
-        event: Event,
-        layout: Layout<'_>,
-        cursor_position: Point,
-        renderer: &R,
-        clipboard: &mut dyn Clipboard,
-        shell: &mut Shell<'_, M>,
-    ) -> event::Status {
-        let mut iter = self
-            .children
-            .iter_mut()
-            .zip(layout.children())
-            .map(|(child, layout)| {
-                (
-                    child.on_event(
-                        event.clone(),
-                        layout,
-                        cursor_position,
+            // Children, cut off at the edges of the list unless they're wrapped to fit inside it
+            if self.wrap {
+                child.draw(
+                    renderer,
+                    &renderer_style,
+                    child_layout,
+                    cursor_position,
+                    viewport,
+                );
+            } else {
+                renderer.with_layer(bounds, |renderer| {
+                    child.draw(
                         renderer,
-                        clipboard,
-                        shell,
-                    ),
-                    layout,
-                )
-            });
-
-        if let Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) = event {
-            self.state.modifiers = modifiers;
-        }
-
-        match event {
-            Event::Keyboard(keyboard::Event::KeyPressed {
-                key_code: KeyCode::Delete,
-                ..
-            }) => {
-                iter.find_map(|(s, _)| matches!(s, event::Status::Captured).then(|| s))
-                    .unwrap_or_else(|| {
-                        if self.state.is_selected {
-                            // Deselect all elements and give the previously selected values to the message
-                            let len = self.state.selected_children.len();
-                            shell.publish((self.on_delete)(std::mem::replace(
-                                &mut self.state.selected_children,
-                                vec![false; len],
-                            )));
-                            event::Status::Captured
-                        } else {
-                            event::Status::Ignored
-                        }
-                    })
+                        &renderer_style,
+                        child_layout,
+                        cursor_position,
+                        viewport,
+                    );
+                });
             }
-            Event::Mouse(mouse::Event::ButtonPressed(_))
-            | Event::Touch(touch::Event::FingerPressed { .. }) => {
-                let bounds = layout.bounds();
-                iter.enumerate()
-                    .filter_map(|(i, (s, l))| match s {
-                        event::Status::Ignored => {
-                            selection_bounds(self.spacing, bounds, l.bounds())
-                                .contains(cursor_position)
-                                .then(|| Some(i))
-                        }
-                        event::Status::Captured => Some(None),
-                    })
-                    .collect::<Option<Vec<_>>>()
-                    .map_or(event::Status::Captured, |v| {
-                        self.state.is_selected = bounds.contains(cursor_position);
-                        v.into_iter().fold(event::Status::Ignored, |_, i| {
-                            self.state.select(i);
-                            event::Status::Captured
-                        })
-                    })
+
+            // Drag handles, drawn as a grip of three short lines
+            if handle_width > 0 {
+                let zone = handle_zone(background_bounds, handle_width);
+                let color = if zone.contains(cursor_position) {
+                    renderer_style.text_color
+                } else {
+                    style.handle_color
+                };
+                for offset in [-3.0, 0.0, 3.0] {
+                    renderer.fill_quad(
+                        Quad {
+                            bounds: Rectangle {
+                                x: zone.center_x() - (HANDLE_LINE_WIDTH * 0.5),
+                                y: zone.center_y() + offset - 0.5,
+                                width: HANDLE_LINE_WIDTH,
+                                height: 1.0,
+                            },
+                            border_radius: 0.0,
+                            border_width: 0.0,
+                            border_color: Color::TRANSPARENT,
+                        },
+                        color,
+                    );
+                }
             }
-            _ => iter
-                .find_map(|(s, _)| matches!(s, event::Status::Captured).then(|| s))
-                .unwrap_or(event::Status::Ignored),
+
+            // Checkboxes
+            if self.on_check.is_some() {
+                let zone = check_zone(background_bounds, handle_width);
+                let checkbox = Rectangle {
+                    x: zone.center_x() - (CHECKBOX_SIZE * 0.5),
+                    y: zone.center_y() - (CHECKBOX_SIZE * 0.5),
+                    width: CHECKBOX_SIZE,
+                    height: CHECKBOX_SIZE,
+                };
+                renderer.fill_quad(
+                    Quad {
+                        bounds: checkbox,
+                        border_radius: 2.0,
+                        border_width: 1.0,
+                        border_color: renderer_style.text_color,
+                    },
+                    Color::TRANSPARENT,
+                );
+                if self.checked.get(i).copied().unwrap_or(false) {
+                    renderer.fill_quad(
+                        Quad {
+                            bounds: Rectangle {
+                                x: checkbox.x + 3.0,
+                                y: checkbox.y + 3.0,
+                                width: CHECKBOX_SIZE - 6.0,
+                                height: CHECKBOX_SIZE - 6.0,
+                            },
+                            border_radius: 1.0,
+                            border_width: 0.0,
+                            border_color: Color::TRANSPARENT,
+                        },
+                        renderer_style.text_color,
+                    );
+                }
+            }
+
+            // Remove buttons
+            if self.on_remove.is_some() {
+                let zone = remove_zone(background_bounds);
+                renderer.fill_text(text::Text {
+                    content: "×",
+                    bounds: Rectangle {
+                        x: zone.center_x(),
+                        y: zone.center_y(),
+                        ..zone
+                    },
+                    size: f32::from(renderer.default_size()),
+                    color: renderer_style.text_color,
+                    font: Default::default(),
+                    horizontal_alignment: alignment::Horizontal::Center,
+                    vertical_alignment: alignment::Vertical::Center,
+                });
+            }
+        }
+
+        if let Some(Drag { to: Some(to), .. }) = self.state.drag {
+            // Drop indicator
+            let y = match layout.children().nth(to) {
+                Some(l) => selection_bounds(self.spacing, self.row_height, bounds, l.bounds()).y,
+                None => layout.children().last().map_or(bounds.y, |l| {
+                    let b = selection_bounds(self.spacing, self.row_height, bounds, l.bounds());
+                    b.y + b.height
+                }),
+            };
+
+            renderer.fill_quad(
+                Quad {
+                    bounds: Rectangle {
+                        x: bounds.x,
+                        y: y - 1.0,
+                        width: bounds.width,
+                        height: 2.0,
+                    },
+                    border_radius: 0.0,
+                    border_width: 0.0,
+                    border_color: Color::TRANSPARENT,
+                },
+                style.drop_indicator_color,
+            );
+        }
+
+        // Marquee
+        if let Some(area) = self
+            .state
+            .marquee
+            .as_ref()
+            .and_then(|m| m.area().intersection(&bounds))
+        {
+            renderer.fill_quad(
+                Quad {
+                    bounds: area,
+                    border_radius: 0.0,
+                    border_width: 1.0,
+                    border_color: style.marquee_color,
+                },
+                Color {
+                    a: style.marquee_color.a * 0.25,
+                    ..style.marquee_color
+                },
+            );
         }
     }
 
+    fn on_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        renderer: &R,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, M>,
+    ) -> event::Status {
+        let generation = self.state.generation;
+
+        let status = self.handle_event(event, layout, cursor_position, renderer, clipboard, shell);
+
+        if let Some(on_select) = &self.on_select {
+            if generation != self.state.generation {
+                shell.publish(on_select(self.state.selected_children.clone()));
+            }
+        }
+
+        status
+    }
+
     fn mouse_interaction(
         &self,
         layout: Layout<'_>,
@@ -295,9 +1094,31 @@ impl<M, R: Renderer> Widget<M, R> for ListBox<'_, M, R> {
         viewport: &Rectangle,
         renderer: &R,
     ) -> mouse::Interaction {
+        if let Some(Drag { to: Some(_), .. }) = self.state.drag {
+            return mouse::Interaction::Grabbing;
+        }
+
+        let visible = self.state.visible_rows(layout.bounds(), Some(*viewport));
+        let handle_width = self.handle_width();
+        if handle_width > 0 {
+            let on_handle = self
+                .state
+                .row_at(layout.bounds(), cursor_position)
+                .filter(|i| visible.contains(i))
+                .and_then(|i| self.state.row(layout.bounds(), i))
+                .map_or(false, |row| {
+                    handle_zone(row, handle_width).contains(cursor_position)
+                });
+            if on_handle {
+                return mouse::Interaction::Grab;
+            }
+        }
+
         self.children
             .iter()
             .zip(layout.children())
+            .skip(visible.start)
+            .take(visible.len())
             .map(|(child, layout)| {
                 child.mouse_interaction(layout, cursor_position, viewport, renderer)
             })
@@ -319,13 +1140,132 @@ impl<'a, M: 'a, R: 'a + Renderer> From<ListBox<'a, M, R>> for Element<'a, M, R>
     }
 }
 
+/// The bounds that an element's selection covers, which spans the full width of the [`ListBox`] and
+/// half of the spacing on either side, so that adjacent bounds meet exactly even with fractional spacing.
+///
+/// With a fixed row height, the selection covers the whole row that the element is centred in, rather
+/// than only the element itself.
+///
+/// The bounds include the zones for drag handles, checkboxes and remove buttons, which are reserved in
+/// the padding rather than taken from the element.
 #[doc(hidden)]
-fn selection_bounds(spacing: f32, bounds: Rectangle, child_bounds: Rectangle) -> Rectangle {
+fn selection_bounds(
+    spacing: f32,
+    row_height: Option<f32>,
+    bounds: Rectangle,
+    child_bounds: Rectangle,
+) -> Rectangle {
+    let (y, height) = match row_height {
+        Some(row_height) => {
+            // Rows that wrap grow past the fixed height to fit their contents
+            let height = child_bounds.height.max(row_height);
+            (child_bounds.center_y() - (height * 0.5), height)
+        }
+        None => (child_bounds.y, child_bounds.height),
+    };
     Rectangle {
         x: bounds.x,
-        y: child_bounds.y - (spacing * 0.5),
+        y: y - (spacing * 0.5),
         width: bounds.width,
-        height: child_bounds.height + spacing,
+        height: height + spacing,
+    }
+}
+
+/// The zone at the left of the given selection bounds that holds the drag handle of an element, which
+/// is the given width.
+fn handle_zone(row: Rectangle, handle_width: u16) -> Rectangle {
+    Rectangle {
+        width: f32::from(handle_width).min(row.width),
+        ..row
+    }
+}
+
+/// The zone at the left of the given selection bounds that holds the checkbox of an element, just to the
+/// right of a drag handle zone of the given width.
+fn check_zone(row: Rectangle, handle_width: u16) -> Rectangle {
+    let offset = f32::from(handle_width).min(row.width);
+    Rectangle {
+        x: row.x + offset,
+        width: f32::from(CHECK_ZONE_WIDTH).min(row.width - offset),
+        ..row
+    }
+}
+
+/// The zone at the right of the given selection bounds that holds the remove button of an element.
+fn remove_zone(row: Rectangle) -> Rectangle {
+    let width = f32::from(REMOVE_ZONE_WIDTH).min(row.width);
+    Rectangle {
+        x: row.x + row.width - width,
+        width,
+        ..row
+    }
+}
+
+#[doc(hidden)]
+fn drop_index(layout: Layout<'_>, y: f32) -> usize {
+    layout
+        .children()
+        .position(|l| {
+            let b = l.bounds();
+            y < b.y + b.height * 0.5
+        })
+        .unwrap_or_else(|| layout.children().count())
+}
+
+/// Given which elements are being removed, finds the index that the element taking the place of the first
+/// removed element will have once they're gone. If nothing comes after the removed elements, this is the
+/// last remaining element instead, and if nothing remains, there isn't one.
+fn next_after_removal(removed: &[bool]) -> Option<usize> {
+    let first = removed.iter().position(|&r| r)?;
+    let remaining = removed.iter().filter(|&&r| !r).count();
+    // Nothing before the first removed element is removed, so it keeps its index
+    Some(first.min(remaining.checked_sub(1)?))
+}
+
+/// A selection of `len` elements where only those from `start` to `end` inclusive are selected. `start`
+/// may be after `end`.
+fn range_mask(len: usize, start: usize, end: usize) -> Vec<bool> {
+    let range = start.min(end)..=start.max(end);
+    (0..len).map(|i| range.contains(&i)).collect()
+}
+
+/// Whether a click on the element at the given index at the given time completes a double-click, given
+/// the previous click.
+fn is_double_click(previous: Option<(usize, Instant)>, index: usize, now: Instant) -> bool {
+    previous.map_or(false, |(i, time)| {
+        i == index && now.saturating_duration_since(time) <= DOUBLE_CLICK_THRESHOLD
+    })
+}
+
+/// An element being dragged to a new position.
+#[derive(Clone, Copy)]
+struct Drag {
+    /// The index of the element being dragged.
+    from: usize,
+    /// The index that the element would be inserted before if it were dropped now, if it has been moved.
+    to: Option<usize>,
+}
+
+/// A rectangle being dragged out to select every element that it touches.
+struct Marquee {
+    /// Where the drag started.
+    origin: Point,
+    /// Where the cursor is now.
+    current: Point,
+    /// The selection from before the drag, which the elements within the rectangle are added to.
+    base: Vec<bool>,
+}
+
+impl Marquee {
+    /// The area between the start of the drag and the cursor. This is always at least a pixel wide and
+    /// tall, so that a perfectly straight drag still touches the elements along it.
+    fn area(&self) -> Rectangle {
+        Rectangle {
+            x: self.origin.x.min(self.current.x),
+            y: self.origin.y.min(self.current.y),
+            width: (self.origin.x - self.current.x).abs().max(1.0),
+            height: (self.origin.y - self.current.y).abs().max(1.0),
+        }
     }
 }
 
@@ -336,6 +1276,20 @@ pub struct State {
     selected_children: Vec<bool>,
     modifiers: keyboard::Modifiers,
     most_recently_selected: Option<usize>,
+    cursor: Option<usize>,
+    bounds: Cell<Option<Rectangle>>,
+    viewport: Cell<Option<Rectangle>>,
+    row_bounds: RefCell<Vec<Rectangle>>,
+    drag: Option<Drag>,
+    /// The index of the most recently clicked element, and when it was clicked.
+    last_click: Option<(usize, Instant)>,
+    marquee: Option<Marquee>,
+    /// Incremented whenever the selection changes, so that changes can be noticed without comparing the
+    /// whole selection.
+    generation: u64,
+    /// Whether a [`ListBox`] with this state has been laid out yet, after which its
+    /// [initial selection](ListBox::initial_selection) no longer applies.
+    laid_out: Cell<bool>,
 }
 
 impl State {
@@ -346,8 +1300,11 @@ impl State {
 
     /// Performs a selection operation according to the given index and current [`Modifiers`].
     ///
-    /// If [`shift`] is pressed, performs a continuous selection - all elements between the given
-    /// index and the most recent selection are selected, and everything else is deselected.
+    /// If [`shift`] and [`command`] are both pressed, extends the selection - all elements between the
+    /// given index and the most recent selection are selected, and everything else remains untouched.
+    ///
+    /// Otherwise, if [`shift`] is pressed, performs a continuous selection - all elements between the
+    /// given index and the most recent selection are selected, and everything else is deselected.
     ///
     /// Otherwise, if [`command`] is pressed, performs a disjoint selection - selection of the element
     /// at the given index is toggled, and everything else remains untouched.
@@ -359,24 +1316,372 @@ impl State {
     /// [`shift`]: keyboard::Modifiers::shift
     /// [`command`]: keyboard::Modifiers::command
     pub fn select(&mut self, index: usize) {
-        if self.modifiers.shift() && self.most_recently_selected.is_some() {
+        let anchor = self
+            .most_recently_selected
+            .filter(|_| self.modifiers.shift());
+        if let Some(i) = anchor.filter(|_| self.modifiers.command()) {
+            // Extending select
+            let range = &mut self.selected_children[index.min(i)..=index.max(i)];
+            if range.contains(&false) {
+                range.fill(true);
+                self.selection_changed();
+            }
+        } else if let Some(i) = anchor {
             // Continuous select
-            let i = self.most_recently_selected.unwrap();
-            let min = index.min(i);
-            let (left, right) = self.selected_children.split_at_mut(min);
-            let (middle, right) = right.split_at_mut((index.max(i) - min) + 1);
-            left.fill(false);
-            middle.fill(true);
-            right.fill(false);
+            self.replace_selection(range_mask(self.selected_children.len(), index, i));
         } else if self.modifiers.command() {
             // Disjoint select
             self.selected_children[index] = !self.selected_children[index];
+            self.selection_changed();
             self.most_recently_selected = Some(index);
         } else {
-            // Single select
+            self.select_only(index);
+        }
+
+        self.cursor = Some(index);
+    }
+
+    /// Selects every element from `start` to `end` inclusive and deselects everything else, as if `start`
+    /// were selected and then `end` were shift-clicked. `start` may be after `end`, and indices past the
+    /// last element are clamped to it.
+    pub fn select_range(&mut self, start: usize, end: usize) {
+        let last = match self.selected_children.len().checked_sub(1) {
+            Some(last) => last,
+            None => return,
+        };
+        let (start, end) = (start.min(last), end.min(last));
+
+        self.replace_selection(range_mask(last + 1, start, end));
+        self.most_recently_selected = Some(end);
+        self.cursor = Some(end);
+    }
+
+    /// Selects only the element at the given index, regardless of the current [`Modifiers`].
+    ///
+    /// [`Modifiers`]: keyboard::Modifiers
+    fn select_only(&mut self, index: usize) {
+        self.replace_selection(range_mask(self.selected_children.len(), index, index));
+        self.most_recently_selected = Some(index);
+        self.cursor = Some(index);
+    }
+
+    /// Returns the indices of every currently selected element, in ascending order.
+    pub fn selected_indices(&self) -> Vec<usize> {
+        self.selected_children
+            .iter()
+            .enumerate()
+            .filter_map(|(i, &selected)| selected.then(|| i))
+            .collect()
+    }
+
+    /// Returns how many elements are currently selected.
+    pub fn selection_count(&self) -> usize {
+        self.selected_children.iter().filter(|&&s| s).count()
+    }
+
+    /// Whether the [`ListBox`] is focused, and so receives keyboard input.
+    pub fn is_focused(&self) -> bool {
+        self.is_selected
+    }
+
+    /// Focuses the [`ListBox`], as if it had been clicked, so that it receives keyboard input.
+    pub fn focus(&mut self) {
+        self.is_selected = true;
+    }
+
+    /// Unfocuses the [`ListBox`], so that it no longer receives keyboard input.
+    pub fn unfocus(&mut self) {
+        self.is_selected = false;
+    }
+
+    /// Describes the [`ListBox`] with the given name and how much of it is selected, such as
+    /// `"File list, 3 of 40 selected"`, for labelling it where the widget itself can't be announced.
+    pub fn description(&self, name: &str) -> String {
+        format!(
+            "{}, {} of {} selected",
+            name,
+            self.selection_count(),
+            self.selected_children.len()
+        )
+    }
+
+    /// Calculates how far the [`ListBox`] should be scrolled for the element at the given index to be fully
+    /// visible, as a fraction of the total distance that it can be scrolled. The first and last elements
+    /// scroll all the way to the start and end respectively.
+    ///
+    /// Returns [`None`] if the element is already fully visible, if the [`ListBox`] doesn't need scrolling,
+    /// or if it hasn't been drawn yet.
+    ///
+    /// When the [`ListBox`] is the content of a [`Scrollable`], the result is suitable for passing to
+    /// [`scrollable::State::snap_to`].
+    ///
+    /// [`Scrollable`]: iced::Scrollable
+    /// [`scrollable::State::snap_to`]: iced::scrollable::State::snap_to
+    pub fn scroll_offset_to(&self, index: usize) -> Option<f32> {
+        let rows = self.row_bounds.borrow();
+        let row = rows.get(index)?;
+        let bounds = self.bounds.get()?;
+        let viewport = self.viewport.get()?;
+
+        let scrollable = bounds.height - viewport.height;
+        if scrollable <= 0.0 {
+            return None;
+        }
+
+        let offset = viewport.y - bounds.y;
+        let target = if index == 0 {
+            0.0
+        } else if index + 1 == rows.len() {
+            scrollable
+        } else if row.y < offset {
+            row.y
+        } else if row.y + row.height > offset + viewport.height {
+            row.y + row.height - viewport.height
+        } else {
+            return None;
+        };
+
+        ((target - offset).abs() > f32::EPSILON).then(|| (target / scrollable).clamp(0.0, 1.0))
+    }
+
+    /// Finds the elements whose selection bounds are at least partly within the given viewport, as of
+    /// the last layout, given the bounds of the [`ListBox`]. Every element is within a missing viewport.
+    fn visible_rows(&self, bounds: Rectangle, viewport: Option<Rectangle>) -> Range<usize> {
+        let rows = self.row_bounds.borrow();
+        let viewport = match viewport {
+            Some(viewport) => viewport,
+            None => return 0..rows.len(),
+        };
+
+        // Rows are sorted from top to bottom without overlapping, so both searches are monotonic
+        let top = viewport.y - bounds.y;
+        let bottom = top + viewport.height;
+        let start = rows.partition_point(|r| r.y + r.height <= top);
+        let end = rows.partition_point(|r| r.y < bottom);
+        start..end.max(start)
+    }
+
+    /// Finds the element whose selection bounds contain the given point, as of the last layout, given
+    /// the bounds of the [`ListBox`].
+    fn row_at(&self, bounds: Rectangle, point: Point) -> Option<usize> {
+        if !bounds.contains(point) {
+            return None;
+        }
+
+        let rows = self.row_bounds.borrow();
+        let y = point.y - bounds.y;
+        let i = rows.partition_point(|r| r.y + r.height <= y);
+        rows.get(i).filter(|r| r.y <= y).map(|_| i)
+    }
+
+    /// The selection bounds of the element at the given index, as of the last layout, given the bounds
+    /// of the [`ListBox`].
+    fn row(&self, bounds: Rectangle, index: usize) -> Option<Rectangle> {
+        self.row_bounds.borrow().get(index).map(|r| Rectangle {
+            x: bounds.x + r.x,
+            y: bounds.y + r.y,
+            ..*r
+        })
+    }
+
+    /// Moves the selection state of the element at `from` to just before the element at `to`, making it
+    /// the most recent selection. Returns the new order of the elements, as the index that each element
+    /// had before it was moved.
+    fn reorder(&mut self, from: usize, to: usize) -> Vec<usize> {
+        let mut order: Vec<_> = (0..self.selected_children.len()).collect();
+        let moved = order.remove(from);
+        let index = if to > from { to - 1 } else { to };
+        order.insert(index, moved);
+
+        self.apply_order(&order);
+        self.most_recently_selected = Some(index);
+        self.cursor = Some(index);
+        order
+    }
+
+    /// Removes the selection state of the element at the given index, keeping the selection of the other
+    /// elements attached to them.
+    fn remove(&mut self, index: usize) {
+        self.remove_indices(&[index]);
+    }
+
+    /// Removes the selection state of the elements at the given indices, as if they had been removed from
+    /// the list some other way than through the [`ListBox`], such as by the app. The selection of the
+    /// other elements stays attached to them, and so does the most recent selection, which shift-clicking
+    /// selects from, unless it was one of the removed elements, in which case it's forgotten. Indices past
+    /// the last element are ignored.
+    pub fn remove_indices(&mut self, indices: &[usize]) {
+        let len = self.selected_children.len();
+        let mut removed = vec![false; len];
+        for &i in indices.iter().filter(|&&i| i < len) {
+            removed[i] = true;
+        }
+
+        // Each remaining element moves back by however many removed elements came before it
+        let remap = |i: Option<usize>| {
+            i.filter(|&i| i < len && !removed[i])
+                .map(|i| i - removed[..i].iter().filter(|&&r| r).count())
+        };
+        self.most_recently_selected = remap(self.most_recently_selected);
+        self.cursor = remap(self.cursor);
+
+        if removed.contains(&true) {
+            let mut removed = removed.into_iter();
+            self.selected_children.retain(|_| !removed.next().unwrap());
+            self.selection_changed();
+        }
+    }
+
+    /// Removes the selection state of every selected element, as if they had been deleted, and returns
+    /// the indices of the elements that were selected, in ascending order. The element that takes the
+    /// place of the first removed element, or the last element if there isn't one, is then selected, so
+    /// that removing elements repeatedly works through the list.
+    pub fn remove_selected(&mut self) -> Vec<usize> {
+        let indices = self.selected_indices();
+        let removed = std::mem::take(&mut self.selected_children);
+        self.selected_children = vec![false; removed.iter().filter(|&&r| !r).count()];
+        if !indices.is_empty() {
+            self.selection_changed();
+        }
+        self.most_recently_selected = None;
+        self.cursor = None;
+
+        if let Some(next) = next_after_removal(&removed) {
+            self.select_only(next);
+        }
+
+        indices
+    }
+
+    /// Rearranges the selection state to follow elements that have been rearranged into the given order,
+    /// given as the index that each element had before being rearranged. The most recent selection is
+    /// forgotten.
+    pub fn apply_order(&mut self, order: &[usize]) {
+        let selection = order.iter().map(|&i| self.selected_children[i]).collect();
+        self.replace_selection(selection);
+        self.most_recently_selected = None;
+        self.cursor = None;
+    }
+
+    /// Replaces the selection with the given mask, where each element is selected if its entry in the mask
+    /// is true. The most recently selected element becomes the last one that's selected in the mask.
+    ///
+    /// The mask should have an entry for every element the [`ListBox`] has when it's next created, which
+    /// may differ from how many it has now, such as when the elements are being filtered or sorted. As when
+    /// the elements change otherwise, if the mask is shorter, the elements past its end are deselected,
+    /// and if it's longer, the entries past the last element are ignored, along with the most recent
+    /// selection if it was one of them.
+    pub fn set_selection(&mut self, mask: Vec<bool>) {
+        self.most_recently_selected = mask.iter().rposition(|&s| s);
+        self.cursor = self.most_recently_selected;
+        self.replace_selection(mask);
+    }
+
+    /// Selects every element.
+    pub fn select_all(&mut self) {
+        if self.selected_children.contains(&false) {
+            self.selected_children.fill(true);
+            self.selection_changed();
+        }
+    }
+
+    /// Toggles the selection of every element, and forgets the most recent selection.
+    pub fn invert_selection(&mut self) {
+        self.selected_children.iter_mut().for_each(|s| *s = !*s);
+        if !self.selected_children.is_empty() {
+            self.selection_changed();
+        }
+        self.most_recently_selected = None;
+        self.cursor = None;
+    }
+
+    /// Deselects every element, and forgets the most recent selection.
+    pub fn deselect_all(&mut self) {
+        if self.selected_children.contains(&true) {
             self.selected_children.fill(false);
-            self.selected_children[index] = true;
-            self.most_recently_selected = Some(index);
+            self.selection_changed();
+        }
+        self.most_recently_selected = None;
+        self.cursor = None;
+    }
+
+    /// Replaces the selection with the given one, noting the change if they differ.
+    fn replace_selection(&mut self, selection: Vec<bool>) {
+        if self.selected_children != selection {
+            self.selected_children = selection;
+            self.selection_changed();
+        }
+    }
+
+    /// Notes that the selection has changed, for [`ListBox::on_select`] to be told about it.
+    fn selection_changed(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Performs a selection operation on the element after (if `forwards` is true) or before the element
+    /// that was most recently passed to [`select`](Self::select), according to the current [`Modifiers`].
+    ///
+    /// If nothing has been selected yet, the first or last element is selected instead. Does nothing if
+    /// there is no element in the given direction.
+    ///
+    /// [`Modifiers`]: keyboard::Modifiers
+    pub fn select_adjacent(&mut self, forwards: bool) {
+        let len = self.selected_children.len();
+        let index = match self.cursor {
+            Some(i) if forwards => (i + 1 < len).then(|| i + 1),
+            Some(i) => i.min(len).checked_sub(1),
+            None if forwards => (len > 0).then(|| 0),
+            None => len.checked_sub(1),
+        };
+
+        if let Some(index) = index {
+            self.select(index);
+        }
+    }
+
+    /// Performs a selection operation on the element a page after (if `forwards` is true) or before the
+    /// element that was most recently passed to [`select`](Self::select), according to the current
+    /// [`Modifiers`]. A page is as many elements as fit within the viewport.
+    ///
+    /// Stops at the first or last element rather than going past it. If nothing has been selected yet,
+    /// the first element is selected instead.
+    ///
+    /// [`Modifiers`]: keyboard::Modifiers
+    pub fn select_page(&mut self, forwards: bool) {
+        let page = self.page_size();
+        self.select_clamped(match self.cursor {
+            Some(i) if forwards => i.saturating_add(page),
+            Some(i) => i.saturating_sub(page),
+            None => 0,
+        });
+    }
+
+    /// Performs a selection operation on the element at the given index, or the last element if the index
+    /// is past it, according to the current [`Modifiers`]. Does nothing if there are no elements.
+    ///
+    /// [`Modifiers`]: keyboard::Modifiers
+    fn select_clamped(&mut self, index: usize) {
+        if let Some(last) = self.selected_children.len().checked_sub(1) {
+            self.select(index.min(last));
+        }
+    }
+
+    /// How many elements fit within the viewport, going by the distance between the first two elements
+    /// as of the last draw. This is always at least one, and is exactly one if there's nothing to go by.
+    fn page_size(&self) -> usize {
+        let rows = self.row_bounds.borrow();
+        let (viewport, first) = match (self.viewport.get(), rows.first()) {
+            (Some(viewport), Some(first)) => (viewport, first),
+            _ => return 1,
+        };
+
+        let stride = rows
+            .get(1)
+            .map_or(first.height, |second| second.y - first.y);
+        if stride > 0.0 {
+            ((viewport.height / stride) as usize).max(1)
+        } else {
+            1
         }
     }
 }
@@ -387,29 +1692,96 @@ pub struct Style {
     pub background: Color,
     /// The background colour for every second child element. If [`None`], uses the base background colour instead.
     pub stripe_background: Option<Color>,
+    /// Whether the stripes start on the first element rather than the second. Elements are counted from
+    /// the first one given to the [`ListBox`], so the stripes follow what's shown after filtering.
+    pub stripe_first: bool,
     /// The background colour for selected elements.
     pub selected_background: Color,
+    /// The width of the outline around selected elements.
+    pub selected_border_width: f32,
+    /// The colour of the outline around selected elements.
+    pub selected_border_color: Color,
+    /// The background colour for the unselected element under the cursor, drawn over its other background.
+    /// If [`None`], hovered elements aren't highlighted.
+    pub hover_background: Option<Color>,
+    /// The background colour for flagged elements. See [`ListBox::flagged`].
+    pub flagged_background: Color,
+    /// The colour of the rectangle dragged out to select elements. Its inside is drawn translucently.
+    pub marquee_color: Color,
+    /// The colour of the line that shows where a dragged element will be dropped.
+    pub drop_indicator_color: Color,
+    /// The colour of the drag handles. See [`ListBox::drag_handles`]. The handle under the cursor is drawn
+    /// in the text colour instead.
+    pub handle_color: Color,
     /// The text colour for unselected elements. If [`None`], uses the parent widget's text colour.
     pub text_color: Option<Color>,
     /// The text colour for selected elements. If [`None`], is the same as the unselected text colour.
     pub selected_text_color: Option<Color>,
+    /// The text colour for unselected malformed elements. See [`ListBox::malformed`].
+    pub malformed_text_color: Color,
+    /// The colour of the border around the whole [`ListBox`].
+    pub border_color: Color,
+    /// The colour of the border around the whole [`ListBox`] while it has focus.
+    pub focus_border_color: Color,
+    /// The width of the border around the whole [`ListBox`]. While it has focus, the border is always at
+    /// least one pixel wide.
+    pub border_width: f32,
 }
 
 impl Style {
-    /// A styling suitable for a light theme.
+    /// Whether the element at the given index is one of the every second elements that have the stripe
+    /// colour, going by [`stripe_first`](Self::stripe_first). This doesn't check that there is a
+    /// [`stripe_background`](Self::stripe_background).
+    fn is_striped(&self, index: usize) -> bool {
+        index % 2 == usize::from(!self.stripe_first)
+    }
+
+    /// A styling suitable for a light theme. If `striped` is true, every second element is given a
+    /// [`stripe_background`](Self::stripe_background).
     pub fn light(striped: bool) -> Self {
         Self {
             background: Color::TRANSPARENT,
             stripe_background: striped.then(|| Color::from_rgb8(0xf5, 0xf5, 0xf5)),
+            stripe_first: false,
             selected_background: Color::from_rgb8(0x30, 0x8e, 0xc9),
+            selected_border_width: 0.0,
+            selected_border_color: Color::TRANSPARENT,
+            hover_background: Some(Color::from_rgba8(0x30, 0x8e, 0xc9, 0.12)),
+            flagged_background: Color::from_rgb8(0xf8, 0xc8, 0xc8),
+            marquee_color: Color::from_rgb8(0x30, 0x8e, 0xc9),
+            drop_indicator_color: Color::from_rgb8(0x30, 0x8e, 0xc9),
+            handle_color: Color::from_rgb8(0xa0, 0xa0, 0xa0),
             text_color: None,
             selected_text_color: Some(Color::WHITE),
+            malformed_text_color: Color::from_rgb8(0xc0, 0x6a, 0x00),
+            border_color: Color::from_rgb8(0xbe, 0xbe, 0xbe),
+            focus_border_color: Color::from_rgb8(0x30, 0x8e, 0xc9),
+            border_width: 0.0,
         }
     }
 
-    /// A styling suitable for a dark theme.
-    pub fn dark(_striped: bool) -> Self {
-        todo!();
+    /// A styling suitable for a dark theme. If `striped` is true, every second element is given a
+    /// [`stripe_background`](Self::stripe_background).
+    pub fn dark(striped: bool) -> Self {
+        Self {
+            background: Color::from_rgb8(0x20, 0x22, 0x25),
+            stripe_background: striped.then(|| Color::from_rgb8(0x2a, 0x2d, 0x31)),
+            stripe_first: false,
+            selected_background: Color::from_rgb8(0x1e, 0x6f, 0xd9),
+            selected_border_width: 0.0,
+            selected_border_color: Color::TRANSPARENT,
+            hover_background: Some(Color::from_rgba8(0xff, 0xff, 0xff, 0.06)),
+            flagged_background: Color::from_rgb8(0x7a, 0x26, 0x2a),
+            marquee_color: Color::from_rgb8(0x5a, 0xa0, 0xf0),
+            drop_indicator_color: Color::from_rgb8(0x5a, 0xa0, 0xf0),
+            handle_color: Color::from_rgb8(0x70, 0x74, 0x79),
+            text_color: Some(Color::from_rgb8(0xe8, 0xe8, 0xe8)),
+            selected_text_color: Some(Color::WHITE),
+            malformed_text_color: Color::from_rgb8(0xf0, 0xa0, 0x30),
+            border_color: Color::from_rgb8(0x5a, 0x5d, 0x61),
+            focus_border_color: Color::from_rgb8(0x5a, 0xa0, 0xf0),
+            border_width: 1.0,
+        }
     }
 }
 
@@ -429,3 +1801,881 @@ impl<'a, T: 'a + StyleSheet> From<T> for Box<dyn StyleSheet + 'a> {
         Box::new(style)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced_native::{clipboard, renderer::Null, widget::Space};
+
+    #[derive(Debug, PartialEq)]
+    enum Message {
+        Deleted(Vec<usize>),
+        Selected(Vec<bool>),
+    }
+
+    /// The given number of elements, each 20 pixels tall.
+    fn rows(count: usize) -> Vec<Element<'static, Message, Null>> {
+        (0..count)
+            .map(|_| Space::new(Length::Units(100), Length::Units(20)).into())
+            .collect()
+    }
+
+    /// Lays out the given list, then sends it the given event with the cursor at the given position.
+    /// Returns whether the event was captured, and the messages that were published.
+    fn send(
+        list: &mut ListBox<Message, Null>,
+        event: Event,
+        cursor_position: Point,
+    ) -> (event::Status, Vec<Message>) {
+        let renderer = Null::new();
+        let node = list.layout(
+            &renderer,
+            &Limits::new(Size::ZERO, Size::new(200.0, 1000.0)),
+        );
+        let mut messages = Vec::new();
+        let status = list.on_event(
+            event,
+            Layout::new(&node),
+            cursor_position,
+            &renderer,
+            &mut clipboard::Null,
+            &mut Shell::new(&mut messages),
+        );
+        (status, messages)
+    }
+
+    /// Presses the given key on the given list, which should be focused.
+    fn press(
+        list: &mut ListBox<Message, Null>,
+        key_code: KeyCode,
+    ) -> (event::Status, Vec<Message>) {
+        let modifiers = list.state.modifiers;
+        let event = Event::Keyboard(keyboard::Event::KeyPressed {
+            key_code,
+            modifiers,
+        });
+        send(list, event, Point::ORIGIN)
+    }
+
+    /// A focused state for the given number of elements, selected where the given mask is true.
+    fn focused(mask: Vec<bool>) -> State {
+        let mut state = State::new();
+        state.set_selection(mask);
+        state.focus();
+        state
+    }
+
+    #[test]
+    fn dark_style_differs_from_light() {
+        let light = Style::light(true);
+        let dark = Style::dark(true);
+        assert_ne!(light.background, dark.background);
+        assert_ne!(light.stripe_background, dark.stripe_background);
+        assert_ne!(light.selected_background, dark.selected_background);
+        assert_ne!(light.text_color, dark.text_color);
+        assert_ne!(light.border_color, dark.border_color);
+    }
+
+    #[test]
+    fn styles_are_only_striped_when_asked() {
+        assert!(Style::light(false).stripe_background.is_none());
+        assert!(Style::light(true).stripe_background.is_some());
+        assert!(Style::dark(false).stripe_background.is_none());
+        assert!(Style::dark(true).stripe_background.is_some());
+    }
+
+    #[test]
+    fn select_all_selects_every_element() {
+        let mut state = focused(vec![true, false, true, false]);
+        state.select_all();
+        assert_eq!(state.selected_children, [true; 4]);
+
+        let mut state = focused(vec![false; 3]);
+        state.select_all();
+        assert_eq!(state.selected_children, [true; 3]);
+    }
+
+    #[test]
+    fn command_a_selects_every_element() {
+        let mut state = focused(vec![false, true, false]);
+        state.modifiers = keyboard::Modifiers::COMMAND;
+        let mut list = ListBox::with_children(&mut state, rows(3), Message::Deleted);
+        let (status, _) = press(&mut list, KeyCode::A);
+        assert_eq!(status, event::Status::Captured);
+        drop(list);
+        assert_eq!(state.selected_children, [true; 3]);
+    }
+
+    #[test]
+    fn selected_indices_and_count_follow_the_selection() {
+        let state = focused(Vec::new());
+        assert!(state.selected_indices().is_empty());
+        assert_eq!(state.selection_count(), 0);
+
+        let state = focused(vec![false, false, false]);
+        assert!(state.selected_indices().is_empty());
+        assert_eq!(state.selection_count(), 0);
+
+        let state = focused(vec![true, false, true, false]);
+        assert_eq!(state.selected_indices(), [0, 2]);
+        assert_eq!(state.selection_count(), 2);
+
+        let state = focused(vec![true, true, true]);
+        assert_eq!(state.selected_indices(), [0, 1, 2]);
+        assert_eq!(state.selection_count(), 3);
+    }
+
+    #[test]
+    fn deselect_all_forgets_the_most_recent_selection() {
+        let mut state = focused(vec![true, false, true]);
+        state.deselect_all();
+        assert_eq!(state.selected_children, [false; 3]);
+        assert_eq!(state.most_recently_selected, None);
+    }
+
+    #[test]
+    fn escape_deselects_every_element() {
+        let mut state = focused(vec![false; 3]);
+        state.select(1);
+        let mut list = ListBox::with_children(&mut state, rows(3), Message::Deleted);
+        let (status, _) = press(&mut list, KeyCode::Escape);
+        assert_eq!(status, event::Status::Captured);
+        drop(list);
+        assert_eq!(state.selected_children, [false; 3]);
+        assert_eq!(state.most_recently_selected, None);
+    }
+
+    /// A list of the given number of elements, laid out, with a viewport 600 pixels tall part of the way
+    /// down it.
+    fn long_list(state: &mut State, len: usize) -> (ListBox<'_, Message, Null>, Node, Rectangle) {
+        let list = ListBox::with_children(state, rows(len), Message::Deleted);
+        let node = list.layout(
+            &Null::new(),
+            &Limits::new(Size::ZERO, Size::new(400.0, f32::INFINITY)),
+        );
+        let viewport = Rectangle {
+            x: 0.0,
+            y: 5000.0,
+            width: 400.0,
+            height: 600.0,
+        };
+        (list, node, viewport)
+    }
+
+    #[test]
+    fn only_elements_in_the_viewport_are_visible() {
+        let mut state = State::new();
+        let (list, node, viewport) = long_list(&mut state, 10_000);
+        list.draw(
+            &mut Null::new(),
+            &renderer::Style::default(),
+            Layout::new(&node),
+            Point::ORIGIN,
+            &viewport,
+        );
+        drop(list);
+        // Each element is 20 pixels tall, so the viewport holds 30 of them
+        assert_eq!(state.visible_rows(node.bounds(), Some(viewport)), 250..280);
+        assert_eq!(
+            state.row_at(node.bounds(), Point::new(10.0, 5010.0)),
+            Some(250)
+        );
+    }
+
+    /// Checks that drawing a long list takes about as long as drawing a short one, as described in the
+    /// module documentation. This depends on timing, so it's only run with `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn responsiveness() {
+        let draw_times: Vec<_> = [1_000, 100_000]
+            .into_iter()
+            .map(|len| {
+                let mut state = State::new();
+                let (list, node, viewport) = long_list(&mut state, len);
+                let start = Instant::now();
+                for _ in 0..100 {
+                    list.draw(
+                        &mut Null::new(),
+                        &renderer::Style::default(),
+                        Layout::new(&node),
+                        Point::new(10.0, 5100.0),
+                        &viewport,
+                    );
+                }
+                start.elapsed()
+            })
+            .collect();
+
+        // A hundred times as many elements would take far longer to draw if they were all visited
+        assert!(
+            draw_times[1] < draw_times[0] * 10,
+            "drawing took {:?} for 1,000 elements but {:?} for 100,000",
+            draw_times[0],
+            draw_times[1]
+        );
+    }
+
+    #[test]
+    fn inverting_twice_restores_the_selection() {
+        let original = vec![true, false, false, true, false];
+        let mut state = focused(original.clone());
+        state.invert_selection();
+        assert_eq!(state.selected_children, [false, true, true, false, true]);
+        assert_eq!(state.most_recently_selected, None);
+        state.invert_selection();
+        assert_eq!(state.selected_children, original);
+    }
+
+    #[test]
+    fn command_i_inverts_the_selection() {
+        let mut state = focused(vec![true, false, true]);
+        state.modifiers = keyboard::Modifiers::COMMAND;
+        let mut list = ListBox::with_children(&mut state, rows(3), Message::Deleted);
+        let (status, _) = press(&mut list, KeyCode::I);
+        assert_eq!(status, event::Status::Captured);
+        drop(list);
+        assert_eq!(state.selected_children, [false, true, false]);
+    }
+
+    #[test]
+    fn focused_borders_stand_out() {
+        for style in [Style::light(true), Style::dark(true)] {
+            assert_ne!(style.focus_border_color, style.border_color);
+        }
+    }
+
+    #[test]
+    fn double_clicks_need_the_same_element_within_the_threshold() {
+        let start = Instant::now();
+        let soon = start + DOUBLE_CLICK_THRESHOLD / 2;
+        let late = start + DOUBLE_CLICK_THRESHOLD + Duration::from_millis(1);
+
+        assert!(is_double_click(Some((2, start)), 2, soon));
+        assert!(is_double_click(
+            Some((2, start)),
+            2,
+            start + DOUBLE_CLICK_THRESHOLD
+        ));
+        assert!(!is_double_click(Some((2, start)), 2, late));
+        assert!(!is_double_click(Some((2, start)), 3, soon));
+        assert!(!is_double_click(None, 2, soon));
+    }
+
+    #[test]
+    fn fractional_spacing_leaves_no_gaps_between_rows() {
+        let mut state = State::new();
+        let list = ListBox::with_children(&mut state, rows(4), Message::Deleted).spacing(1.5);
+        let node = list.layout(
+            &Null::new(),
+            &Limits::new(Size::ZERO, Size::new(200.0, 1000.0)),
+        );
+        drop(list);
+
+        let rows = state.row_bounds.borrow();
+        // The first row starts half a gap above its element, the same as every other row
+        assert_eq!(rows[0].y, -0.75);
+        assert_eq!(node.children()[0].bounds().y, 0.0);
+        for pair in rows.windows(2) {
+            assert_eq!(pair[0].height, 21.5);
+            assert_eq!(pair[0].y + pair[0].height, pair[1].y);
+        }
+    }
+
+    #[test]
+    fn select_range_selects_only_the_range() {
+        let mut state = focused(vec![true, false, false, false, true]);
+        state.select_range(1, 3);
+        assert_eq!(state.selected_children, [false, true, true, true, false]);
+        assert_eq!(state.most_recently_selected, Some(3));
+
+        state.select_range(3, 1);
+        assert_eq!(state.selected_children, [false, true, true, true, false]);
+        assert_eq!(state.most_recently_selected, Some(1));
+    }
+
+    #[test]
+    fn select_range_clamps_to_the_last_element() {
+        let mut state = focused(vec![false; 4]);
+        state.select_range(2, 10);
+        assert_eq!(state.selected_children, [false, false, true, true]);
+        assert_eq!(state.most_recently_selected, Some(3));
+
+        let mut state = focused(Vec::new());
+        state.select_range(0, 3);
+        assert!(state.selected_children.is_empty());
+        assert_eq!(state.most_recently_selected, None);
+    }
+
+    #[test]
+    fn selecting_is_only_published_when_the_selection_changes() {
+        let mut state = focused(vec![true, false, false]);
+        let mut list = ListBox::with_children(&mut state, rows(3), Message::Deleted)
+            .on_select(Message::Selected);
+        let click = Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left));
+        let moved = Event::Mouse(mouse::Event::CursorMoved {
+            position: Point::new(10.0, 30.0),
+        });
+
+        let (_, messages) = send(&mut list, moved, Point::new(10.0, 30.0));
+        assert!(messages.is_empty());
+
+        let (_, messages) = send(&mut list, click.clone(), Point::new(10.0, 30.0));
+        assert_eq!(messages, [Message::Selected(vec![false, true, false])]);
+
+        // Clicking the only selected element again leaves the selection as it was
+        let (_, messages) = send(&mut list, click, Point::new(10.0, 30.0));
+        assert!(messages.is_empty());
+
+        let (_, messages) = press(&mut list, KeyCode::Escape);
+        assert_eq!(messages, [Message::Selected(vec![false; 3])]);
+        let (_, messages) = press(&mut list, KeyCode::Escape);
+        assert!(messages.is_empty());
+    }
+
+    #[test]
+    fn empty_lists_have_a_valid_layout() {
+        for empty_text in [None, Some("No files")] {
+            let mut state = State::new();
+            let mut list =
+                ListBox::with_children(&mut state, Vec::new(), Message::Deleted).padding(5);
+            if let Some(empty_text) = empty_text {
+                list = list.empty_text(empty_text);
+            }
+
+            let node = list.layout(
+                &Null::new(),
+                &Limits::new(Size::ZERO, Size::new(200.0, 1000.0)),
+            );
+            let size = node.size();
+            assert!(node.children().is_empty());
+            assert!(size.width.is_finite() && size.height.is_finite());
+            // There's at least room for the padding, and for the placeholder text if there is any
+            assert!(size.height >= if empty_text.is_some() { 30.0 } else { 10.0 });
+
+            let layout = Layout::new(&node);
+            let viewport = node.bounds();
+            list.draw(
+                &mut Null::new(),
+                &renderer::Style::default(),
+                layout,
+                Point::new(10.0, 10.0),
+                &viewport,
+            );
+            let interaction =
+                list.mouse_interaction(layout, Point::new(10.0, 10.0), &viewport, &Null::new());
+            assert_eq!(interaction, mouse::Interaction::default());
+            drop(list);
+            assert_eq!(state.visible_rows(node.bounds(), Some(viewport)), 0..0);
+        }
+    }
+
+    #[test]
+    fn removal_is_followed_by_the_next_remaining_element() {
+        assert_eq!(next_after_removal(&[false, true, false, false]), Some(1));
+        assert_eq!(
+            next_after_removal(&[true, true, false, true, false]),
+            Some(0)
+        );
+        // Removing the end of the list goes back to the new last element
+        assert_eq!(next_after_removal(&[false, false, true, true]), Some(1));
+        assert_eq!(next_after_removal(&[true, true]), None);
+        assert_eq!(next_after_removal(&[false, false]), None);
+        assert_eq!(next_after_removal(&[]), None);
+    }
+
+    #[test]
+    fn removing_the_selection_selects_what_took_its_place() {
+        let mut state = focused(vec![false, true, true, false, true, false]);
+        assert_eq!(state.remove_selected(), [1, 2, 4]);
+        assert_eq!(state.selected_children, [false, true, false]);
+
+        // Removing repeatedly works through the list, then back from the end of it
+        assert_eq!(state.remove_selected(), [1]);
+        assert_eq!(state.selected_children, [false, true]);
+        assert_eq!(state.remove_selected(), [1]);
+        assert_eq!(state.selected_children, [true]);
+        assert_eq!(state.remove_selected(), [0]);
+        assert!(state.selected_children.is_empty());
+        assert!(state.remove_selected().is_empty());
+    }
+
+    #[test]
+    fn deleting_publishes_the_original_indices() {
+        let mut state = focused(vec![true, false, true, false]);
+        let mut list = ListBox::with_children(&mut state, rows(4), Message::Deleted);
+        let (_, messages) = press(&mut list, KeyCode::Delete);
+        assert_eq!(messages, [Message::Deleted(vec![0, 2])]);
+        drop(list);
+        assert_eq!(state.selected_children, [true, false]);
+
+        // The newly selected element is what shift-clicking selects from
+        state.modifiers = keyboard::Modifiers::SHIFT;
+        state.select(1);
+        assert_eq!(state.selected_children, [true, true]);
+    }
+
+    #[test]
+    fn stripe_parity_flips_with_stripe_first() {
+        let mut style = Style::light(true);
+        let striped = |style: &Style| (0..4).map(|i| style.is_striped(i)).collect::<Vec<_>>();
+        assert_eq!(striped(&style), [false, true, false, true]);
+
+        style.stripe_first = true;
+        assert_eq!(striped(&style), [true, false, true, false]);
+    }
+
+    #[test]
+    fn marquees_start_in_the_empty_space_below_the_list() {
+        let mut state = State::new();
+        state.viewport.set(Some(Rectangle {
+            x: 0.0,
+            y: 0.0,
+            width: 200.0,
+            height: 400.0,
+        }));
+        let mut list = ListBox::with_children(&mut state, rows(4), Message::Deleted);
+        let press = Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left));
+        let (status, _) = send(&mut list, press, Point::new(10.0, 300.0));
+        assert_eq!(status, event::Status::Captured);
+
+        // Dragging up into the third element selects it and the fourth, which is in the way
+        let moved = Event::Mouse(mouse::Event::CursorMoved {
+            position: Point::new(10.0, 50.0),
+        });
+        send(&mut list, moved, Point::new(10.0, 50.0));
+        let release = Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left));
+        send(&mut list, release, Point::new(10.0, 50.0));
+        drop(list);
+        assert!(state.is_focused());
+        assert_eq!(state.selected_children, [false, false, true, true]);
+    }
+
+    #[test]
+    fn pressing_outside_the_viewport_does_not_start_a_marquee() {
+        let mut state = State::new();
+        state.viewport.set(Some(Rectangle {
+            x: 0.0,
+            y: 0.0,
+            width: 200.0,
+            height: 400.0,
+        }));
+        let mut list = ListBox::with_children(&mut state, rows(4), Message::Deleted);
+        let press = Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left));
+        let (status, _) = send(&mut list, press, Point::new(10.0, 500.0));
+        assert_eq!(status, event::Status::Ignored);
+        drop(list);
+        assert!(!state.is_focused());
+        assert!(state.marquee.is_none());
+    }
+
+    #[test]
+    fn pages_are_as_many_elements_as_fit_in_the_viewport() {
+        let mut state = State::new();
+        assert_eq!(state.page_size(), 1);
+
+        let (list, node, viewport) = long_list(&mut state, 100);
+        list.draw(
+            &mut Null::new(),
+            &renderer::Style::default(),
+            Layout::new(&node),
+            Point::ORIGIN,
+            &viewport,
+        );
+        drop(list);
+        // The viewport is 600 pixels tall, and each element 20
+        assert_eq!(state.page_size(), 30);
+
+        // A viewport shorter than an element still moves by one element at a time
+        state.viewport.set(Some(Rectangle {
+            height: 5.0,
+            ..viewport
+        }));
+        assert_eq!(state.page_size(), 1);
+    }
+
+    #[test]
+    fn paging_stops_at_the_first_and_last_elements() {
+        let mut state = focused(vec![false; 100]);
+        state.viewport.set(Some(Rectangle {
+            x: 0.0,
+            y: 0.0,
+            width: 200.0,
+            height: 600.0,
+        }));
+        let mut list = ListBox::with_children(&mut state, rows(100), Message::Deleted);
+        let selected = |list: &ListBox<Message, Null>| list.state.selected_indices();
+
+        // Without a selection, paging starts from the first element
+        press(&mut list, KeyCode::PageDown);
+        assert_eq!(selected(&list), [0]);
+        press(&mut list, KeyCode::PageDown);
+        assert_eq!(selected(&list), [30]);
+        for _ in 0..3 {
+            press(&mut list, KeyCode::PageDown);
+        }
+        assert_eq!(selected(&list), [99]);
+
+        press(&mut list, KeyCode::PageUp);
+        assert_eq!(selected(&list), [69]);
+        for _ in 0..3 {
+            press(&mut list, KeyCode::PageUp);
+        }
+        assert_eq!(selected(&list), [0]);
+
+        press(&mut list, KeyCode::End);
+        assert_eq!(selected(&list), [99]);
+        press(&mut list, KeyCode::Home);
+        assert_eq!(selected(&list), [0]);
+    }
+
+    #[test]
+    fn deleting_nothing_publishes_nothing() {
+        let mut state = focused(vec![false, false, false]);
+        let mut list = ListBox::with_children(&mut state, rows(3), Message::Deleted)
+            .on_select(Message::Selected);
+        let (status, messages) = press(&mut list, KeyCode::Delete);
+        assert_eq!(status, event::Status::Ignored);
+        assert!(messages.is_empty());
+        drop(list);
+        assert_eq!(state.selected_children, [false, false, false]);
+    }
+
+    #[test]
+    fn set_selection_replaces_the_selection() {
+        let mut state = focused(vec![true, true, false, false]);
+        state.set_selection(vec![false, true, false, true]);
+        let list = ListBox::with_children(&mut state, rows(4), Message::Deleted);
+        drop(list);
+        assert_eq!(state.selected_children, [false, true, false, true]);
+        assert_eq!(state.most_recently_selected, Some(3));
+        assert_eq!(state.cursor, Some(3));
+    }
+
+    #[test]
+    fn shorter_selections_deselect_the_rest() {
+        let mut state = focused(vec![true; 4]);
+        state.set_selection(vec![true, false]);
+        let list = ListBox::with_children(&mut state, rows(4), Message::Deleted);
+        drop(list);
+        assert_eq!(state.selected_children, [true, false, false, false]);
+        assert_eq!(state.most_recently_selected, Some(0));
+    }
+
+    #[test]
+    fn longer_selections_ignore_the_extra_entries() {
+        let mut state = focused(vec![false; 3]);
+        state.set_selection(vec![false, true, false, true, true]);
+        let list = ListBox::with_children(&mut state, rows(3), Message::Deleted);
+        drop(list);
+        assert_eq!(state.selected_children, [false, true, false]);
+        assert_eq!(state.most_recently_selected, None);
+        assert_eq!(state.cursor, None);
+    }
+
+    #[test]
+    fn deleting_needs_the_configured_modifiers() {
+        let mut state = focused(vec![false, true, false]);
+        let mut list = ListBox::with_children(&mut state, rows(3), Message::Deleted)
+            .delete_modifiers(keyboard::Modifiers::SHIFT);
+        let (status, messages) = press(&mut list, KeyCode::Delete);
+        assert_eq!(status, event::Status::Ignored);
+        assert!(messages.is_empty());
+
+        let held = |modifiers| Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers));
+        send(&mut list, held(keyboard::Modifiers::CTRL), Point::ORIGIN);
+        assert!(press(&mut list, KeyCode::Delete).1.is_empty());
+
+        // Other modifiers can be held as well
+        let shift_ctrl = keyboard::Modifiers::SHIFT | keyboard::Modifiers::CTRL;
+        send(&mut list, held(shift_ctrl), Point::ORIGIN);
+        let (_, messages) = press(&mut list, KeyCode::Delete);
+        assert_eq!(messages, [Message::Deleted(vec![1])]);
+    }
+
+    /// Selects the given index with the given modifiers held, with the given elements selected beforehand
+    /// and `anchor` as the most recent selection.
+    fn select_from(
+        anchor: usize,
+        mask: Vec<bool>,
+        modifiers: keyboard::Modifiers,
+        index: usize,
+    ) -> State {
+        let mut state = focused(mask);
+        state.most_recently_selected = Some(anchor);
+        state.modifiers = modifiers;
+        state.select(index);
+        state
+    }
+
+    #[test]
+    fn selecting_without_modifiers_selects_only_that_element() {
+        let state = select_from(
+            1,
+            vec![true, true, false, false],
+            keyboard::Modifiers::empty(),
+            3,
+        );
+        assert_eq!(state.selected_indices(), [3]);
+        assert_eq!(state.most_recently_selected, Some(3));
+        assert_eq!(state.cursor, Some(3));
+    }
+
+    #[test]
+    fn selecting_with_command_toggles_only_that_element() {
+        let state = select_from(
+            1,
+            vec![true, true, false, false],
+            keyboard::Modifiers::COMMAND,
+            3,
+        );
+        assert_eq!(state.selected_indices(), [0, 1, 3]);
+        assert_eq!(state.most_recently_selected, Some(3));
+
+        let state = select_from(
+            1,
+            vec![true, true, false, false],
+            keyboard::Modifiers::COMMAND,
+            0,
+        );
+        assert_eq!(state.selected_indices(), [1]);
+        assert_eq!(state.most_recently_selected, Some(0));
+    }
+
+    #[test]
+    fn selecting_with_shift_replaces_the_selection_with_a_range() {
+        let state = select_from(
+            1,
+            vec![true, true, false, false, true],
+            keyboard::Modifiers::SHIFT,
+            3,
+        );
+        assert_eq!(state.selected_indices(), [1, 2, 3]);
+        // The anchor stays put, so that the range can be changed by shift-clicking again
+        assert_eq!(state.most_recently_selected, Some(1));
+        assert_eq!(state.cursor, Some(3));
+    }
+
+    #[test]
+    fn selecting_with_shift_and_command_adds_a_range() {
+        let modifiers = keyboard::Modifiers::SHIFT | keyboard::Modifiers::COMMAND;
+        let state = select_from(3, vec![true, false, false, true, false], modifiers, 1);
+        assert_eq!(state.selected_indices(), [0, 1, 2, 3]);
+        assert_eq!(state.most_recently_selected, Some(3));
+        assert_eq!(state.cursor, Some(1));
+    }
+
+    #[test]
+    fn selecting_with_shift_and_nothing_selected_before() {
+        let mut state = focused(vec![false; 4]);
+        state.modifiers = keyboard::Modifiers::SHIFT;
+        state.select(2);
+        assert_eq!(state.selected_indices(), [2]);
+
+        let mut state = focused(vec![false; 4]);
+        state.modifiers = keyboard::Modifiers::SHIFT | keyboard::Modifiers::COMMAND;
+        state.select(2);
+        assert_eq!(state.selected_indices(), [2]);
+        assert_eq!(state.most_recently_selected, Some(2));
+    }
+
+    /// Removes the given indices from a list of six elements where 1 and 4 are selected and 2 was selected
+    /// most recently, then shift-selects the last element.
+    fn remove_then_extend(indices: &[usize]) -> State {
+        let mut state = focused(vec![false, true, false, false, true, false]);
+        state.most_recently_selected = Some(2);
+        state.remove_indices(indices);
+        state.modifiers = keyboard::Modifiers::SHIFT;
+        let last = state.selected_children.len() - 1;
+        state.select(last);
+        state
+    }
+
+    #[test]
+    fn removing_before_the_anchor_moves_it_back() {
+        let mut state = focused(vec![false, true, false, false, true, false]);
+        state.most_recently_selected = Some(2);
+        state.remove_indices(&[0]);
+        assert_eq!(state.selected_indices(), [0, 3]);
+        assert_eq!(state.most_recently_selected, Some(1));
+
+        let state = remove_then_extend(&[0]);
+        assert_eq!(state.selected_indices(), [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn removing_the_anchor_forgets_it() {
+        let mut state = focused(vec![false, true, false, false, true, false]);
+        state.most_recently_selected = Some(2);
+        state.remove_indices(&[2]);
+        assert_eq!(state.selected_indices(), [1, 3]);
+        assert_eq!(state.most_recently_selected, None);
+
+        // Without an anchor, shift-selecting selects only that element
+        let state = remove_then_extend(&[2]);
+        assert_eq!(state.selected_indices(), [4]);
+    }
+
+    #[test]
+    fn removing_after_the_anchor_leaves_it() {
+        let mut state = focused(vec![false, true, false, false, true, false]);
+        state.most_recently_selected = Some(2);
+        state.remove_indices(&[4, 3]);
+        assert_eq!(state.selected_indices(), [1]);
+        assert_eq!(state.most_recently_selected, Some(2));
+
+        let state = remove_then_extend(&[4, 3]);
+        assert_eq!(state.selected_indices(), [2, 3]);
+    }
+
+    #[test]
+    fn removing_past_the_end_changes_nothing() {
+        let mut state = focused(vec![false, true, false]);
+        let generation = state.generation;
+        state.remove_indices(&[3, 10]);
+        assert_eq!(state.selected_children, [false, true, false]);
+        assert_eq!(state.most_recently_selected, Some(1));
+        assert_eq!(state.generation, generation);
+    }
+
+    /// Builds, lays out and draws a list of the given number of elements once, with the given initial
+    /// selection, then gives the indices that were selected.
+    fn draw_with_initial_selection(state: &mut State, len: usize, mask: Vec<bool>) -> Vec<usize> {
+        let list =
+            ListBox::with_children(state, rows(len), Message::Deleted).initial_selection(mask);
+        let node = list.layout(
+            &Null::new(),
+            &Limits::new(Size::ZERO, Size::new(200.0, 1000.0)),
+        );
+        list.draw(
+            &mut Null::new(),
+            &renderer::Style::default(),
+            Layout::new(&node),
+            Point::ORIGIN,
+            &node.bounds(),
+        );
+        drop(list);
+        state.selected_indices()
+    }
+
+    #[test]
+    fn initial_selections_are_shown_on_the_first_draw() {
+        let mut state = State::new();
+        assert_eq!(
+            draw_with_initial_selection(&mut state, 4, vec![false, true, true, false]),
+            [1, 2]
+        );
+        assert_eq!(state.most_recently_selected, Some(2));
+    }
+
+    #[test]
+    fn initial_selections_only_apply_before_the_first_layout() {
+        let mut state = State::new();
+        draw_with_initial_selection(&mut state, 3, vec![true, false, false]);
+        state.select(2);
+        assert_eq!(
+            draw_with_initial_selection(&mut state, 3, vec![true, true, true]),
+            [2]
+        );
+    }
+
+    #[test]
+    fn initial_selections_are_resized_to_the_children() {
+        let mut state = State::new();
+        assert_eq!(
+            draw_with_initial_selection(&mut state, 2, vec![false, true, true, true]),
+            [1]
+        );
+        assert_eq!(state.selected_children, [false, true]);
+        assert_eq!(state.most_recently_selected, Some(1));
+
+        let mut state = State::new();
+        assert_eq!(draw_with_initial_selection(&mut state, 3, vec![true]), [0]);
+        assert_eq!(state.selected_children, [true, false, false]);
+    }
+
+    #[test]
+    fn predicates_select_the_matching_elements() {
+        let names = ["a.txt", "b.tmp", "c.tmp", "d.txt"];
+        let mut state = State::new();
+        let list = ListBox::with_children(&mut state, rows(names.len()), Message::Deleted)
+            .select_where(|i| names[i].ends_with(".tmp"));
+        drop(list);
+        assert_eq!(state.selected_indices(), [1, 2]);
+    }
+
+    /// An element that's the given number of pixels wide on a single line, and wraps onto another 20 pixel
+    /// line for every time it overflows the width it's given, as wrapped text would.
+    struct Wrapped(f32);
+
+    impl<M> Widget<M, Null> for Wrapped {
+        fn width(&self) -> Length {
+            Length::Shrink
+        }
+
+        fn height(&self) -> Length {
+            Length::Shrink
+        }
+
+        fn layout(&self, _renderer: &Null, limits: &Limits) -> Node {
+            let max = limits.max();
+            let lines = (self.0 / max.width).ceil().max(1.0);
+            Node::new(Size::new(
+                self.0.min(max.width),
+                (lines * 20.0).min(max.height),
+            ))
+        }
+
+        fn draw(
+            &self,
+            _renderer: &mut Null,
+            _style: &renderer::Style,
+            _layout: Layout<'_>,
+            _cursor_position: Point,
+            _viewport: &Rectangle,
+        ) {
+        }
+    }
+
+    /// Lays out a list 100 pixels wide with 20 pixel rows, holding elements that would be the given widths
+    /// on a single line, and gives the bounds of each row.
+    fn narrow_rows(widths: &[f32], wrap: bool) -> (Size, Vec<Rectangle>) {
+        let mut state = State::new();
+        let children = widths
+            .iter()
+            .map(|&width| Element::new(Wrapped(width)))
+            .collect();
+        let list = ListBox::<Message, Null>::with_children(&mut state, children, Message::Deleted)
+            .row_height(Some(20.0))
+            .wrap(wrap);
+        let node = list.layout(
+            &Null::new(),
+            &Limits::new(Size::ZERO, Size::new(100.0, f32::INFINITY)),
+        );
+        let bounds = node.children().iter().map(Node::bounds).collect();
+        (node.size(), bounds)
+    }
+
+    #[test]
+    fn wrapped_rows_grow_to_fit_narrow_lists() {
+        let (size, rows) = narrow_rows(&[50.0, 250.0, 80.0], true);
+        let heights: Vec<_> = rows.iter().map(|r| r.height).collect();
+        let tops: Vec<_> = rows.iter().map(|r| r.y).collect();
+        assert_eq!(heights, [20.0, 60.0, 20.0]);
+        assert_eq!(tops, [0.0, 20.0, 80.0]);
+        assert_eq!(size.height, 100.0);
+        assert!(rows.iter().all(|r| r.width <= 100.0));
+
+        // The selection covers the whole of the taller row
+        let list_bounds = Rectangle::new(Point::ORIGIN, size);
+        let selection = selection_bounds(0.0, Some(20.0), list_bounds, rows[1]);
+        assert_eq!((selection.y, selection.height), (20.0, 60.0));
+    }
+
+    #[test]
+    fn unwrapped_rows_keep_their_height_in_narrow_lists() {
+        let (size, rows) = narrow_rows(&[50.0, 250.0, 80.0], false);
+        let heights: Vec<_> = rows.iter().map(|r| r.height).collect();
+        assert_eq!(heights, [20.0, 20.0, 20.0]);
+        assert_eq!(size.height, 60.0);
+    }
+}
@@ -1,17 +1,40 @@
+mod config;
+mod diff;
+mod ellipsis;
+mod export;
 mod listbox;
+mod plan;
+mod rename;
+mod scan;
+mod style;
+mod template;
 
 use std::{
     borrow::Cow,
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fmt, io,
     path::{Path, PathBuf},
     sync::Arc,
+    time::SystemTime,
 };
 
+use config::{DeleteKey, Preferences, Preset, Recent, SavedPlan, Session};
+use ellipsis::Ellipsized;
 use listbox::ListBox;
+use plan::{Destination, NameWarning, Problem, RenamePlan};
+use rename::{CaseMode, LoadWarning, Pipeline, Rule, SpaceReplacement};
+use style::{Highlight, Theme};
 
+use chrono::{DateTime, Local};
 use iced::{
-    button, container, executor, scrollable, text_input, Application, Button, Color, Column,
-    Command, Container, Element, Length, Scrollable, Settings, Text, TextInput,
+    button, clipboard, executor, keyboard, pick_list, scrollable, text_input, tooltip, window,
+    Alignment, Application, Button, Checkbox, Color, Column, Command, Container, Element, Length,
+    PickList, Point, ProgressBar, Radio, Row, Scrollable, Settings, Subscription, Text, TextInput,
+    Tooltip,
 };
+use iced_native::{event, subscription, Event};
 use native_dialog::FileDialog;
 
 fn main() -> iced::Result {
@@ -20,148 +43,4015 @@ fn main() -> iced::Result {
 
 #[derive(Clone, Debug)]
 enum Message {
+    RuleSelected(usize),
+    AddRule,
+    RemoveRule,
+    MoveRule(bool),
     HighlightInputChanged(String),
+    ReplaceInputChanged(String),
+    TemplateToggled(bool),
+    TemplateChanged(String),
+    RegexToggled(bool),
+    WholeWordToggled(bool),
+    IgnoreCaseToggled(bool),
+    CaseModeChanged(CaseMode),
+    RemoveToggled(bool),
+    RemoveStartChanged(String),
+    RemoveCountChanged(String),
+    StripCounterToggled(bool),
+    TrimToggled(bool),
+    CollapseWhitespaceToggled(bool),
+    SpaceReplacementChanged(SpaceReplacement),
+    PrefixChanged(String),
+    SuffixChanged(String),
+    ChangeExtensionToggled(bool),
+    ExtensionFromChanged(String),
+    ExtensionToChanged(String),
+    NumberingToggled(bool),
+    NumberingBaseChanged(String),
+    NumberingStartChanged(String),
+    NumberingStepChanged(String),
+    NumberingWidthChanged(String),
     FileButtonPressed,
     FilesRecieved(Arc<native_dialog::Result<Vec<PathBuf>>>),
-    FilesDeleted(Vec<bool>),
+    FileDropped(PathBuf),
+    FolderButtonPressed,
+    FolderReceived(Option<PathBuf>),
+    RecentSelected(RecentPath),
+    RecursiveToggled(bool),
+    MoveToDirToggled(bool),
+    RenameDirsToggled(bool),
+    TruncateNamesToggled(bool),
+    ResolveCollisionsToggled(bool),
+    TargetDirButtonPressed,
+    TargetDirReceived(Option<PathBuf>),
+    CreateTargetDir,
+    ExportRequested,
+    ListExported(Option<PathBuf>),
+    ImportList,
+    ListImported(Option<PathBuf>),
+    FilesDeleted(Vec<usize>),
+    RemoveSelectedPressed,
+    EntryRemoved(usize),
+    EntryChecked(usize),
+    CheckAll(bool),
+    ClearAll,
+    ConfirmDelete,
+    CancelDelete,
+    DeleteToTrashToggled(bool),
+    ConfirmTrash,
+    CancelTrash,
+    Trashed(Vec<(PathBuf, Result<(), String>)>),
+    ListScrolled(f32),
+    EntriesReordered(Vec<usize>),
+    SortBy(SortKey),
+    SortDescendingToggled(bool),
+    SortNaturalToggled(bool),
+    ThemeChanged(Theme),
+    HighlightChanged(Highlight),
+    DeleteKeyChanged(DeleteKey),
+    NextMatch,
+    PreviousMatch,
+    FilterChanged(String),
+    FilterCaseToggled(bool),
+    RenameHiddenToggled(bool),
+    SelectionOnlyToggled(bool),
+    SelectionChanged(Vec<bool>),
+    ShowFullPathsToggled(bool),
+    ShortenPathsToggled(bool),
+    ShowDetailsToggled(bool),
+    ShowDiffToggled(bool),
+    CompactRowsToggled(bool),
+    WrapNamesToggled(bool),
+    ConfirmThresholdChanged(String),
+    SettingsToggled,
+    EntryContextMenu(usize, Point),
+    EditEntry(usize),
+    CopySelected(Vec<usize>),
+    Pasted(String),
+    EditChanged(String),
+    CommitEdit,
+    FocusNext(bool),
+    EnterPressed,
+    Dismiss,
+    ContextRemovePressed,
+    ContextMenuClosed,
+    ApplyRename,
+    ConfirmRename,
+    CancelRename,
+    RenameProgress(Arc<Vec<io::Result<()>>>),
+    ScanProgress(u64, Arc<scan::ScanBatch>),
+    Undo,
+    Redo,
+    PresetSelected(String),
+    PresetNameChanged(String),
+    SavePreset,
+    SavePlan,
+    PlanSaved(Option<PathBuf>),
+    LoadPlan,
+    PlanLoaded(Option<PathBuf>),
+}
+
+/// A property of entries that they can be sorted by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SortKey {
+    Name,
+    Extension,
+    Path,
+    Modified,
+}
+
+impl SortKey {
+    const ALL: [SortKey; 4] = [
+        SortKey::Name,
+        SortKey::Extension,
+        SortKey::Path,
+        SortKey::Modified,
+    ];
+}
+
+impl fmt::Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SortKey::Name => "Name",
+            SortKey::Extension => "Extension",
+            SortKey::Path => "Full Path",
+            SortKey::Modified => "Date Modified",
+        })
+    }
+}
+
+/// A button that keyboard focus can be moved to, so that it can be pressed with Enter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FocusableButton {
+    OpenFiles,
+    OpenFolder,
+    Apply,
+    ConfirmRename,
+    CancelRename,
+}
+
+impl FocusableButton {
+    /// The message that pressing this button sends.
+    fn message(self) -> Message {
+        match self {
+            FocusableButton::OpenFiles => Message::FileButtonPressed,
+            FocusableButton::OpenFolder => Message::FolderButtonPressed,
+            FocusableButton::Apply => Message::ApplyRename,
+            FocusableButton::ConfirmRename => Message::ConfirmRename,
+            FocusableButton::CancelRename => Message::CancelRename,
+        }
+    }
+
+    /// The style of this button, which stands out while it has keyboard focus.
+    fn style<'a>(self, theme: Theme, focused: Option<Self>) -> Box<dyn button::StyleSheet + 'a> {
+        if focused == Some(self) {
+            style::FocusedButton(theme).into()
+        } else {
+            theme.into()
+        }
+    }
+}
+
+/// A recently opened file or folder, as listed in the recent menu.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RecentPath(PathBuf);
+
+impl fmt::Display for RecentPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.display().fmt(f)
+    }
 }
 
 struct Entry {
+    path: PathBuf,
     text: String,
     malformed: bool,
+    /// Whether there was nothing at this entry's path when it was added, or it couldn't be accessed.
+    missing: bool,
+    /// The size of the file in bytes, if it could be read when this entry was added.
+    size: Option<u64>,
+    /// When the file was last modified, if it could be read when this entry was added.
+    modified: Option<SystemTime>,
+    /// Whether this entry has been unchecked, so that it's never renamed.
+    excluded: bool,
 }
 
 impl<T: AsRef<Path>> From<T> for Entry {
     fn from(p: T) -> Self {
-        let text = p.as_ref().to_string_lossy();
+        let path = p.as_ref();
+        Self::new(path, std::fs::metadata(path).ok().as_ref())
+    }
+}
+
+impl Entry {
+    /// Creates an entry for the given path, given its metadata if that could be read, such as by a scan.
+    fn new(path: &Path, metadata: Option<&std::fs::Metadata>) -> Self {
+        let text = path.to_string_lossy();
         Self {
             malformed: matches!(text, Cow::Owned(_)),
+            missing: metadata.is_none(),
+            size: metadata.map(|m| m.len()),
+            modified: metadata.and_then(|m| m.modified().ok()),
+            excluded: false,
             text: text.into(),
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// The file name portion of this entry's path, if it is valid UTF-8.
+    fn name(&self) -> Option<&str> {
+        self.path.file_name().and_then(|n| n.to_str())
+    }
+
+    /// Whether this entry can be renamed. Malformed entries are never renamed, as their lossy text can't
+    /// be written back safely, and neither are missing entries, as there's nothing to rename.
+    fn is_renameable(&self) -> bool {
+        !self.malformed && !self.missing
+    }
+
+    /// Computes the path that this entry, at the given position in the list, would be renamed to by the
+    /// given pipeline, wherever the given destination puts it. Entries that can't be renamed keep their
+    /// path.
+    ///
+    /// When directories are renamed, only the find and replace of the pipeline is applied to their names,
+    /// as described in [`Pipeline::replace_matches`].
+    fn target(&self, index: usize, pipeline: &Pipeline, destination: &Destination) -> PathBuf {
+        if !self.is_renameable() {
+            return self.path.clone();
+        }
+
+        let target = pipeline.target(&self.path, index, self.modified);
+        let (parent, name) = match (self.path.parent(), target.file_name()) {
+            (Some(parent), Some(name)) => (parent, name),
+            _ => return target,
+        };
+        match destination {
+            Destination::SameDirectory => target,
+            Destination::Directory(directory) => directory.join(name),
+            Destination::RenamedDirectories(base) => match parent.strip_prefix(base) {
+                Ok(relative) => {
+                    let mut directory = base.clone();
+                    for component in relative.components() {
+                        let component = component.as_os_str();
+                        match component.to_str() {
+                            Some(c) => directory.push(pipeline.replace_matches(c)),
+                            // Names that aren't valid UTF-8 can't be matched against
+                            None => directory.push(component),
+                        }
+                    }
+                    directory.join(name)
+                }
+                Err(_) => target,
+            },
+        }
+    }
+
+    /// Displays this entry according to the given options, highlighting the matches of the given rule and
+    /// previewing the name of the path that it would be renamed to. Entries that won't be renamed because
+    /// they're out of scope don't have a target.
+    fn view(
+        &self,
+        rule: &Rule,
+        target: Option<&Path>,
+        warning: Option<NameWarning>,
+        options: DisplayOptions,
+    ) -> Element<Message> {
+        let name = self.name().unwrap_or_default();
+        let width = if options.wrap {
+            Length::Fill
+        } else {
+            Length::Shrink
+        };
+
+        let ranges: Vec<_> = rule.matches(name).map(|m| m.start..m.end).collect();
+        let show = move |text: Cow<'_, str>| {
+            // Only the file name gets renamed, so only highlight matches within it
+            let offset = text.rfind(name).unwrap_or(text.len());
+            ranges.iter().fold(Text::new(text).width(width), |t, m| {
+                t.highlight(offset + m.start, offset + m.end, options.highlight)
+            })
+        };
+        let original: Element<_> = if !options.full_paths {
+            // Malformed names still need to be shown, even though they can't be matched against
+            show(self.path.file_name().unwrap_or_default().to_string_lossy()).into()
+        } else if options.shorten {
+            // Paths are shortened to the space left beside the preview, with the full path on hover
+            let full_text = &self.text;
+            Ellipsized::new(full_text, name, move |text| {
+                let is_shortened = matches!(text, Cow::Owned(_));
+                let original = show(text);
+                if is_shortened {
+                    Tooltip::new(original, full_text, tooltip::Position::FollowCursor)
+                        .style(options.theme)
+                        .padding(4)
+                        .into()
+                } else {
+                    original.into()
+                }
+            })
+            .into()
+        } else {
+            show(Cow::Borrowed(&self.text)).into()
+        };
+
+        let unchanged = Color::from_rgb8(0xa0, 0xa0, 0xa0);
+        let preview = match target {
+            Some(target) => {
+                // Files being moved into another directory show where they'll end up
+                let is_moved = target.parent() != self.path.parent();
+                let new_name = if is_moved {
+                    target.to_string_lossy()
+                } else {
+                    target
+                        .file_name()
+                        .map_or(Cow::Borrowed(""), |n| n.to_string_lossy())
+                };
+                if new_name == name {
+                    Text::new(format!("→ {}", new_name)).color(unchanged)
+                } else if options.diff {
+                    let old_name = if is_moved {
+                        self.path.to_string_lossy()
+                    } else {
+                        Cow::Borrowed(name)
+                    };
+                    diff_text(&old_name, &new_name, options.theme)
+                } else {
+                    Text::new(format!("→ {}", new_name))
+                }
+            }
+            None => Text::new("(not in scope)").color(unchanged),
+        }
+        .width(width);
+
+        let mut row = Row::new().spacing(8);
+        if !self.is_renameable() {
+            row = row.push(Text::new("⚠"));
+        }
+        if options.details {
+            let size = self.size.map_or_else(|| "—".to_owned(), format_size);
+            let modified = self.modified.map_or_else(
+                || "—".to_owned(),
+                |t| {
+                    DateTime::<Local>::from(t)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                },
+            );
+            row = row
+                .push(Text::new(size).width(Length::Units(72)))
+                .push(Text::new(modified).width(Length::Units(128)));
+        }
+
+        row = row.push(original).push(preview);
+        if plan::CASE_INSENSITIVE && target.map_or(false, |t| plan::is_case_only(&self.path, t)) {
+            row = row.push(Text::new("ⓘ only the case changes").color(unchanged));
+        }
+        if let Some(warning) = warning {
+            row = row.push(Text::new(format!("⚠ {}", warning)).color(warning_color(warning)));
+        }
+        row.into()
+    }
+}
+
+/// An entry being renamed by hand, in place of its row in the list.
+struct Editing {
+    /// The path of the entry being renamed. This is a path rather than an index so that it stays attached
+    /// to the same entry if the list changes while it's being edited.
+    path: PathBuf,
+    value: String,
+    input_state: text_input::State,
+}
+
+impl Editing {
+    /// Starts editing the name of the given entry, with all of it selected.
+    fn new(entry: &Entry) -> Self {
+        let mut input_state = text_input::State::focused();
+        input_state.select_all();
+        Self {
+            path: entry.path.clone(),
+            value: entry.name().unwrap_or_default().to_owned(),
+            input_state,
+        }
+    }
+
+    /// The path that the entry would be renamed to, if the edited name is valid and isn't taken by
+    /// another file.
+    fn target(&self) -> Option<PathBuf> {
+        plan::validate_filename(&self.value).ok()?;
+        let target = self.path.with_file_name(&self.value);
+        (target == self.path || !plan::target_exists(&self.path, &target)).then(|| target)
+    }
+
+    fn view(&mut self, theme: Theme) -> Element<Message> {
+        let is_valid = self.target().is_some();
+        let input = TextInput::new(
+            &mut self.input_state,
+            "New Name...",
+            &self.value,
+            Message::EditChanged,
+        )
+        .on_submit(Message::CommitEdit)
+        .padding(2);
+
+        if is_valid {
+            input.style(theme).into()
+        } else {
+            input.style(style::InvalidInput(theme)).into()
+        }
+    }
+}
+
+/// How entries are displayed in the list.
+#[derive(Clone, Copy)]
+struct DisplayOptions {
+    /// The colour behind the parts of names that match the find text.
+    highlight: Color,
+    /// Whether to show the full path of each entry rather than only its file name.
+    full_paths: bool,
+    /// Whether long full paths have their leading directories replaced with an ellipsis, with the full
+    /// path shown in a tooltip.
+    shorten: bool,
+    /// Whether to show the size and modification time of each entry.
+    details: bool,
+    /// Whether to highlight the characters that renaming would insert and remove, as with [`diff_text`].
+    diff: bool,
+    /// Whether the original and new names share the width of the row, wrapping if they don't fit.
+    wrap: bool,
+    /// The theme that tooltips and changes are styled with.
+    theme: Theme,
+}
+
+/// The colour that the given warning is shown in, which is red for warnings that stop a plan from being
+/// applied, and amber for the rest.
+fn warning_color(warning: NameWarning) -> Color {
+    match warning {
+        NameWarning::EmptyStem => Color::from_rgb8(0xd0, 0x30, 0x30),
+        NameWarning::SharedName => Color::from_rgb8(0xc0, 0x80, 0x00),
+    }
+}
+
+/// Shows the given new name after an arrow, highlighting where it differs from the given old name.
+/// Inserted characters are highlighted in green, and removed characters are shown where they were,
+/// highlighted in red. Names that are too long to compare, as described in [`diff::diff`], are shown
+/// without highlighting.
+fn diff_text(old: &str, new: &str, theme: Theme) -> Text {
+    let spans = match diff::diff(old, new) {
+        Some(spans) => spans,
+        None => return Text::new(format!("→ {}", new)),
+    };
+    let mut content = String::from("→ ");
+    let mut highlights = Vec::new();
+    for (change, span) in spans {
+        let start = content.len();
+        content += &span;
+        match change {
+            diff::Change::Kept => {}
+            diff::Change::Removed => highlights.push((start, content.len(), theme.removed())),
+            diff::Change::Inserted => highlights.push((start, content.len(), theme.inserted())),
+        }
+    }
+
+    highlights
+        .into_iter()
+        .fold(Text::new(content), |text, (start, end, color)| {
+            text.highlight(start, end, color)
+        })
+}
+
+/// Formats the given number of bytes compactly, such as `512 B` or `1.5 MB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KB", "MB", "GB", "TB", "PB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Compares two names in natural order, where runs of digits are compared by their numeric value rather
+/// than digit by digit, so that `file2` comes before `file10`. Names that only differ in the leading
+/// zeroes of their numbers are compared directly, so that they still have a consistent order.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let is_number = |chunk: &str| chunk.starts_with(|c: char| c.is_ascii_digit());
+    let (mut a_chunks, mut b_chunks) = (natural_chunks(a), natural_chunks(b));
+    loop {
+        let ordering = match (a_chunks.next(), b_chunks.next()) {
+            (Some(x), Some(y)) if is_number(x) && is_number(y) => {
+                // Comparing the digits as text avoids overflowing on very long numbers
+                let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+            }
+            (Some(x), Some(y)) => x.cmp(y),
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (None, None) => return a.cmp(b),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Compares two paths a component at a time, as [`Path::cmp`] does, so that the files in a folder stay
+/// together. If `natural` is set, each component is compared in natural order, as with [`natural_cmp`].
+fn compare_paths(a: &Path, b: &Path, natural: bool) -> Ordering {
+    if !natural {
+        return a.cmp(b);
+    }
+
+    let (mut a, mut b) = (a.iter(), b.iter());
+    loop {
+        let ordering = match (a.next(), b.next()) {
+            (Some(x), Some(y)) => natural_cmp(&x.to_string_lossy(), &y.to_string_lossy()),
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (None, None) => return Ordering::Equal,
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Splits the given name into alternating runs of digits and of everything else.
+fn natural_chunks(name: &str) -> impl Iterator<Item = &str> {
+    let mut rest = name;
+    std::iter::from_fn(move || {
+        let is_digit = rest.chars().next()?.is_ascii_digit();
+        let end = rest
+            .find(|c: char| c.is_ascii_digit() != is_digit)
+            .unwrap_or(rest.len());
+        let (chunk, tail) = rest.split_at(end);
+        rest = tail;
+        Some(chunk)
+    })
+}
+
+/// Determines which entries would be renamed onto the same path as another entry, onto a file that
+/// already exists on disk, or to a name that isn't valid, given the path that each entry would be
+/// renamed to.
+///
+/// Whether anything exists at each target is taken from `existing` if it's there, so that the disk is
+/// only checked for targets that changed since the last time. `existing` is then replaced with whether
+/// anything exists at each of these targets.
+fn detect_collisions(
+    entries: &[Entry],
+    targets: &[PathBuf],
+    destination: &Destination,
+    existing: &mut HashMap<PathBuf, bool>,
+) -> Vec<bool> {
+    // Targets are compared as the platform compares names, as described in `plan::collision_key`
+    let keys: Vec<_> = targets.iter().map(|t| plan::collision_key(t)).collect();
+    let mut counts = HashMap::<&Path, usize>::new();
+    for key in &keys {
+        *counts.entry(key).or_default() += 1;
+    }
+
+    // Files that are being renamed away don't collide with whatever's renamed to their old names
+    let moving: HashSet<PathBuf> = entries
+        .iter()
+        .zip(targets)
+        .filter(|(entry, target)| entry.path != **target)
+        .map(|(entry, _)| plan::collision_key(&entry.path))
+        .collect();
+
+    let mut checked = HashMap::new();
+    let collisions = entries
+        .iter()
+        .zip(targets)
+        .zip(&keys)
+        .map(|((entry, target), key)| {
+            if counts[key.as_path()] > 1 {
+                return true;
+            }
+            if *target == entry.path {
+                return false;
+            }
+            let exists = existing
+                .get(target)
+                .copied()
+                .unwrap_or_else(|| target.exists());
+            checked.insert(target.clone(), exists);
+            (plan::is_other_file(&entry.path, target, exists) && !moving.contains(key))
+                || plan::validate_target(&entry.path, target, destination).is_err()
+        })
+        .collect();
+    *existing = checked;
+    collisions
+}
+
+/// Shortens the file name of the given path if it's too long, as described in [`plan::truncate_name`].
+/// Paths whose file name isn't valid UTF-8 are returned unchanged.
+fn truncate_target(path: PathBuf) -> PathBuf {
+    let truncated = path.file_name().and_then(|n| n.to_str()).and_then(|name| {
+        match plan::truncate_name(name) {
+            Cow::Owned(name) => Some(name),
+            Cow::Borrowed(_) => None,
         }
+    });
+    match truncated {
+        Some(name) => path.with_file_name(name),
+        None => path,
+    }
+}
+
+/// A change to the list of entries, recorded so that it can be undone.
+///
+/// Entries are recorded by their paths, so renaming files would leave the recorded changes referring to
+/// paths that no longer exist. Instead, renaming files, or undoing a rename, forgets every recorded
+/// change. This means that every recorded change is newer than the most recent rename, so undoing
+/// undoes changes to the list first, and then the rename.
+enum ListEdit {
+    /// Entries for these paths were added to the end of the list.
+    Added(Vec<PathBuf>),
+    /// Entries for these paths were added to the end of the list by the scan with this token. The scan
+    /// adds to these as it finds more files, so that undoing it removes everything it found, rather than
+    /// only its last batch.
+    Scanned(u64, Vec<PathBuf>),
+    /// These entries were removed, in ascending order of the positions they were removed from.
+    Removed(Vec<RemovedEntry>),
+    /// The entries were rearranged into this order, given as the position each had before.
+    Reordered(Vec<usize>),
+}
+
+/// An entry that was removed from the list, with what's needed to put it back as it was.
+struct RemovedEntry {
+    /// The position that the entry was removed from.
+    index: usize,
+    path: PathBuf,
+    /// Whether the entry was excluded from renaming, as described in [`Entry::excluded`].
+    excluded: bool,
+}
+
+/// The height of each row of the list, in pixels, when it's shown with compact rows.
+const COMPACT_ROW_HEIGHT: f32 = 20.0;
+
+/// How many files are renamed by each task while a plan is being applied.
+const RENAME_CHUNK_SIZE: usize = 64;
+
+/// A plan that's being applied in the background, a chunk of files at a time, so that the window stays
+/// responsive while renaming many files.
+struct Renaming {
+    /// The `(old, new)` paths of every step of the plan, in the order they're applied.
+    renames: Vec<(PathBuf, PathBuf)>,
+    /// The result of each rename that's finished so far.
+    results: Vec<io::Result<()>>,
+    /// The `(old, new)` paths of every file that's been renamed successfully so far.
+    renamed: Vec<(PathBuf, PathBuf)>,
+    /// How many files were left out of the renames because their name wouldn't change.
+    skipped: usize,
+    /// The directories that were created for the renames, parents first.
+    created_directories: Vec<PathBuf>,
+    /// The old directories that the renames move files out of, which are removed if they're left empty.
+    /// See [`RenamePlan::emptied_directories`].
+    emptied_directories: Vec<PathBuf>,
+    /// The original path of each file that a step moves to a temporary name, by that temporary name, so
+    /// that the file can be put back if the step that moves it on from there fails.
+    temporary: HashMap<PathBuf, PathBuf>,
+    /// The position in the list of the entry for each path, as it's moved from one path to the next, so
+    /// that each step can find its entry without searching the list. See [`move_entry`].
+    positions: HashMap<PathBuf, usize>,
+}
+
+impl Renaming {
+    /// Starts renaming the next chunk of files, reporting the results with [`Message::RenameProgress`].
+    fn next_chunk(&self) -> Command<Message> {
+        let start = self.results.len();
+        let end = (start + RENAME_CHUNK_SIZE).min(self.renames.len());
+        let chunk = self.renames[start..end].to_vec();
+        Command::perform(
+            async move {
+                chunk
+                    .iter()
+                    .map(|(old, new)| plan::rename_file(old, new))
+                    .collect::<Vec<_>>()
+            },
+            |results| Message::RenameProgress(Arc::new(results)),
+        )
+    }
+
+    fn is_finished(&self) -> bool {
+        self.results.len() >= self.renames.len()
     }
 }
 
+/// Replaces the entry for the file at `old` with one for `new`, if it's still in the list, finding it by
+/// its position in `positions` and keeping that up to date with the move. The list is only searched if it
+/// has changed since the positions were recorded, so that the entry isn't where it was.
+fn move_entry(
+    entries: &mut [Entry],
+    entry_paths: &mut HashSet<PathBuf>,
+    positions: &mut HashMap<PathBuf, usize>,
+    old: &Path,
+    new: &Path,
+) {
+    let index = match positions.remove(old) {
+        Some(i) if entries.get(i).map_or(false, |e| e.path == old) => Some(i),
+        _ => entries.iter().position(|e| e.path == old),
+    };
+    if let Some(i) = index {
+        entries[i] = Entry::from(new);
+        entry_paths.remove(old);
+        entry_paths.insert(new.to_path_buf());
+        positions.insert(new.to_path_buf(), i);
+    }
+}
+
+/// The position of the entry for each path in the given list.
+fn entry_positions(entries: &[Entry]) -> HashMap<PathBuf, usize> {
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, e)| (e.path.clone(), i))
+        .collect()
+}
+
+/// How many directories are read by each task while a folder is being scanned.
+const SCAN_CHUNK_SIZE: usize = 16;
+
+/// A folder that's being scanned for files in the background, a few directories at a time, so that the
+/// window stays responsive while scanning large or slow folders.
+struct Scanning {
+    /// The folder being scanned.
+    dir: PathBuf,
+    /// Whether the subdirectories of the folder are being scanned too.
+    recursive: bool,
+    /// How many files have been found so far.
+    found: usize,
+}
+
 #[derive(Default)]
 struct App {
     entries: Vec<Entry>,
+    /// The path of every entry, for checking whether a path is already listed without searching them.
+    entry_paths: HashSet<PathBuf>,
+    /// Every rule that's applied to the names of the entries, in order.
+    pipeline: Pipeline,
+    /// The position in the pipeline of the rule being edited.
+    current_rule: usize,
+    add_rule_button_state: button::State,
+    remove_rule_button_state: button::State,
+    move_rule_earlier_button_state: button::State,
+    move_rule_later_button_state: button::State,
     highlight_input_state: text_input::State,
-    highlight_input_value: String,
+    replace_input_state: text_input::State,
+    template_input_state: text_input::State,
+    prefix_input_state: text_input::State,
+    suffix_input_state: text_input::State,
+    extension_from_state: text_input::State,
+    extension_to_state: text_input::State,
+    numbering_base_state: text_input::State,
+    numbering_start_state: text_input::State,
+    numbering_start_value: String,
+    numbering_step_state: text_input::State,
+    numbering_step_value: String,
+    numbering_width_state: text_input::State,
+    numbering_width_value: String,
+    remove_start_state: text_input::State,
+    remove_start_value: String,
+    remove_count_state: text_input::State,
+    remove_count_value: String,
     file_button_state: button::State,
+    apply_button_state: button::State,
+    folder_button_state: button::State,
+    /// The button that keyboard focus was most recently moved to, as described in
+    /// [`keyboard_focused_button`](Self::keyboard_focused_button).
+    focused_button: Option<FocusableButton>,
+    recursive: bool,
+    scan_errors: Vec<io::Error>,
+    export_button_state: button::State,
+    export_error: Option<io::Error>,
+    /// Whether renamed files are moved into [`target_dir`](Self::target_dir).
+    move_to_dir: bool,
+    /// Whether the folders that entries are in are renamed too, as described in
+    /// [`Destination::RenamedDirectories`].
+    rename_dirs: bool,
+    /// Whether names that are too long are shortened to fit, as described in [`plan::truncate_name`].
+    truncate_names: bool,
+    /// Whether names that collide are numbered to tell them apart, as described in
+    /// [`plan::resolve_collisions`].
+    resolve_collisions: bool,
+    target_dir: Option<PathBuf>,
+    target_dir_button_state: button::State,
+    create_dir_button_state: button::State,
+    create_dir_error: Option<io::Error>,
+    import_button_state: button::State,
+    import_error: Option<io::Error>,
+    plan_file_error: Option<io::Error>,
+    plan_file_warning: Option<String>,
+    /// The kind of state that most recently failed to save, and why, as described in
+    /// [`record_save`](Self::record_save).
+    save_error: Option<(&'static str, io::Error)>,
+    /// Everything that didn't load exactly as it was saved in the most recently loaded rules.
+    load_warnings: Vec<LoadWarning>,
+    rename_results: Vec<io::Result<()>>,
+    /// How many files the most recently applied rename skipped because their name wouldn't change.
+    rename_skipped: usize,
+    undo_button_state: button::State,
+    remove_button_state: button::State,
+    check_all_button_state: button::State,
+    uncheck_all_button_state: button::State,
+    clear_button_state: button::State,
+    /// The renames waiting for the user to confirm that they should be applied.
+    plan: Option<RenamePlan>,
+    plan_scroll_state: scrollable::State,
+    confirm_rename_button_state: button::State,
+    cancel_rename_button_state: button::State,
+    /// The plan being applied, if any. Nothing else can be changed until it finishes.
+    renaming: Option<Renaming>,
+    /// The folder being scanned, if any.
+    scanning: Option<Scanning>,
+    /// Identifies the most recently started scan, so that the results of scans that have since been
+    /// cancelled or replaced can be told apart and ignored.
+    scan_token: u64,
+    /// The paths of the entries waiting for the user to confirm that they should be removed.
+    pending_removal: Option<HashSet<PathBuf>>,
+    confirm_delete_button_state: button::State,
+    cancel_delete_button_state: button::State,
+    /// The paths of the files waiting for the user to confirm that they should be moved to the trash.
+    pending_trash: Option<HashSet<PathBuf>>,
+    confirm_trash_button_state: button::State,
+    cancel_trash_button_state: button::State,
+    /// The paths of the files that couldn't be moved to the trash the last time any were, and why.
+    trash_errors: Vec<(PathBuf, String)>,
+    /// Whether anything exists at each target that the preview last checked, so that the disk isn't
+    /// checked for every target on every redraw. See [`detect_collisions`]. It's forgotten whenever files
+    /// are renamed or added, in case the files on disk changed.
+    existing_targets: HashMap<PathBuf, bool>,
+    /// The `(old, new)` paths of every file renamed by the most recently applied rename.
+    undo_renames: Vec<(PathBuf, PathBuf)>,
+    /// The directories that the most recently applied rename created, parents first, which undoing it
+    /// removes again.
+    undo_created_directories: Vec<PathBuf>,
+    /// The old directories that the most recently applied rename left empty and removed, which undoing
+    /// it creates again.
+    undo_removed_directories: Vec<PathBuf>,
+    /// The most recent changes to the list, oldest first, for undoing them.
+    list_history: Vec<ListEdit>,
+    /// The changes to the list that have been undone, most recently undone last, for redoing them.
+    list_redo: Vec<ListEdit>,
+    recent: Recent,
+    recent_list_state: pick_list::State<RecentPath>,
+    presets: Vec<Preset>,
+    selected_preset: Option<String>,
+    preset_list_state: pick_list::State<String>,
+    preset_name_state: text_input::State,
+    preset_name_value: String,
+    save_preset_button_state: button::State,
+    save_plan_button_state: button::State,
+    load_plan_button_state: button::State,
+    sort_key: Option<SortKey>,
+    sort_descending: bool,
+    sort_natural: bool,
+    sort_list_state: pick_list::State<SortKey>,
+    /// How the app looks and behaves, as chosen in the settings panel.
+    preferences: Preferences,
+    /// Whether the settings panel is shown in place of the list.
+    show_settings: bool,
+    settings_button_state: button::State,
+    close_settings_button_state: button::State,
+    confirm_threshold_state: text_input::State,
+    confirm_threshold_value: String,
+    theme_list_state: pick_list::State<Theme>,
+    highlight_list_state: pick_list::State<Highlight>,
+    delete_key_list_state: pick_list::State<DeleteKey>,
+    /// The position amongst the visible entries of the match that was most recently jumped to.
+    current_match: Option<usize>,
+    next_match_button_state: button::State,
+    previous_match_button_state: button::State,
+    filter_input_state: text_input::State,
+    filter_input_value: String,
+    /// The paths of the selected entries that are hidden by the filter, as described in
+    /// [`refilter`](Self::refilter).
+    hidden_selection: HashSet<PathBuf>,
+    /// Whether entries hidden by the filter are renamed along with the visible ones.
+    rename_hidden: bool,
+    /// Whether only the selected entries are renamed.
+    selection_only: bool,
+    /// The entry being renamed by hand, if any.
+    editing: Option<Editing>,
+    /// The path of the entry that the context menu is open for, if it's open. This is a path rather than
+    /// an index so that it stays attached to the same entry if the list changes while the menu is open.
+    context_entry: Option<PathBuf>,
+    context_remove_button_state: button::State,
+    context_close_button_state: button::State,
     listbox_state: listbox::State,
     scroll_state: scrollable::State,
 }
 
 impl App {
     const VERSION: &'static str = env!("CARGO_PKG_VERSION");
-}
+    /// The most changes to the list that are remembered for undoing.
+    const LIST_HISTORY_LIMIT: usize = 100;
 
-impl Application for App {
-    type Executor = executor::Default;
-    type Message = Message;
-    type Flags = std::env::Args;
+    /// Whether the given entry matches the filter, and so should be displayed.
+    fn is_visible(&self, entry: &Entry) -> bool {
+        if self.filter_input_value.is_empty() {
+            return true;
+        }
 
-    fn new(flags: Self::Flags) -> (Self, Command<Self::Message>) {
-        (
-            App {
-                entries: flags.skip(1).map(Entry::from).collect(),
-                ..Default::default()
-            },
-            Command::none(),
-        )
+        let name = entry.path.file_name().unwrap_or_default().to_string_lossy();
+        if self.preferences.filter_case_sensitive {
+            name.contains(&self.filter_input_value)
+        } else {
+            name.to_lowercase()
+                .contains(&self.filter_input_value.to_lowercase())
+        }
     }
 
-    fn title(&self) -> String {
-        format!("Mass Renamer - Version {}", Self::VERSION)
+    /// The indices of every entry that matches the filter, in display order.
+    fn visible_indices(&self) -> Vec<usize> {
+        (0..self.entries.len())
+            .filter(|&i| self.is_visible(&self.entries[i]))
+            .collect()
     }
 
-    fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
-        use Message::*;
-        match message {
-            HighlightInputChanged(input) => self.highlight_input_value = input,
-            FileButtonPressed => {
-                return Command::perform(
-                    async { FileDialog::new().show_open_multiple_file() },
-                    |r| Message::FilesRecieved(Arc::new(r)),
-                );
+    /// Changes which entries are visible, or the order they're in, with the given function, keeping every
+    /// entry that was selected before selected afterwards, even if it's hidden in between.
+    ///
+    /// The list only tracks the selection of the visible entries, so the selection is carried across by
+    /// path, and the paths of selected entries that become hidden are kept until they're shown again.
+    fn refilter(&mut self, change: impl FnOnce(&mut Self)) {
+        let mut selected = std::mem::take(&mut self.hidden_selection);
+        selected.extend(self.visible_paths(&self.listbox_state.selected_indices()));
+
+        change(self);
+
+        let visible = self.visible_indices();
+        let shown: HashSet<usize> = visible.iter().copied().collect();
+        self.listbox_state.set_selection(
+            visible
+                .iter()
+                .map(|&i| selected.contains(&self.entries[i].path))
+                .collect(),
+        );
+        self.hidden_selection = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(i, e)| !shown.contains(i) && selected.contains(&e.path))
+            .map(|(_, e)| e.path.clone())
+            .collect();
+    }
+
+    /// The positions amongst the visible entries of every entry whose name matches the find text.
+    fn match_positions(&self) -> Vec<usize> {
+        self.visible_indices()
+            .into_iter()
+            .enumerate()
+            .filter(|(_, i)| {
+                let name = self.entries[*i].name().unwrap_or_default();
+                self.rule().matches(name).next().is_some()
+            })
+            .map(|(position, _)| position)
+            .collect()
+    }
+
+    /// Scrolls the list to show the first visible entry whose name matches the find text, if there is one.
+    fn scroll_to_first_match(&mut self) {
+        let first = self
+            .entries
+            .iter()
+            .filter(|e| self.is_visible(e))
+            .position(|e| {
+                self.rule()
+                    .matches(e.name().unwrap_or_default())
+                    .next()
+                    .is_some()
+            });
+
+        if let Some(offset) = first.and_then(|p| self.listbox_state.scroll_offset_to(p)) {
+            self.scroll_state.snap_to(offset);
+        }
+    }
+
+    /// Selects the next or previous entry whose name matches the find text, wrapping around at the ends,
+    /// and scrolls it into view.
+    fn jump_to_match(&mut self, forwards: bool) {
+        let positions = self.match_positions();
+        let current = self.current_match;
+        let next = if forwards {
+            positions
+                .iter()
+                .find(|&&p| current.map_or(true, |c| p > c))
+                .or_else(|| positions.first())
+        } else {
+            positions
+                .iter()
+                .rev()
+                .find(|&&p| current.map_or(true, |c| p < c))
+                .or_else(|| positions.last())
+        };
+        let next = match next {
+            Some(&next) => next,
+            None => return,
+        };
+
+        self.current_match = Some(next);
+        self.listbox_state.select_range(next, next);
+        if let Some(offset) = self.listbox_state.scroll_offset_to(next) {
+            self.scroll_state.snap_to(offset);
+        }
+    }
+
+    /// Determines which entries should be renamed when the rule is applied. When only the selection is
+    /// renamed, hidden entries are never in scope, as they can't be selected.
+    fn scope(&self) -> Vec<bool> {
+        let selected: Option<HashSet<usize>> = self.selection_only.then(|| {
+            let visible = self.visible_indices();
+            self.listbox_state
+                .selected_indices()
+                .into_iter()
+                .filter_map(|i| visible.get(i).copied())
+                .collect()
+        });
+
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                !entry.excluded
+                    && match &selected {
+                        Some(selected) => selected.contains(&i),
+                        None => self.rename_hidden || self.is_visible(entry),
+                    }
+            })
+            .collect()
+    }
+
+    /// Whether numbering the entries that are in scope would go past the largest number that can be used,
+    /// as described in [`Pipeline::numbering_overflows`].
+    fn numbering_overflows(&self) -> bool {
+        let count = self.scope().into_iter().filter(|&s| s).count();
+        self.pipeline.numbering_overflows(count)
+    }
+
+    /// Computes the path that every entry would be renamed to. Entries that aren't in scope keep their
+    /// current path, and the rest are numbered by their position amongst the entries that are in scope.
+    /// Targets that collide are then told apart, if that's turned on.
+    fn targets(&self) -> Vec<PathBuf> {
+        self.targets_for(&self.destination())
+    }
+
+    /// Computes the path that every entry would be renamed to, as [`targets`](Self::targets) does, given
+    /// the destination that's already been worked out, so that it's only worked out once.
+    fn targets_for(&self, destination: &Destination) -> Vec<PathBuf> {
+        let mut index = 0;
+        let targets: Vec<_> = self
+            .entries
+            .iter()
+            .zip(self.scope())
+            .map(|(entry, in_scope)| {
+                if in_scope {
+                    index += 1;
+                    let target = entry.target(index - 1, &self.pipeline, destination);
+                    if self.truncate_names {
+                        truncate_target(target)
+                    } else {
+                        target
+                    }
+                } else {
+                    entry.path.clone()
+                }
+            })
+            .collect();
+
+        if self.resolve_collisions {
+            let paths: Vec<_> = self.entries.iter().map(|e| e.path.clone()).collect();
+            plan::resolve_collisions(&paths, targets)
+        } else {
+            targets
+        }
+    }
+
+    /// Where files end up when they're renamed. Moving them into a folder takes priority over renaming
+    /// their folders.
+    fn destination(&self) -> Destination {
+        match &self.target_dir {
+            Some(dir) if self.move_to_dir => Destination::Directory(dir.clone()),
+            _ if self.rename_dirs => self
+                .directory_base()
+                .map_or(Destination::SameDirectory, Destination::RenamedDirectories),
+            _ => Destination::SameDirectory,
+        }
+    }
+
+    /// The directory that renaming directories stops at, which contains the deepest directory that
+    /// contains every entry, so that only that directory and the ones within it can be renamed. There
+    /// isn't one if the entries only have the root in common.
+    fn directory_base(&self) -> Option<PathBuf> {
+        let mut parents = self.entries.iter().filter_map(|e| e.path.parent());
+        let mut common = parents.next()?.to_path_buf();
+        for parent in parents {
+            while !parent.starts_with(&common) {
+                if !common.pop() {
+                    return None;
+                }
             }
-            FilesRecieved(files) => {
-                if let Ok(paths) = &*files {
-                    self.entries.extend(paths.iter().map(Entry::from));
+        }
+        common.parent().map(Path::to_path_buf)
+    }
+
+    /// The rule being edited.
+    fn rule(&self) -> &Rule {
+        &self.pipeline.rules()[self.current_rule]
+    }
+
+    /// The rule being edited, for changing it.
+    fn rule_mut(&mut self) -> &mut Rule {
+        &mut self.pipeline.rules_mut()[self.current_rule]
+    }
+
+    /// Replaces every rule, updating the inputs to display the first one. If the rules were loaded,
+    /// anything that didn't load exactly as it was saved is reported.
+    fn set_pipeline(&mut self, mut pipeline: Pipeline) {
+        self.load_warnings = pipeline.take_load_warnings();
+        self.pipeline = pipeline;
+        self.edit_rule(0);
+    }
+
+    /// Starts editing the rule at the given position in the pipeline, updating the inputs to display it.
+    fn edit_rule(&mut self, index: usize) {
+        self.current_rule = index.min(self.pipeline.rules().len() - 1);
+        let rule = &self.pipeline.rules()[self.current_rule];
+        self.numbering_start_value = rule.numbering.start.to_string();
+        self.numbering_step_value = rule.numbering.step.to_string();
+        self.numbering_width_value = rule.numbering.width.to_string();
+        self.remove_start_value = rule.remove_start.to_string();
+        self.remove_count_value = rule.remove_count.to_string();
+        self.current_match = None;
+    }
+
+    /// Saves the current rules as a preset with the name in the preset name input, replacing any existing
+    /// preset with the same name.
+    fn save_preset(&mut self) {
+        let name = self.preset_name_value.trim();
+        if name.is_empty() {
+            return;
+        }
+
+        let preset = Preset {
+            name: name.to_owned(),
+            pipeline: self.pipeline.clone(),
+        };
+        match self.presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+        self.selected_preset = Some(name.to_owned());
+
+        let result = Preset::save_all(&self.presets);
+        self.record_save("presets", result);
+    }
+
+    /// Rearranges the entries into the given order, given as the index that each entry had before being
+    /// rearranged.
+    fn reorder_entries(&mut self, order: &[usize]) {
+        self.apply_order(order);
+        self.record(ListEdit::Reordered(order.to_vec()));
+        self.save_session();
+    }
+
+    /// Rearranges the entries into the given order, given as the position each had before.
+    fn apply_order(&mut self, order: &[usize]) {
+        let mut entries: Vec<_> = self.entries.drain(..).map(Some).collect();
+        self.entries = order.iter().filter_map(|&i| entries[i].take()).collect();
+    }
+
+    /// Records the given change to the list so that it can be undone, forgetting the oldest change if
+    /// there are too many. Changes that were undone can't be redone after this.
+    fn record(&mut self, edit: ListEdit) {
+        self.list_redo.clear();
+        self.list_history.push(edit);
+        if self.list_history.len() > Self::LIST_HISTORY_LIMIT {
+            self.list_history.remove(0);
+        }
+    }
+
+    /// Forgets every recorded change to the list, such as after renaming files.
+    fn forget_list_history(&mut self) {
+        self.list_history.clear();
+        self.list_redo.clear();
+    }
+
+    /// Makes the given change to the list again if `forwards` is true, or undoes it otherwise.
+    fn apply_edit(&mut self, edit: &ListEdit, forwards: bool) {
+        match (edit, forwards) {
+            (ListEdit::Added(paths) | ListEdit::Scanned(_, paths), true) => {
+                self.entries.extend(paths.iter().map(Entry::from));
+                self.entry_paths.extend(paths.iter().cloned());
+            }
+            (ListEdit::Removed(removed), false) => self.restore_entries(removed),
+            (ListEdit::Added(paths) | ListEdit::Scanned(_, paths), false) => {
+                let paths: HashSet<_> = paths.iter().map(|p| p.as_path()).collect();
+                self.retain_entries(|p| !paths.contains(p));
+            }
+            (ListEdit::Removed(removed), true) => {
+                let paths: HashSet<_> = removed.iter().map(|r| r.path.as_path()).collect();
+                self.retain_entries(|p| !paths.contains(p));
+            }
+            (ListEdit::Reordered(order), true) => self.apply_order(order),
+            (ListEdit::Reordered(order), false) => {
+                let mut inverse = vec![0; order.len()];
+                for (new, &old) in order.iter().enumerate() {
+                    inverse[old] = new;
                 }
+                self.apply_order(&inverse);
             }
-            FilesDeleted(indexes) => {
-                let mut iter = indexes.iter();
-                self.entries.retain(|_| !iter.next().unwrap());
+        }
+
+        // The selection doesn't follow the entries through the change
+        self.listbox_state.deselect_all();
+        self.save_session();
+    }
+
+    /// Undoes the most recent change to the list, or if there isn't one, the most recent rename.
+    fn undo(&mut self) {
+        match self.list_history.pop() {
+            Some(edit) => {
+                self.apply_edit(&edit, false);
+                self.list_redo.push(edit);
             }
+            None => self.undo_rename(),
         }
+    }
 
-        Command::none()
+    /// Makes the most recently undone change to the list again.
+    fn redo(&mut self) {
+        if let Some(edit) = self.list_redo.pop() {
+            self.apply_edit(&edit, true);
+            self.list_history.push(edit);
+        }
     }
 
-    fn view(&mut self) -> Element<Self::Message> {
-        Column::with_children(vec![
-            Button::new(&mut self.file_button_state, Text::new("Open Files"))
-                .on_press(Message::FileButtonPressed)
-                .into(),
-            TextInput::new(
-                &mut self.highlight_input_state,
-                "Highlight Text...",
-                &self.highlight_input_value,
-                Message::HighlightInputChanged,
-            )
-            .padding(4)
-            .into(),
-            Container::new(
-                Scrollable::new(&mut self.scroll_state).push(
-                    ListBox::with_children(
-                        &mut self.listbox_state,
-                        self.entries
-                            .iter()
-                            .map(|e| {
-                                if self.highlight_input_value.is_empty() {
-                                    Text::new(&e.text)
-                                } else {
-                                    e.text.match_indices(&self.highlight_input_value).fold(
-                                        Text::new(&e.text),
-                                        |t, (i, _)| {
-                                            t.highlight(
-                                                i,
-                                                i + self.highlight_input_value.len(),
-                                                Color::from_rgb8(0xff, 0xc0, 0xcb),
-                                            )
-                                        },
-                                    )
-                                }
-                                .into()
-                            })
-                            .collect(),
-                        Message::FilesDeleted,
-                    )
-                    .width(Length::Fill)
-                    .padding([1, 23])
-                    .spacing(4)
-                    .style(listbox::Style::light(true)),
-                ),
-            )
-            .height(Length::Fill)
-            .padding(1)
-            .style(ContainerStyle)
-            .into(),
-        ])
-        .padding(16)
-        .spacing(16)
-        .into()
+    /// Every text input that keyboard focus moves through, in the order they're displayed.
+    fn text_inputs(&mut self) -> [&mut text_input::State; 15] {
+        [
+            &mut self.highlight_input_state,
+            &mut self.preset_name_state,
+            &mut self.replace_input_state,
+            &mut self.template_input_state,
+            &mut self.numbering_base_state,
+            &mut self.numbering_start_state,
+            &mut self.numbering_step_state,
+            &mut self.numbering_width_state,
+            &mut self.remove_start_state,
+            &mut self.remove_count_state,
+            &mut self.prefix_input_state,
+            &mut self.suffix_input_state,
+            &mut self.extension_from_state,
+            &mut self.extension_to_state,
+            &mut self.filter_input_state,
+        ]
+    }
+
+    /// The buttons that keyboard focus moves through after the list, in the order they're displayed.
+    fn focusable_buttons(&self) -> Vec<FocusableButton> {
+        let mut buttons = vec![
+            FocusableButton::OpenFiles,
+            FocusableButton::OpenFolder,
+            FocusableButton::Apply,
+        ];
+        if self.plan.is_some() {
+            buttons.extend([
+                FocusableButton::ConfirmRename,
+                FocusableButton::CancelRename,
+            ]);
+        }
+        buttons
     }
-}
 
-struct ContainerStyle;
+    /// Moves keyboard focus to the next text input, or the previous one if `forwards` is false, in the
+    /// order they're displayed. The list comes after every text input, followed by the
+    /// [`focusable_buttons`](Self::focusable_buttons), and focus wraps around at either end.
+    fn cycle_focus(&mut self, forwards: bool) {
+        let buttons = self.focusable_buttons();
+        let focused_button = self
+            .focused_button
+            .and_then(|focused| buttons.iter().position(|&b| b == focused));
+        let list_focused = self.listbox_state.is_focused();
+        let mut inputs = self.text_inputs();
 
-impl container::StyleSheet for ContainerStyle {
-    fn style(&self) -> container::Style {
-        container::Style {
-            border_width: 1.0,
-            border_color: Color::from_rgb8(0xbe, 0xbe, 0xbe),
-            ..Default::default()
+        // The list is counted as one past the last input, and the buttons come after it
+        let list = inputs.len();
+        let count = list + 1 + buttons.len();
+        let current = if list_focused {
+            Some(list)
+        } else if let Some(i) = focused_button {
+            Some(list + 1 + i)
+        } else {
+            inputs.iter().position(|input| input.is_focused())
+        };
+        let next = match (current, forwards) {
+            (Some(i), true) => (i + 1) % count,
+            (Some(i), false) => (i + count - 1) % count,
+            (None, true) => 0,
+            (None, false) => count - 1,
+        };
+
+        for input in inputs.iter_mut() {
+            input.unfocus();
+        }
+        if let Some(input) = inputs.get_mut(next) {
+            input.focus();
+            input.move_cursor_to_end();
+        }
+        self.listbox_state.unfocus();
+        if next == list {
+            self.listbox_state.focus();
+        }
+        self.focused_button = next.checked_sub(list + 1).map(|i| buttons[i]);
+    }
+
+    /// Gives keyboard focus to the given button, taking it from whatever had it.
+    fn focus_button(&mut self, button: FocusableButton) {
+        for input in self.text_inputs() {
+            input.unfocus();
+        }
+        self.listbox_state.unfocus();
+        self.focused_button = Some(button);
+    }
+
+    /// The button that has keyboard focus, if it's still shown and nothing else has taken focus since,
+    /// such as a text input that was clicked.
+    fn keyboard_focused_button(&mut self) -> Option<FocusableButton> {
+        let elsewhere = self.listbox_state.is_focused()
+            || self.text_inputs().iter().any(|input| input.is_focused());
+        let button = self.focused_button.filter(|_| !elsewhere)?;
+        self.focusable_buttons().contains(&button).then(|| button)
+    }
+
+    /// Presses the button that has keyboard focus, if there is one.
+    fn press_focused_button(&mut self) -> Command<Message> {
+        match self.keyboard_focused_button() {
+            Some(button) => self.update(button.message()),
+            None => Command::none(),
+        }
+    }
+
+    /// Closes whichever dialog, menu or editor is open, so that each can be dismissed from the keyboard.
+    fn dismiss(&mut self) {
+        if self.editing.is_some() {
+            self.editing = None;
+        } else if self.context_entry.is_some() {
+            self.context_entry = None;
+        } else if self.pending_trash.is_some() {
+            self.pending_trash = None;
+        } else if self.pending_removal.is_some() {
+            self.pending_removal = None;
+        } else {
+            self.plan = None;
+        }
+    }
+
+    /// Sorts the entries by the current sort key, keeping each entry's selection attached to it. Entries
+    /// whose modification time can't be read always sort last. Names and paths are compared in natural
+    /// order if it's enabled, as described in [`natural_cmp`].
+    fn sort_entries(&mut self) {
+        let key = match self.sort_key {
+            Some(key) => key,
+            None => return,
+        };
+
+        let direct = |o: Ordering| if self.sort_descending { o.reverse() } else { o };
+        let compare = |a: Option<&OsStr>, b: Option<&OsStr>| match (a, b) {
+            (Some(a), Some(b)) if self.sort_natural => {
+                natural_cmp(&a.to_string_lossy(), &b.to_string_lossy())
+            }
+            _ => a.cmp(&b),
+        };
+        let entries = &self.entries;
+        let mut order: Vec<_> = (0..entries.len()).collect();
+        match key {
+            SortKey::Name => order.sort_by(|&a, &b| {
+                let (a, b) = (&entries[a].path, &entries[b].path);
+                direct(compare(a.file_name(), b.file_name()))
+            }),
+            SortKey::Extension => order.sort_by(|&a, &b| {
+                let (a, b) = (&entries[a].path, &entries[b].path);
+                direct(
+                    compare(a.extension(), b.extension())
+                        .then_with(|| compare(a.file_name(), b.file_name())),
+                )
+            }),
+            SortKey::Path => order.sort_by(|&a, &b| {
+                let (a, b) = (&entries[a].path, &entries[b].path);
+                direct(compare_paths(a, b, self.sort_natural))
+            }),
+            SortKey::Modified => {
+                order.sort_by(|&a, &b| match (entries[a].modified, entries[b].modified) {
+                    (Some(a), Some(b)) => direct(a.cmp(&b)),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                });
+            }
+        }
+
+        // The selection only tracks the visible entries, so a sort of a filtered list has to carry the
+        // selection across by path instead
+        if self.filter_input_value.is_empty() {
+            self.listbox_state.apply_order(&order);
+            self.reorder_entries(&order);
+        } else {
+            self.refilter(|app| app.reorder_entries(&order));
         }
     }
+
+    /// The paths of the entries at the given positions amongst the visible entries.
+    fn visible_paths(&self, positions: &[usize]) -> HashSet<PathBuf> {
+        let visible = self.visible_indices();
+        positions
+            .iter()
+            .filter_map(|&p| visible.get(p))
+            .map(|&i| self.entries[i].path.clone())
+            .collect()
+    }
+
+    /// Removes the entries at the given positions amongst the visible entries, which the list has already
+    /// taken out of its selection. If there are more than the
+    /// [`confirm_removal_threshold`](Preferences::confirm_removal_threshold) of them, they're only removed
+    /// once the user confirms it, and until then they're put back as described in
+    /// [`restore_selection`](Self::restore_selection).
+    fn remove_entries(&mut self, positions: &[usize], selected: bool) {
+        let paths = self.visible_paths(positions);
+        if paths.len() > self.preferences.confirm_removal_threshold {
+            self.restore_selection(positions, selected);
+            self.pending_removal = Some(paths);
+        } else {
+            self.remove_paths(&paths);
+        }
+    }
+
+    /// Puts the entries at the given positions amongst the visible entries back into the list's
+    /// selection, which the list takes them out of as soon as they're deleted from it, so that the
+    /// selection stays as it was while their removal waits to be confirmed.
+    ///
+    /// If `selected` is true, the entries were the whole selection, as when the selection is deleted, so
+    /// they're selected again and nothing else is. Otherwise they're put back unselected, and every other
+    /// entry keeps its selection.
+    fn restore_selection(&mut self, positions: &[usize], selected: bool) {
+        if selected {
+            self.listbox_state.deselect_all();
+        }
+        let remaining: HashSet<usize> = self.listbox_state.selected_indices().into_iter().collect();
+        let positions: HashSet<usize> = positions.iter().copied().collect();
+
+        let mut index = 0;
+        let mask = (0..self.visible_indices().len())
+            .map(|position| {
+                if positions.contains(&position) {
+                    selected
+                } else {
+                    index += 1;
+                    remaining.contains(&(index - 1))
+                }
+            })
+            .collect();
+        self.listbox_state.set_selection(mask);
+    }
+
+    /// Takes the visible entries with the given paths out of the list's selection, keeping the rest of the
+    /// selection attached to its entries, for when they're about to be removed.
+    fn forget_selection(&mut self, paths: &HashSet<PathBuf>) {
+        let positions: Vec<_> = self
+            .visible_indices()
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, i)| paths.contains(&self.entries[i].path))
+            .map(|(position, _)| position)
+            .collect();
+        self.listbox_state.remove_indices(&positions);
+    }
+
+    /// Moves the files at the given paths to the trash in the background, reporting the result for each
+    /// with [`Message::Trashed`].
+    fn trash_paths(paths: HashSet<PathBuf>) -> Command<Message> {
+        Command::perform(
+            async move {
+                paths
+                    .into_iter()
+                    .map(|path| {
+                        let result = trash::delete(&path).map_err(|e| e.to_string());
+                        (path, result)
+                    })
+                    .collect()
+            },
+            Message::Trashed,
+        )
+    }
+
+    /// Removes the entries of the files that were moved to the trash, keeping the error for each of those
+    /// that weren't.
+    fn record_trashed(&mut self, results: Vec<(PathBuf, Result<(), String>)>) {
+        self.existing_targets.clear();
+        let mut trashed = HashSet::new();
+        self.trash_errors.clear();
+        for (path, result) in results {
+            match result {
+                Ok(()) => {
+                    trashed.insert(path);
+                }
+                Err(e) => self.trash_errors.push((path, e)),
+            }
+        }
+        self.forget_selection(&trashed);
+        // Trashed files can't be brought back by undoing, so their removal isn't recorded
+        self.retain_entries(|p| !trashed.contains(p));
+        self.save_session();
+    }
+
+    /// Removes the entries for the given paths.
+    fn remove_paths(&mut self, paths: &HashSet<PathBuf>) {
+        let removed = self.removed_entries(paths);
+        if removed.is_empty() {
+            return;
+        }
+
+        self.retain_entries(|p| !paths.contains(p));
+        self.record(ListEdit::Removed(removed));
+        self.save_session();
+    }
+
+    /// The entries for the given paths, as they'd need to be recorded to put them back once removed.
+    fn removed_entries(&self, paths: &HashSet<PathBuf>) -> Vec<RemovedEntry> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| paths.contains(&e.path))
+            .map(|(index, e)| RemovedEntry {
+                index,
+                path: e.path.clone(),
+                excluded: e.excluded,
+            })
+            .collect()
+    }
+
+    /// Puts the given removed entries back where they were removed from, as they were.
+    fn restore_entries(&mut self, removed: &[RemovedEntry]) {
+        for removed in removed {
+            let mut entry = Entry::from(&removed.path);
+            entry.excluded = removed.excluded;
+            self.entries
+                .insert(removed.index.min(self.entries.len()), entry);
+            self.entry_paths.insert(removed.path.clone());
+        }
+    }
+
+    /// Keeps only the entries whose paths the given function returns true for.
+    fn retain_entries(&mut self, keep: impl Fn(&Path) -> bool) {
+        self.entries.retain(|e| keep(&e.path));
+        self.entry_paths.retain(|p| keep(p));
+    }
+
+    /// Adds entries for the given paths, skipping any that are already in the list.
+    fn add_entries<P: AsRef<Path>>(&mut self, paths: impl IntoIterator<Item = P>) {
+        self.existing_targets.clear();
+        let mut added = Vec::new();
+        for path in paths {
+            let path = path.as_ref();
+            if self.entry_paths.insert(path.to_path_buf()) {
+                self.entries.push(Entry::from(path));
+                added.push(path.to_path_buf());
+            }
+        }
+        if !added.is_empty() {
+            self.record(ListEdit::Added(added));
+        }
+        self.save_session();
+    }
+
+    /// Remembers the given paths as the most recently opened files or folders, in the order they were
+    /// opened.
+    fn remember(&mut self, paths: impl IntoIterator<Item = PathBuf>) {
+        self.recent.add(paths);
+        let result = self.recent.save();
+        self.record_save("recent files", result);
+    }
+
+    /// Saves the current preferences, which happens whenever one of them is changed.
+    fn save_preferences(&mut self) {
+        let result = self.preferences.save();
+        self.record_save("preferences", result);
+    }
+
+    /// Saves the current list of entries and rules so that they can be restored by a later run. Malformed
+    /// paths are left out, as they can't be represented in the saved file.
+    fn save_session(&mut self) {
+        let session = Session {
+            pipeline: self.pipeline.clone(),
+            target_dir: self.target_dir.clone(),
+            paths: self
+                .entries
+                .iter()
+                .filter(|e| !e.malformed)
+                .map(|e| e.path.clone())
+                .collect(),
+        };
+
+        let result = session.save();
+        self.record_save("session", result);
+    }
+
+    /// Keeps the result of saving the given kind of state, so that a failure is shown until that kind is
+    /// saved successfully.
+    fn record_save(&mut self, kind: &'static str, result: io::Result<()>) {
+        match result {
+            Err(e) => self.save_error = Some((kind, e)),
+            Ok(()) if matches!(self.save_error, Some((k, _)) if k == kind) => {
+                self.save_error = None
+            }
+            Ok(()) => {}
+        }
+    }
+
+    /// Plans renaming every entry that's in scope to its computed target, for the user to review before
+    /// it's applied. There isn't a plan if the find text isn't a valid pattern, or if a template that's
+    /// in use isn't valid.
+    fn plan_rename(&self) -> Option<RenamePlan> {
+        if self.pipeline.pattern_error().is_some()
+            || self.pipeline.template_error().is_some()
+            || self.numbering_overflows()
+        {
+            return None;
+        }
+
+        let destination = self.destination();
+        Some(RenamePlan::new(
+            self.entries
+                .iter()
+                .zip(self.targets_for(&destination))
+                .zip(self.scope())
+                .filter(|((entry, _), in_scope)| *in_scope && entry.is_renameable())
+                .map(|((entry, target), _)| (entry.path.clone(), target)),
+            &destination,
+        ))
+    }
+
+    /// Anything the user should check about the new name of each entry, given the target and scope of
+    /// each, as a plan would find it. See [`plan::name_warnings`].
+    fn name_warnings(&self, targets: &[PathBuf], scope: &[bool]) -> Vec<Option<NameWarning>> {
+        let planned: Vec<usize> = (0..self.entries.len())
+            .filter(|&i| scope[i] && self.entries[i].is_renameable())
+            .collect();
+        let warnings = plan::name_warnings(
+            planned
+                .iter()
+                .map(|&i| (self.entries[i].path.as_path(), targets[i].as_path())),
+        );
+
+        let mut name_warnings = vec![None; self.entries.len()];
+        for (i, warning) in planned.into_iter().zip(warnings) {
+            name_warnings[i] = warning;
+        }
+        name_warnings
+    }
+
+    /// Starts renaming every file in the given plan on disk in the background. Plans with problems
+    /// aren't applied at all, and files whose name wouldn't change are skipped and only counted.
+    ///
+    /// The directories that the plan moves files into are created first, and nothing is renamed if any
+    /// of them can't be.
+    fn apply_plan(&mut self, plan: RenamePlan) -> Command<Message> {
+        if plan.is_blocked() {
+            return Command::none();
+        }
+        if plan.renames.is_empty() {
+            self.rename_results.clear();
+            self.rename_skipped = plan.unchanged;
+            return Command::none();
+        }
+
+        let created_directories = match plan::create_directories(&plan.created_directories) {
+            Ok(created) => created,
+            Err(e) => {
+                self.rename_results = vec![Err(e)];
+                self.rename_skipped = 0;
+                return Command::none();
+            }
+        };
+        let renames = plan.steps();
+        // A path that one step moves a file to and a later step moves it on from is a temporary name
+        let mut arrived = HashMap::new();
+        let mut temporary = HashMap::new();
+        for (old, new) in &renames {
+            if let Some(original) = arrived.remove(old) {
+                temporary.insert(old.clone(), original);
+            }
+            arrived.insert(new.clone(), old.clone());
+        }
+        let renaming = Renaming {
+            renames,
+            results: Vec::new(),
+            renamed: Vec::new(),
+            skipped: plan.unchanged,
+            created_directories,
+            emptied_directories: plan.emptied_directories(),
+            temporary,
+            positions: entry_positions(&self.entries),
+        };
+        let command = renaming.next_chunk();
+        self.renaming = Some(renaming);
+        command
+    }
+
+    /// Records the results of the most recently finished chunk of renames, then either starts the next
+    /// chunk or, once every file has been renamed, keeps the result of each rename.
+    fn record_renames(&mut self, results: &[io::Result<()>]) -> Command<Message> {
+        let renaming = match &mut self.renaming {
+            Some(renaming) => renaming,
+            None => return Command::none(),
+        };
+
+        self.existing_targets.clear();
+        let start = renaming.results.len();
+        for ((old, new), result) in renaming.renames[start..].iter().zip(results) {
+            renaming.results.push(match result {
+                Ok(()) => {
+                    move_entry(
+                        &mut self.entries,
+                        &mut self.entry_paths,
+                        &mut renaming.positions,
+                        old,
+                        new,
+                    );
+                    renaming.renamed.push((old.clone(), new.clone()));
+                    Ok(())
+                }
+                // The error is shared with the message, so a copy of it is kept instead
+                Err(e) => {
+                    let mut message = e.to_string();
+                    // A file that can't be moved on from its temporary name is put back where it was,
+                    // rather than being left hidden under that name
+                    if let Some(original) = renaming.temporary.get(old) {
+                        match plan::rename_file(old, original) {
+                            Ok(()) => {
+                                move_entry(
+                                    &mut self.entries,
+                                    &mut self.entry_paths,
+                                    &mut renaming.positions,
+                                    old,
+                                    original,
+                                );
+                                renaming.renamed.retain(|(_, new)| new != old);
+                            }
+                            Err(_) => {
+                                message =
+                                    format!("{} (the file was left at {})", message, old.display())
+                            }
+                        }
+                    }
+                    Err(io::Error::new(e.kind(), message))
+                }
+            });
+        }
+
+        if !renaming.is_finished() {
+            return renaming.next_chunk();
+        }
+
+        if let Some(renaming) = self.renaming.take() {
+            self.rename_results = renaming.results;
+            self.rename_skipped = renaming.skipped;
+            self.undo_renames = renaming.renamed;
+            self.undo_created_directories = renaming.created_directories;
+            self.undo_removed_directories =
+                plan::remove_empty_directories(&renaming.emptied_directories);
+            self.forget_list_history();
+            self.save_session();
+        }
+        Command::none()
+    }
+
+    /// Starts scanning the given folder for files in the background, cancelling any scan that's already
+    /// running. The files are added to the list as they're found, with [`Message::ScanProgress`].
+    fn start_scan(&mut self, dir: PathBuf) -> Command<Message> {
+        self.cancel_scan();
+        self.scan_errors.clear();
+        self.scanning = Some(Scanning {
+            dir: dir.clone(),
+            recursive: self.recursive,
+            found: 0,
+        });
+        self.next_scan_batch(vec![dir])
+    }
+
+    /// Starts reading the next batch of the given directories for the current scan.
+    fn next_scan_batch(&self, pending: Vec<PathBuf>) -> Command<Message> {
+        let token = self.scan_token;
+        let recursive = self.scanning.as_ref().map_or(false, |s| s.recursive);
+        Command::perform(
+            async move { scan::scan_dirs(pending, recursive, SCAN_CHUNK_SIZE) },
+            move |batch| Message::ScanProgress(token, Arc::new(batch)),
+        )
+    }
+
+    /// Stops the current scan, if there is one. Any of its results that are still on their way are
+    /// ignored, but the files it already found stay in the list.
+    fn cancel_scan(&mut self) {
+        self.scan_token = self.scan_token.wrapping_add(1);
+        // The session isn't saved while scanning, so it's saved with whatever was found instead
+        if self.scanning.take().is_some() {
+            self.save_session();
+        }
+    }
+
+    /// Adds the files found by a batch of the scan with the given token, and starts reading the next
+    /// batch if there are directories left. Batches from scans that were cancelled are ignored.
+    ///
+    /// The session is only saved once the scan finishes, rather than after every batch.
+    fn record_scan(&mut self, token: u64, batch: &scan::ScanBatch) -> Command<Message> {
+        if token != self.scan_token || self.scanning.is_none() {
+            return Command::none();
+        }
+
+        self.existing_targets.clear();
+        let len = self.entries.len();
+        let mut added = Vec::new();
+        for file in &batch.files {
+            if self.entry_paths.insert(file.path.clone()) {
+                self.entries
+                    .push(Entry::new(&file.path, file.metadata.as_ref()));
+                added.push(file.path.clone());
+            }
+        }
+        if !added.is_empty() {
+            let scanned = self
+                .list_history
+                .iter_mut()
+                .rev()
+                .find_map(|edit| match edit {
+                    ListEdit::Scanned(t, paths) if *t == token => Some(paths),
+                    _ => None,
+                });
+            match scanned {
+                Some(paths) => paths.append(&mut added),
+                None => self.record(ListEdit::Scanned(token, added)),
+            }
+        }
+        // The errors are shared with the message, so copies of them are kept instead
+        self.scan_errors.extend(
+            batch
+                .errors
+                .iter()
+                .map(|e| io::Error::new(e.kind(), e.to_string())),
+        );
+
+        if let Some(scanning) = &mut self.scanning {
+            scanning.found += self.entries.len() - len;
+        }
+        if batch.pending.is_empty() {
+            self.scanning = None;
+            self.save_session();
+            Command::none()
+        } else {
+            self.next_scan_batch(batch.pending.clone())
+        }
+    }
+
+    /// Replaces the rules and the folder that files are moved into with those of the given plan. The
+    /// preview shows what the plan would do to the files that are already loaded, but nothing is renamed
+    /// until the user applies it as usual.
+    ///
+    /// Plans that don't name a folder don't move files, rather than moving them wherever the user most
+    /// recently chose.
+    fn use_saved_plan(&mut self, plan: SavedPlan) {
+        self.set_pipeline(plan.pipeline);
+        self.preset_name_value = plan.name;
+        self.selected_preset = None;
+        self.move_to_dir = plan.move_to_dir && plan.target_dir.is_some();
+        if plan.target_dir.is_some() {
+            self.target_dir = plan.target_dir;
+        }
+        // A confirmation that's already open would apply the old rules
+        self.plan = None;
+    }
+
+    /// Renames a single file, replacing the results and undo history of the most recently applied rename.
+    fn rename_one(&mut self, old: PathBuf, new: PathBuf) {
+        self.existing_targets.clear();
+        let result = plan::rename_through_steps(&old, &new);
+        if result.is_ok() {
+            if let Some(entry) = self.entries.iter_mut().find(|e| e.path == old) {
+                *entry = Entry::from(&new);
+                self.entry_paths.remove(&old);
+                self.entry_paths.insert(new.clone());
+            }
+            self.undo_renames = vec![(old, new)];
+            self.undo_created_directories.clear();
+            self.undo_removed_directories.clear();
+            self.forget_list_history();
+            self.save_session();
+        }
+        self.rename_results = vec![result];
+        self.rename_skipped = 0;
+    }
+
+    /// Renames every file renamed by the most recently applied rename back to its old name, recreating
+    /// the old directories that it removed, and then removing the directories that it created.
+    ///
+    /// Files that can't be renamed back are reported and left as they are, without affecting the rest.
+    /// Directories that aren't empty once the files are renamed back are left as they are too.
+    fn undo_rename(&mut self) {
+        self.existing_targets.clear();
+        self.rename_skipped = 0;
+        // If the old directories can't be created, renaming the files back into them reports why
+        let _ = plan::create_directories(&std::mem::take(&mut self.undo_removed_directories));
+        self.rename_results = std::mem::take(&mut self.undo_renames)
+            .into_iter()
+            .rev()
+            .map(|(old, new)| {
+                plan::rename_through_steps(&new, &old)?;
+                if let Some(entry) = self.entries.iter_mut().find(|e| e.path == new) {
+                    *entry = Entry::from(&old);
+                    self.entry_paths.remove(&new);
+                    self.entry_paths.insert(old);
+                }
+                Ok(())
+            })
+            .collect();
+        plan::remove_empty_directories(
+            std::mem::take(&mut self.undo_created_directories)
+                .iter()
+                .rev(),
+        );
+        self.forget_list_history();
+        self.save_session();
+    }
+}
+
+impl Application for App {
+    type Executor = executor::Default;
+    type Message = Message;
+    type Flags = std::env::Args;
+
+    fn new(flags: Self::Flags) -> (Self, Command<Self::Message>) {
+        let session = Session::load();
+        let saved_preferences = Preferences::load();
+        let migrated = saved_preferences.is_none();
+        let preferences = saved_preferences.unwrap_or_else(Preferences::load_from_session);
+        let mut paths = scan::expand_args(flags.skip(1));
+        if paths.is_empty() {
+            paths = session.paths;
+        }
+
+        let entries: Vec<Entry> = paths.into_iter().map(Entry::from).collect();
+        let mut app = App {
+            entry_paths: entries.iter().map(|e| e.path.clone()).collect(),
+            entries,
+            confirm_threshold_value: preferences.confirm_removal_threshold.to_string(),
+            preferences,
+            target_dir: session.target_dir,
+            recent: Recent::load(),
+            presets: Preset::load_all(),
+            sort_natural: true,
+            ..Default::default()
+        };
+        app.set_pipeline(session.pipeline);
+        // The session drops the preferences it used to hold the next time it's saved
+        if migrated {
+            app.save_preferences();
+        }
+
+        (app, Command::none())
+    }
+
+    fn background_color(&self) -> Color {
+        self.preferences.theme.background()
+    }
+
+    fn title(&self) -> String {
+        format!("Mass Renamer - Version {}", Self::VERSION)
+    }
+
+    fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
+        use Message::*;
+
+        // Nothing that could change the entries may happen while they're being renamed, except for taking
+        // files that have already gone to the trash off the list
+        if self.renaming.is_some()
+            && !matches!(
+                message,
+                RenameProgress(_)
+                    | Trashed(_)
+                    | ScanProgress(..)
+                    | ListScrolled(_)
+                    | ThemeChanged(_)
+                    | HighlightChanged(_)
+                    | DeleteKeyChanged(_)
+            )
+        {
+            return Command::none();
+        }
+
+        match message {
+            RuleSelected(index) => self.edit_rule(index),
+            AddRule => {
+                self.pipeline.insert(self.current_rule + 1, Rule::default());
+                self.edit_rule(self.current_rule + 1);
+            }
+            RemoveRule => {
+                self.pipeline.remove(self.current_rule);
+                self.edit_rule(self.current_rule.saturating_sub(1));
+            }
+            MoveRule(later) => {
+                let other = if later {
+                    self.current_rule + 1
+                } else {
+                    self.current_rule.wrapping_sub(1)
+                };
+                if other < self.pipeline.rules().len() {
+                    self.pipeline.rules_mut().swap(self.current_rule, other);
+                    self.current_rule = other;
+                }
+            }
+            HighlightInputChanged(input) => {
+                self.rule_mut().set_find(input);
+                self.current_match = None;
+                self.scroll_to_first_match();
+            }
+            ReplaceInputChanged(input) => self.rule_mut().replace = input,
+            TemplateToggled(use_template) => self.rule_mut().use_template = use_template,
+            TemplateChanged(template) => self.rule_mut().set_template(template),
+            RegexToggled(regex) => self.rule_mut().set_regex(regex),
+            WholeWordToggled(whole_word) => self.rule_mut().whole_word = whole_word,
+            IgnoreCaseToggled(ignore_case) => self.rule_mut().set_case_insensitive(ignore_case),
+            CaseModeChanged(mode) => self.rule_mut().case = mode,
+            RemoveToggled(remove) => self.rule_mut().remove = remove,
+            RemoveStartChanged(input) => {
+                if let Ok(start) = input.parse() {
+                    self.rule_mut().remove_start = start;
+                }
+                self.remove_start_value = input;
+            }
+            RemoveCountChanged(input) => {
+                if let Ok(count) = input.parse() {
+                    self.rule_mut().remove_count = count;
+                }
+                self.remove_count_value = input;
+            }
+            StripCounterToggled(strip_counter) => self.rule_mut().strip_counter = strip_counter,
+            TrimToggled(trim) => self.rule_mut().trim = trim,
+            CollapseWhitespaceToggled(collapse) => self.rule_mut().collapse_whitespace = collapse,
+            SpaceReplacementChanged(spaces) => self.rule_mut().spaces = spaces,
+            PrefixChanged(input) => self.rule_mut().prefix = input,
+            SuffixChanged(input) => self.rule_mut().suffix = input,
+            ChangeExtensionToggled(change) => self.rule_mut().change_extension = change,
+            ExtensionFromChanged(input) => self.rule_mut().extension_from = input,
+            ExtensionToChanged(input) => self.rule_mut().extension_to = input,
+            NumberingToggled(number) => self.rule_mut().number = number,
+            NumberingBaseChanged(input) => self.rule_mut().numbering.base = input,
+            NumberingStartChanged(input) => {
+                if let Ok(start) = input.parse() {
+                    self.rule_mut().numbering.start = start;
+                }
+                self.numbering_start_value = input;
+            }
+            NumberingStepChanged(input) => {
+                if let Ok(step) = input.parse() {
+                    self.rule_mut().numbering.step = step;
+                }
+                self.numbering_step_value = input;
+            }
+            NumberingWidthChanged(input) => {
+                // Padding past the longest possible name would only waste memory
+                if let Ok(width) = input.parse::<usize>() {
+                    self.rule_mut().numbering.width = width.min(plan::MAX_NAME_LENGTH);
+                }
+                self.numbering_width_value = input;
+            }
+            FileButtonPressed => {
+                return Command::perform(
+                    async { FileDialog::new().show_open_multiple_file() },
+                    |r| Message::FilesRecieved(Arc::new(r)),
+                );
+            }
+            FilesRecieved(files) => {
+                if let Ok(paths) = &*files {
+                    self.add_entries(paths);
+                    self.remember(paths.iter().cloned());
+                }
+            }
+            FileDropped(path) | RecentSelected(RecentPath(path)) => {
+                if path.is_dir() {
+                    return self.update(FolderReceived(Some(path)));
+                }
+                self.add_entries([&path]);
+                self.remember([path]);
+            }
+            FolderButtonPressed => {
+                return Command::perform(async { FileDialog::new().show_open_single_dir() }, |r| {
+                    Message::FolderReceived(r.ok().flatten())
+                });
+            }
+            FolderReceived(Some(dir)) => {
+                self.remember([dir.clone()]);
+                return self.start_scan(dir);
+            }
+            ScanProgress(token, batch) => return self.record_scan(token, &batch),
+            FolderReceived(None) => {}
+            RecursiveToggled(recursive) => self.recursive = recursive,
+            MoveToDirToggled(move_to_dir) => self.move_to_dir = move_to_dir,
+            RenameDirsToggled(rename_dirs) => self.rename_dirs = rename_dirs,
+            TruncateNamesToggled(truncate_names) => self.truncate_names = truncate_names,
+            ResolveCollisionsToggled(resolve_collisions) => {
+                self.resolve_collisions = resolve_collisions;
+            }
+            TargetDirButtonPressed => {
+                return Command::perform(async { FileDialog::new().show_open_single_dir() }, |r| {
+                    Message::TargetDirReceived(r.ok().flatten())
+                });
+            }
+            TargetDirReceived(Some(dir)) => {
+                self.target_dir = Some(dir);
+                self.move_to_dir = true;
+                self.save_session();
+            }
+            TargetDirReceived(None) => {}
+            CreateTargetDir => {
+                if let Some(dir) = &self.target_dir {
+                    self.create_dir_error = std::fs::create_dir_all(dir).err();
+                }
+                // The plan was made while the directory was missing, so it needs making again
+                if self.plan.is_some() {
+                    self.plan = self.plan_rename();
+                }
+            }
+            ExportRequested => {
+                return Command::perform(
+                    async {
+                        FileDialog::new()
+                            .add_filter("Text", &["txt"])
+                            .add_filter("CSV", &["csv"])
+                            .show_save_single_file()
+                    },
+                    |r| Message::ListExported(r.ok().flatten()),
+                );
+            }
+            ListExported(Some(path)) => {
+                let targets = self.targets();
+                let entries: Vec<_> = self
+                    .entries
+                    .iter()
+                    .map(|e| e.path.clone())
+                    .zip(targets)
+                    .collect();
+                self.export_error = export::export_list(&path, &entries).err();
+            }
+            ListExported(None) => {}
+            ImportList => {
+                return Command::perform(
+                    async {
+                        FileDialog::new()
+                            .add_filter("Text", &["txt"])
+                            .show_open_single_file()
+                    },
+                    |r| Message::ListImported(r.ok().flatten()),
+                );
+            }
+            ListImported(Some(path)) => match std::fs::read_to_string(&path) {
+                Ok(list) => {
+                    self.import_error = None;
+                    self.add_entries(
+                        list.lines()
+                            .map(str::trim)
+                            .filter(|l| !l.is_empty())
+                            .map(PathBuf::from),
+                    );
+                }
+                Err(e) => self.import_error = Some(e),
+            },
+            ListImported(None) => {}
+            FilesDeleted(positions) if self.preferences.delete_to_trash => {
+                let paths = self.visible_paths(&positions);
+
+                // Files are always confirmed before they're trashed
+                if !paths.is_empty() {
+                    self.restore_selection(&positions, true);
+                    self.pending_trash = Some(paths);
+                }
+            }
+            FilesDeleted(positions) => self.remove_entries(&positions, true),
+            ClearAll => {
+                if !self.entries.is_empty() {
+                    self.pending_removal =
+                        Some(self.entries.iter().map(|e| e.path.clone()).collect());
+                }
+            }
+            ConfirmDelete => {
+                if let Some(paths) = self.pending_removal.take() {
+                    self.forget_selection(&paths);
+                    self.remove_paths(&paths);
+                }
+                if self.entries.is_empty() {
+                    self.listbox_state = listbox::State::new();
+                    self.current_match = None;
+                    self.cancel_scan();
+                }
+            }
+            CancelDelete => self.pending_removal = None,
+            DeleteToTrashToggled(delete_to_trash) => {
+                self.preferences.delete_to_trash = delete_to_trash;
+                self.save_preferences();
+            }
+            ConfirmTrash => {
+                if let Some(paths) = self.pending_trash.take() {
+                    return Self::trash_paths(paths);
+                }
+            }
+            CancelTrash => self.pending_trash = None,
+            Trashed(results) => self.record_trashed(results),
+            EntryRemoved(index) => self.remove_entries(&[index], false),
+            EntryChecked(position) => {
+                if let Some(&i) = self.visible_indices().get(position) {
+                    self.entries[i].excluded = !self.entries[i].excluded;
+                }
+            }
+            CheckAll(checked) => {
+                for i in self.visible_indices() {
+                    self.entries[i].excluded = !checked;
+                }
+            }
+            RemoveSelectedPressed => {
+                let positions = self.listbox_state.remove_selected();
+                self.remove_entries(&positions, true);
+            }
+            FilterChanged(input) => self.refilter(|app| app.filter_input_value = input),
+            FilterCaseToggled(case_sensitive) => {
+                self.refilter(|app| app.preferences.filter_case_sensitive = case_sensitive);
+                self.save_preferences();
+            }
+            RenameHiddenToggled(rename_hidden) => self.rename_hidden = rename_hidden,
+            SelectionOnlyToggled(selection_only) => self.selection_only = selection_only,
+            // The selection is read from the list when it's needed, so this only has to refresh the view
+            SelectionChanged(_) => {}
+            ShowFullPathsToggled(show) => {
+                self.preferences.show_full_paths = show;
+                self.save_preferences();
+            }
+            ShortenPathsToggled(shorten) => {
+                self.preferences.shorten_paths = shorten;
+                self.save_preferences();
+            }
+            ShowDetailsToggled(show) => {
+                self.preferences.show_details = show;
+                self.save_preferences();
+            }
+            ShowDiffToggled(show) => {
+                self.preferences.show_diff = show;
+                self.save_preferences();
+            }
+            CompactRowsToggled(compact) => {
+                self.preferences.compact_rows = compact;
+                self.save_preferences();
+            }
+            WrapNamesToggled(wrap) => {
+                self.preferences.wrap_names = wrap;
+                self.save_preferences();
+            }
+            ConfirmThresholdChanged(input) => {
+                if let Ok(threshold) = input.parse() {
+                    self.preferences.confirm_removal_threshold = threshold;
+                    self.save_preferences();
+                }
+                self.confirm_threshold_value = input;
+            }
+            SettingsToggled => self.show_settings = !self.show_settings,
+            EntryContextMenu(index, _) => {
+                self.context_entry = self
+                    .visible_indices()
+                    .get(index)
+                    .map(|&i| self.entries[i].path.clone());
+            }
+            CopySelected(positions) => {
+                let visible = self.visible_indices();
+                let text = export::copy_text(
+                    positions
+                        .iter()
+                        .filter_map(|&p| visible.get(p))
+                        .map(|&i| self.entries[i].path.as_path()),
+                    self.preferences.show_full_paths,
+                );
+                return clipboard::write(text);
+            }
+            Pasted(text) => self.add_entries(scan::paths_from_text(&text)),
+            ContextRemovePressed => {
+                if let Some(path) = self.context_entry.take() {
+                    let paths = HashSet::from([path]);
+                    // Keep the rest of the selection, so that shift-clicking carries on from where it was
+                    self.forget_selection(&paths);
+                    self.remove_paths(&paths);
+                }
+            }
+            ContextMenuClosed => self.context_entry = None,
+            EditEntry(index) => {
+                self.editing = self
+                    .visible_indices()
+                    .get(index)
+                    .map(|&i| &self.entries[i])
+                    .filter(|e| e.is_renameable())
+                    .map(Editing::new);
+            }
+            EditChanged(input) => {
+                if let Some(editing) = &mut self.editing {
+                    editing.value = input;
+                }
+            }
+            CommitEdit => {
+                // Invalid names are left in the editor for the user to fix
+                if let Some(target) = self.editing.as_ref().and_then(Editing::target) {
+                    if let Some(editing) = self.editing.take() {
+                        if target != editing.path {
+                            self.rename_one(editing.path, target);
+                        }
+                    }
+                }
+            }
+            FocusNext(forwards) => self.cycle_focus(forwards),
+            EnterPressed => return self.press_focused_button(),
+            Dismiss => self.dismiss(),
+            ListScrolled(offset) => self.scroll_state.snap_to(offset),
+            EntriesReordered(order) => self.reorder_entries(&order),
+            SortBy(key) => {
+                self.sort_key = Some(key);
+                self.sort_entries();
+            }
+            SortDescendingToggled(descending) => {
+                self.sort_descending = descending;
+                self.sort_entries();
+            }
+            SortNaturalToggled(natural) => {
+                self.sort_natural = natural;
+                self.sort_entries();
+            }
+            ThemeChanged(theme) => {
+                self.preferences.theme = theme;
+                self.save_preferences();
+            }
+            HighlightChanged(highlight) => {
+                self.preferences.highlight = highlight;
+                self.save_preferences();
+            }
+            DeleteKeyChanged(delete_key) => {
+                self.preferences.delete_key = delete_key;
+                self.save_preferences();
+            }
+            NextMatch => self.jump_to_match(true),
+            PreviousMatch => self.jump_to_match(false),
+            ApplyRename => {
+                self.plan = self.plan_rename();
+                // The confirmation takes focus, so that it can be confirmed straight away with Enter
+                if self.plan.is_some() {
+                    self.focus_button(FocusableButton::ConfirmRename);
+                }
+            }
+            ConfirmRename => {
+                if let Some(plan) = self.plan.take() {
+                    return self.apply_plan(plan);
+                }
+            }
+            CancelRename => self.plan = None,
+            RenameProgress(results) => return self.record_renames(&results),
+            Undo => self.undo(),
+            Redo => self.redo(),
+            PresetSelected(name) => {
+                let pipeline = self
+                    .presets
+                    .iter()
+                    .find(|p| p.name == name)
+                    .map(|p| p.pipeline.clone());
+                if let Some(pipeline) = pipeline {
+                    self.set_pipeline(pipeline);
+                    self.preset_name_value = name.clone();
+                    self.selected_preset = Some(name);
+                }
+            }
+            PresetNameChanged(input) => self.preset_name_value = input,
+            SavePreset => self.save_preset(),
+            SavePlan => {
+                return Command::perform(
+                    async {
+                        FileDialog::new()
+                            .add_filter("Rename Plan", &["json"])
+                            .show_save_single_file()
+                    },
+                    |r| Message::PlanSaved(r.ok().flatten()),
+                );
+            }
+            PlanSaved(Some(path)) => {
+                let name = match self.preset_name_value.trim() {
+                    "" => path
+                        .file_stem()
+                        .map(|s| s.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    name => name.to_owned(),
+                };
+                let plan = SavedPlan::new(
+                    name,
+                    self.pipeline.clone(),
+                    self.move_to_dir,
+                    self.target_dir.clone(),
+                );
+                self.plan_file_warning = None;
+                self.plan_file_error = plan.save_to(&path).err();
+            }
+            PlanSaved(None) => {}
+            LoadPlan => {
+                return Command::perform(
+                    async {
+                        FileDialog::new()
+                            .add_filter("Rename Plan", &["json"])
+                            .show_open_single_file()
+                    },
+                    |r| Message::PlanLoaded(r.ok().flatten()),
+                );
+            }
+            PlanLoaded(Some(path)) => match SavedPlan::load_from(&path) {
+                Ok(plan) => {
+                    self.plan_file_error = None;
+                    self.plan_file_warning = plan.is_other_version().then(|| {
+                        format!(
+                            "{} was saved by another version of the app, so some of it might not \
+                             have loaded",
+                            path.display()
+                        )
+                    });
+                    self.use_saved_plan(plan);
+                    self.save_session();
+                }
+                Err(e) => self.plan_file_error = Some(e),
+            },
+            PlanLoaded(None) => {}
+        }
+
+        Command::none()
+    }
+
+    fn subscription(&self) -> Subscription<Self::Message> {
+        subscription::events_with(|event, status| match event {
+            Event::Window(window::Event::FileDropped(path)) => Some(Message::FileDropped(path)),
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code: keyboard::KeyCode::Z,
+                modifiers,
+            }) if modifiers.command() && status == event::Status::Ignored => {
+                Some(if modifiers.shift() {
+                    Message::Redo
+                } else {
+                    Message::Undo
+                })
+            }
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code: keyboard::KeyCode::Y,
+                modifiers,
+            }) if modifiers.command() && status == event::Status::Ignored => Some(Message::Redo),
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code: keyboard::KeyCode::F3,
+                modifiers,
+            }) => Some(if modifiers.shift() {
+                Message::PreviousMatch
+            } else {
+                Message::NextMatch
+            }),
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code: keyboard::KeyCode::Escape,
+                ..
+            }) => Some(Message::Dismiss),
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code: keyboard::KeyCode::Tab,
+                modifiers,
+            }) => Some(Message::FocusNext(!modifiers.shift())),
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code: keyboard::KeyCode::O,
+                modifiers,
+            }) if modifiers.command() => Some(if modifiers.shift() {
+                Message::FolderButtonPressed
+            } else {
+                Message::FileButtonPressed
+            }),
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code: keyboard::KeyCode::Enter,
+                modifiers,
+            }) if modifiers.command() => Some(Message::ApplyRename),
+            Event::Keyboard(keyboard::Event::KeyPressed {
+                key_code: keyboard::KeyCode::Enter,
+                ..
+            }) if status == event::Status::Ignored => Some(Message::EnterPressed),
+            _ => None,
+        })
+    }
+
+    fn view(&mut self) -> Element<Self::Message> {
+        let theme = self.preferences.theme;
+        let focused_button = self.keyboard_focused_button();
+        let rule = &self.pipeline.rules()[self.current_rule];
+        let destination = self.destination();
+        let targets = self.targets_for(&destination);
+        let mut status = format!(
+            "{} files loaded, {}, {} will be renamed\n",
+            self.entries.len(),
+            self.listbox_state.description("File list"),
+            self.entries
+                .iter()
+                .zip(&targets)
+                .filter(|(e, target)| e.path != **target)
+                .count()
+        );
+        status += &match self.rename_results.iter().find_map(|r| r.as_ref().err()) {
+            Some(e) => format!(
+                "{} of {} renames failed: {}",
+                self.rename_results.iter().filter(|r| r.is_err()).count(),
+                self.rename_results.len(),
+                e
+            ),
+            None => format!("{} files renamed", self.rename_results.len()),
+        };
+        if self.rename_skipped > 0 {
+            status += &format!(", {} unchanged files skipped", self.rename_skipped);
+        }
+        if let Some(e) = &self.import_error {
+            status += &format!("\nThe list could not be imported: {}", e);
+        }
+        if let Some(e) = &self.export_error {
+            status += &format!("\nThe list could not be exported: {}", e);
+        }
+        if let Some(scanning) = &self.scanning {
+            status += &format!(
+                "\nScanning {}: {} files found so far",
+                scanning.dir.display(),
+                scanning.found
+            );
+        }
+        if let Some(e) = &self.plan_file_error {
+            status += &format!("\nThe plan could not be saved or loaded: {}", e);
+        }
+        if let Some(warning) = &self.plan_file_warning {
+            status += &format!("\n{}", warning);
+        }
+        for warning in &self.load_warnings {
+            status += &format!("\n{}", warning);
+        }
+        if let Some((kind, e)) = &self.save_error {
+            status += &format!("\nThe {} could not be saved: {}", kind, e);
+        }
+        if let Some(e) = self.pipeline.template_error() {
+            status += &format!("\nThe template is not valid: {}", e);
+        }
+        if self.numbering_overflows() {
+            status += "\nThe numbering goes past the largest number it can use";
+        }
+        if let Some(e) = &self.create_dir_error {
+            status += &format!("\nThe folder could not be created: {}", e);
+        }
+        if let Some((path, e)) = self.trash_errors.first() {
+            status += &format!(
+                "\n{} files could not be moved to the trash: {}: {}",
+                self.trash_errors.len(),
+                path.display(),
+                e
+            );
+        }
+        if let Some(e) = self.scan_errors.first() {
+            status += &format!(
+                "\n{} folders could not be read: {}",
+                self.scan_errors.len(),
+                e
+            );
+        }
+
+        let collisions = detect_collisions(
+            &self.entries,
+            &targets,
+            &destination,
+            &mut self.existing_targets,
+        );
+        let visible = self.visible_indices();
+        let scope = self.scope();
+        let name_warnings = self.name_warnings(&targets, &scope);
+        let options = DisplayOptions {
+            highlight: self.preferences.highlight.color(theme),
+            // Renaming folders changes more than the file name, so the whole path needs showing
+            full_paths: self.preferences.show_full_paths || self.rename_dirs,
+            shorten: self.preferences.shorten_paths,
+            details: self.preferences.show_details,
+            diff: self.preferences.show_diff,
+            wrap: self.preferences.wrap_names,
+            theme,
+        };
+        let has_matches = !self.match_positions().is_empty();
+
+        let numbering_overflows = rule.numbering_overflows(scope.iter().filter(|&&s| s).count());
+        let mut editing = self.editing.as_mut();
+        let mut list = ListBox::with_children(
+            &mut self.listbox_state,
+            visible
+                .iter()
+                .map(|&i| {
+                    let entry = &self.entries[i];
+                    match editing.take() {
+                        Some(e) if e.path == entry.path => e.view(theme),
+                        e => {
+                            editing = e;
+                            let target = scope[i].then(|| targets[i].as_path());
+                            entry.view(rule, target, name_warnings[i], options)
+                        }
+                    }
+                })
+                .collect(),
+            Message::FilesDeleted,
+        )
+        .on_scroll(Message::ListScrolled)
+        .on_activate(Message::EditEntry)
+        .on_edit(Message::EditEntry)
+        .on_copy(Message::CopySelected)
+        .on_paste(Message::Pasted)
+        .on_context(Message::EntryContextMenu)
+        .on_remove(Message::EntryRemoved)
+        .on_check(
+            visible.iter().map(|&i| !self.entries[i].excluded).collect(),
+            Message::EntryChecked,
+        )
+        .empty_text(if self.entries.is_empty() {
+            "No files loaded. Open or drop some files to get started."
+        } else {
+            "No files match the filter."
+        })
+        .width(Length::Fill)
+        .padding([1, 23])
+        .spacing(if self.preferences.compact_rows {
+            0.0
+        } else {
+            4.0
+        })
+        .row_height(self.preferences.compact_rows.then(|| COMPACT_ROW_HEIGHT))
+        .wrap(self.preferences.wrap_names)
+        .flagged(visible.iter().map(|&i| collisions[i]).collect())
+        .malformed(
+            visible
+                .iter()
+                .map(|&i| !self.entries[i].is_renameable())
+                .collect(),
+        )
+        .delete_modifiers(self.preferences.delete_key.modifiers())
+        .style(theme.listbox());
+
+        // Reordering a filtered list would be ambiguous about where the hidden entries should go
+        if self.filter_input_value.is_empty() {
+            list = list
+                .on_reorder(Message::EntriesReordered)
+                .drag_handles(true);
+        }
+        if self.selection_only {
+            list = list.on_select(Message::SelectionChanged);
+        }
+        let mut apply_button = Button::new(&mut self.apply_button_state, Text::new("Apply"))
+            .style(FocusableButton::Apply.style(theme, focused_button));
+        let nothing_selected = self.selection_only && !scope.contains(&true);
+        if self.pipeline.pattern_error().is_none()
+            && self.pipeline.template_error().is_none()
+            && !self.numbering_overflows()
+            && !collisions.contains(&true)
+            && self.renaming.is_none()
+            && !nothing_selected
+        {
+            apply_button = apply_button.on_press(Message::ApplyRename);
+        }
+        let apply_button: Element<_> = if nothing_selected {
+            Tooltip::new(
+                apply_button,
+                "Select the entries to rename, or turn off Rename Selection Only",
+                tooltip::Position::Bottom,
+            )
+            .style(theme)
+            .padding(4)
+            .into()
+        } else {
+            apply_button.into()
+        };
+
+        let mut undo_button =
+            Button::new(&mut self.undo_button_state, Text::new("Undo")).style(theme);
+        if !(self.undo_renames.is_empty() && self.list_history.is_empty())
+            && self.renaming.is_none()
+        {
+            undo_button = undo_button.on_press(Message::Undo);
+        }
+
+        let mut remove_button =
+            Button::new(&mut self.remove_button_state, Text::new("Remove From List")).style(theme);
+        if self.listbox_state.selection_count() > 0 {
+            remove_button = remove_button.on_press(Message::RemoveSelectedPressed);
+        }
+
+        let mut clear_button =
+            Button::new(&mut self.clear_button_state, Text::new("Clear All")).style(theme);
+        if !self.entries.is_empty() {
+            clear_button = clear_button.on_press(Message::ClearAll);
+        }
+
+        let mut previous_match_button = Button::new(
+            &mut self.previous_match_button_state,
+            Text::new("Previous Match"),
+        )
+        .style(theme);
+        let mut next_match_button =
+            Button::new(&mut self.next_match_button_state, Text::new("Next Match")).style(theme);
+        if has_matches {
+            previous_match_button = previous_match_button.on_press(Message::PreviousMatch);
+            next_match_button = next_match_button.on_press(Message::NextMatch);
+        }
+
+        let find_input = TextInput::new(
+            &mut self.highlight_input_state,
+            "Highlight Text...",
+            rule.find(),
+            Message::HighlightInputChanged,
+        )
+        .padding(4);
+
+        let rule_count = self.pipeline.rules().len();
+        let mut remove_rule_button =
+            Button::new(&mut self.remove_rule_button_state, Text::new("Remove Rule")).style(theme);
+        if rule_count > 1 {
+            remove_rule_button = remove_rule_button.on_press(Message::RemoveRule);
+        }
+        let mut move_rule_earlier_button = Button::new(
+            &mut self.move_rule_earlier_button_state,
+            Text::new("Move Earlier"),
+        )
+        .style(theme);
+        if self.current_rule > 0 {
+            move_rule_earlier_button = move_rule_earlier_button.on_press(Message::MoveRule(false));
+        }
+        let mut move_rule_later_button = Button::new(
+            &mut self.move_rule_later_button_state,
+            Text::new("Move Later"),
+        )
+        .style(theme);
+        if self.current_rule + 1 < rule_count {
+            move_rule_later_button = move_rule_later_button.on_press(Message::MoveRule(true));
+        }
+
+        // Every rule is listed in the order it's applied, with the one being edited chosen
+        let rules_row = (0..rule_count)
+            .fold(Row::new().push(Text::new("Rules:")), |row, i| {
+                row.push(
+                    Radio::new(
+                        i,
+                        format!("Rule {}", i + 1),
+                        Some(self.current_rule),
+                        Message::RuleSelected,
+                    )
+                    .style(theme),
+                )
+            })
+            .push(
+                Button::new(&mut self.add_rule_button_state, Text::new("Add Rule"))
+                    .style(theme)
+                    .on_press(Message::AddRule),
+            )
+            .push(remove_rule_button)
+            .push(move_rule_earlier_button)
+            .push(move_rule_later_button)
+            .spacing(8)
+            .align_items(Alignment::Center);
+
+        let context_menu = self.context_entry.as_ref().map(|path| {
+            Row::with_children(vec![
+                Text::new(path.file_name().unwrap_or_default().to_string_lossy()).into(),
+                Button::new(
+                    &mut self.context_remove_button_state,
+                    Text::new("Remove From List"),
+                )
+                .style(theme)
+                .on_press(Message::ContextRemovePressed)
+                .into(),
+                Button::new(&mut self.context_close_button_state, Text::new("Close"))
+                    .style(theme)
+                    .on_press(Message::ContextMenuClosed)
+                    .into(),
+            ])
+            .spacing(8)
+            .align_items(Alignment::Center)
+        });
+
+        let mut content = Column::with_children(vec![
+            Row::with_children(vec![
+                Button::new(&mut self.file_button_state, Text::new("Open Files"))
+                    .style(FocusableButton::OpenFiles.style(theme, focused_button))
+                    .on_press(Message::FileButtonPressed)
+                    .into(),
+                Button::new(&mut self.folder_button_state, Text::new("Open Folder"))
+                    .style(FocusableButton::OpenFolder.style(theme, focused_button))
+                    .on_press(Message::FolderButtonPressed)
+                    .into(),
+                PickList::new(
+                    &mut self.recent_list_state,
+                    self.recent
+                        .paths
+                        .iter()
+                        .cloned()
+                        .map(RecentPath)
+                        .collect::<Vec<_>>(),
+                    None,
+                    Message::RecentSelected,
+                )
+                .placeholder("Recent")
+                .style(theme)
+                .into(),
+                Checkbox::new(
+                    self.recursive,
+                    "Include Subfolders",
+                    Message::RecursiveToggled,
+                )
+                .style(theme)
+                .into(),
+                apply_button,
+                undo_button.into(),
+                remove_button.into(),
+                Button::new(&mut self.check_all_button_state, Text::new("Check All"))
+                    .style(theme)
+                    .on_press(Message::CheckAll(true))
+                    .into(),
+                Button::new(&mut self.uncheck_all_button_state, Text::new("Uncheck All"))
+                    .style(theme)
+                    .on_press(Message::CheckAll(false))
+                    .into(),
+                clear_button.into(),
+                Button::new(&mut self.import_button_state, Text::new("Import List"))
+                    .style(theme)
+                    .on_press(Message::ImportList)
+                    .into(),
+                Button::new(&mut self.export_button_state, Text::new("Export List"))
+                    .style(theme)
+                    .on_press(Message::ExportRequested)
+                    .into(),
+            ])
+            .spacing(8)
+            .align_items(Alignment::Center)
+            .into(),
+            Row::with_children(vec![
+                Checkbox::new(
+                    self.move_to_dir,
+                    "Move To Folder",
+                    Message::MoveToDirToggled,
+                )
+                .style(theme)
+                .into(),
+                Button::new(
+                    &mut self.target_dir_button_state,
+                    Text::new("Choose Folder"),
+                )
+                .style(theme)
+                .on_press(Message::TargetDirButtonPressed)
+                .into(),
+                Text::new(
+                    self.target_dir
+                        .as_ref()
+                        .map_or_else(|| "No folder chosen".into(), |dir| dir.to_string_lossy()),
+                )
+                .width(Length::Fill)
+                .into(),
+                Tooltip::new(
+                    Checkbox::new(
+                        self.rename_dirs,
+                        "Rename Folders Too",
+                        Message::RenameDirsToggled,
+                    )
+                    .style(theme),
+                    "Also finds and replaces in the names of the folders that files are in, \
+                     moving the files into folders with the new names",
+                    tooltip::Position::Bottom,
+                )
+                .style(theme)
+                .padding(4)
+                .into(),
+                Checkbox::new(
+                    self.truncate_names,
+                    "Shorten Long Names",
+                    Message::TruncateNamesToggled,
+                )
+                .style(theme)
+                .into(),
+                Checkbox::new(
+                    self.resolve_collisions,
+                    "Number Duplicate Names",
+                    Message::ResolveCollisionsToggled,
+                )
+                .style(theme)
+                .into(),
+            ])
+            .spacing(8)
+            .align_items(Alignment::Center)
+            .into(),
+            Row::with_children(vec![
+                PickList::new(
+                    &mut self.sort_list_state,
+                    &SortKey::ALL[..],
+                    self.sort_key,
+                    Message::SortBy,
+                )
+                .placeholder("Sort By")
+                .style(theme)
+                .into(),
+                Checkbox::new(
+                    self.sort_descending,
+                    "Descending",
+                    Message::SortDescendingToggled,
+                )
+                .style(theme)
+                .into(),
+                Checkbox::new(
+                    self.sort_natural,
+                    "Natural Order",
+                    Message::SortNaturalToggled,
+                )
+                .style(theme)
+                .into(),
+                Button::new(&mut self.settings_button_state, Text::new("Settings"))
+                    .style(theme)
+                    .on_press(Message::SettingsToggled)
+                    .into(),
+                PickList::new(
+                    &mut self.preset_list_state,
+                    self.presets
+                        .iter()
+                        .map(|p| p.name.clone())
+                        .collect::<Vec<_>>(),
+                    self.selected_preset.clone(),
+                    Message::PresetSelected,
+                )
+                .placeholder("Presets")
+                .style(theme)
+                .into(),
+                TextInput::new(
+                    &mut self.preset_name_state,
+                    "Preset Name...",
+                    &self.preset_name_value,
+                    Message::PresetNameChanged,
+                )
+                .padding(4)
+                .style(theme)
+                .into(),
+                Button::new(&mut self.save_preset_button_state, Text::new("Save Preset"))
+                    .style(theme)
+                    .on_press(Message::SavePreset)
+                    .into(),
+                Button::new(&mut self.save_plan_button_state, Text::new("Save Plan"))
+                    .style(theme)
+                    .on_press(Message::SavePlan)
+                    .into(),
+                Button::new(&mut self.load_plan_button_state, Text::new("Load Plan"))
+                    .style(theme)
+                    .on_press(Message::LoadPlan)
+                    .into(),
+            ])
+            .spacing(8)
+            .align_items(Alignment::Center)
+            .into(),
+            rules_row.into(),
+            Row::with_children(vec![
+                if rule.pattern_error().is_some() {
+                    find_input.style(style::InvalidInput(theme))
+                } else {
+                    find_input.style(theme)
+                }
+                .into(),
+                Checkbox::new(rule.is_regex(), "Regex", Message::RegexToggled)
+                    .style(theme)
+                    .into(),
+                Checkbox::new(
+                    rule.is_case_insensitive(),
+                    "Ignore Case",
+                    Message::IgnoreCaseToggled,
+                )
+                .style(theme)
+                .into(),
+                Checkbox::new(rule.whole_word, "Whole Word", Message::WholeWordToggled)
+                    .style(theme)
+                    .into(),
+                previous_match_button.into(),
+                next_match_button.into(),
+            ])
+            .spacing(8)
+            .align_items(Alignment::Center)
+            .into(),
+            TextInput::new(
+                &mut self.replace_input_state,
+                "Replace With...",
+                &rule.replace,
+                Message::ReplaceInputChanged,
+            )
+            .padding(4)
+            .style(theme)
+            .into(),
+            Row::with_children(vec![
+                Checkbox::new(rule.use_template, "Template", Message::TemplateToggled)
+                    .style(theme)
+                    .into(),
+                {
+                    let input = TextInput::new(
+                        &mut self.template_input_state,
+                        "{name}_{counter:03}{ext}",
+                        rule.template(),
+                        Message::TemplateChanged,
+                    )
+                    .padding(4);
+                    if rule.template_error().is_some() {
+                        input.style(style::InvalidInput(theme))
+                    } else {
+                        input.style(theme)
+                    }
+                    .into()
+                },
+            ])
+            .spacing(8)
+            .align_items(Alignment::Center)
+            .into(),
+            Row::with_children(vec![
+                Checkbox::new(rule.number, "Number", Message::NumberingToggled)
+                    .style(theme)
+                    .into(),
+                TextInput::new(
+                    &mut self.numbering_base_state,
+                    "Base Name...",
+                    &rule.numbering.base,
+                    Message::NumberingBaseChanged,
+                )
+                .padding(4)
+                .style(theme)
+                .into(),
+                {
+                    let input = TextInput::new(
+                        &mut self.numbering_start_state,
+                        "Start",
+                        &self.numbering_start_value,
+                        Message::NumberingStartChanged,
+                    )
+                    .padding(4)
+                    .width(Length::Units(60));
+                    if numbering_overflows {
+                        input.style(style::InvalidInput(theme))
+                    } else {
+                        input.style(theme)
+                    }
+                    .into()
+                },
+                {
+                    let input = TextInput::new(
+                        &mut self.numbering_step_state,
+                        "Step",
+                        &self.numbering_step_value,
+                        Message::NumberingStepChanged,
+                    )
+                    .padding(4)
+                    .width(Length::Units(60));
+                    if numbering_overflows {
+                        input.style(style::InvalidInput(theme))
+                    } else {
+                        input.style(theme)
+                    }
+                    .into()
+                },
+                TextInput::new(
+                    &mut self.numbering_width_state,
+                    "Digits",
+                    &self.numbering_width_value,
+                    Message::NumberingWidthChanged,
+                )
+                .padding(4)
+                .style(theme)
+                .width(Length::Units(60))
+                .into(),
+            ])
+            .spacing(8)
+            .align_items(Alignment::Center)
+            .into(),
+            Row::with_children(
+                CaseMode::ALL
+                    .iter()
+                    .map(|&mode| {
+                        Radio::new(
+                            mode,
+                            mode.to_string(),
+                            Some(rule.case),
+                            Message::CaseModeChanged,
+                        )
+                        .style(theme)
+                        .into()
+                    })
+                    .collect(),
+            )
+            .spacing(16)
+            .into(),
+            Row::with_children(vec![
+                Checkbox::new(rule.remove, "Remove Characters", Message::RemoveToggled)
+                    .style(theme)
+                    .into(),
+                TextInput::new(
+                    &mut self.remove_start_state,
+                    "From",
+                    &self.remove_start_value,
+                    Message::RemoveStartChanged,
+                )
+                .padding(4)
+                .style(theme)
+                .width(Length::Units(60))
+                .into(),
+                TextInput::new(
+                    &mut self.remove_count_state,
+                    "Count",
+                    &self.remove_count_value,
+                    Message::RemoveCountChanged,
+                )
+                .padding(4)
+                .style(theme)
+                .width(Length::Units(60))
+                .into(),
+                Checkbox::new(
+                    rule.strip_counter,
+                    "Strip Counter",
+                    Message::StripCounterToggled,
+                )
+                .style(theme)
+                .into(),
+            ])
+            .spacing(8)
+            .align_items(Alignment::Center)
+            .into(),
+            Row::with_children(
+                [
+                    Element::from(
+                        Checkbox::new(rule.trim, "Trim Whitespace", Message::TrimToggled)
+                            .style(theme),
+                    ),
+                    Checkbox::new(
+                        rule.collapse_whitespace,
+                        "Collapse Whitespace",
+                        Message::CollapseWhitespaceToggled,
+                    )
+                    .style(theme)
+                    .into(),
+                ]
+                .into_iter()
+                .chain(SpaceReplacement::ALL.iter().map(|&spaces| {
+                    Radio::new(
+                        spaces,
+                        spaces.to_string(),
+                        Some(rule.spaces),
+                        Message::SpaceReplacementChanged,
+                    )
+                    .style(theme)
+                    .into()
+                }))
+                .collect(),
+            )
+            .spacing(16)
+            .into(),
+            Row::with_children(vec![
+                TextInput::new(
+                    &mut self.prefix_input_state,
+                    "Prefix...",
+                    &rule.prefix,
+                    Message::PrefixChanged,
+                )
+                .padding(4)
+                .style(theme)
+                .into(),
+                TextInput::new(
+                    &mut self.suffix_input_state,
+                    "Suffix...",
+                    &rule.suffix,
+                    Message::SuffixChanged,
+                )
+                .padding(4)
+                .style(theme)
+                .into(),
+            ])
+            .spacing(8)
+            .into(),
+            Row::with_children(vec![
+                Checkbox::new(
+                    rule.change_extension,
+                    "Change Extension",
+                    Message::ChangeExtensionToggled,
+                )
+                .style(theme)
+                .into(),
+                TextInput::new(
+                    &mut self.extension_from_state,
+                    "From (Blank For None)...",
+                    &rule.extension_from,
+                    Message::ExtensionFromChanged,
+                )
+                .padding(4)
+                .style(theme)
+                .into(),
+                TextInput::new(
+                    &mut self.extension_to_state,
+                    "To (Blank To Remove)...",
+                    &rule.extension_to,
+                    Message::ExtensionToChanged,
+                )
+                .padding(4)
+                .style(theme)
+                .into(),
+            ])
+            .spacing(8)
+            .align_items(Alignment::Center)
+            .into(),
+            Row::with_children(vec![
+                TextInput::new(
+                    &mut self.filter_input_state,
+                    "Filter...",
+                    &self.filter_input_value,
+                    Message::FilterChanged,
+                )
+                .padding(4)
+                .style(theme)
+                .into(),
+                Checkbox::new(
+                    self.rename_hidden,
+                    "Rename Hidden Entries",
+                    Message::RenameHiddenToggled,
+                )
+                .style(theme)
+                .into(),
+                Checkbox::new(
+                    self.selection_only,
+                    "Rename Selection Only",
+                    Message::SelectionOnlyToggled,
+                )
+                .style(theme)
+                .into(),
+            ])
+            .spacing(8)
+            .align_items(Alignment::Center)
+            .into(),
+            match (
+                &self.renaming,
+                &self.pending_removal,
+                &self.pending_trash,
+                &self.plan,
+            ) {
+                // Shown in place of the list so that the list can't be changed while renaming
+                (Some(renaming), _, _, _) => Container::new(
+                    Column::with_children(vec![
+                        Text::new(format!(
+                            "Renaming {} of {} files...",
+                            renaming.results.len(),
+                            renaming.renames.len()
+                        ))
+                        .into(),
+                        ProgressBar::new(
+                            0.0..=renaming.renames.len() as f32,
+                            renaming.results.len() as f32,
+                        )
+                        .style(theme)
+                        .into(),
+                    ])
+                    .spacing(16)
+                    .align_items(Alignment::Center),
+                )
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .padding(32)
+                .center_x()
+                .center_y(),
+                // Shown in place of the list so that the list can't be changed while confirming
+                (None, Some(paths), _, _) => Container::new(
+                    Column::with_children(vec![
+                        Text::new(format!("Remove {} entries from the list?", paths.len())).into(),
+                        Row::with_children(vec![
+                            Button::new(&mut self.confirm_delete_button_state, Text::new("Remove"))
+                                .style(theme)
+                                .on_press(Message::ConfirmDelete)
+                                .into(),
+                            Button::new(&mut self.cancel_delete_button_state, Text::new("Cancel"))
+                                .style(theme)
+                                .on_press(Message::CancelDelete)
+                                .into(),
+                        ])
+                        .spacing(8)
+                        .into(),
+                    ])
+                    .spacing(16)
+                    .align_items(Alignment::Center),
+                )
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .center_x()
+                .center_y(),
+                (None, None, Some(paths), _) => Container::new(
+                    Column::with_children(vec![
+                        Text::new(format!(
+                            "Move {} files to the trash? They will be removed from the list.",
+                            paths.len()
+                        ))
+                        .into(),
+                        Row::with_children(vec![
+                            Button::new(
+                                &mut self.confirm_trash_button_state,
+                                Text::new("Move to Trash"),
+                            )
+                            .style(theme)
+                            .on_press(Message::ConfirmTrash)
+                            .into(),
+                            Button::new(&mut self.cancel_trash_button_state, Text::new("Cancel"))
+                                .style(theme)
+                                .on_press(Message::CancelTrash)
+                                .into(),
+                        ])
+                        .spacing(8)
+                        .into(),
+                    ])
+                    .spacing(16)
+                    .align_items(Alignment::Center),
+                )
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .center_x()
+                .center_y(),
+                (None, None, None, Some(plan)) => {
+                    let mut confirm_button =
+                        Button::new(&mut self.confirm_rename_button_state, Text::new("Rename"))
+                            .style(FocusableButton::ConfirmRename.style(theme, focused_button));
+                    if !plan.is_blocked() {
+                        confirm_button = confirm_button.on_press(Message::ConfirmRename);
+                    }
+                    let mut buttons: Vec<Element<_>> = vec![confirm_button.into()];
+                    if plan
+                        .problems
+                        .iter()
+                        .any(|(_, _, p)| *p == Problem::MissingDirectory)
+                    {
+                        buttons.push(
+                            Button::new(
+                                &mut self.create_dir_button_state,
+                                Text::new("Create Folder"),
+                            )
+                            .style(theme)
+                            .on_press(Message::CreateTargetDir)
+                            .into(),
+                        );
+                    }
+                    buttons.push(
+                        Button::new(&mut self.cancel_rename_button_state, Text::new("Cancel"))
+                            .style(FocusableButton::CancelRename.style(theme, focused_button))
+                            .on_press(Message::CancelRename)
+                            .into(),
+                    );
+
+                    let changes = plan
+                        .renamed_directories
+                        .iter()
+                        .map(|(old, new, count)| {
+                            let created = if plan.created_directories.contains(new) {
+                                ", new folder"
+                            } else {
+                                ""
+                            };
+                            Text::new(format!(
+                                "{} → {} ({} files{})",
+                                old.display(),
+                                new.display(),
+                                count,
+                                created
+                            ))
+                            .color(Color::from_rgb8(0xc0, 0x80, 0x00))
+                            .into()
+                        })
+                        .chain(plan.problems.iter().map(|(old, new, problem)| {
+                            Text::new(format!(
+                                "{} → {} ({})",
+                                old.display(),
+                                new.display(),
+                                problem
+                            ))
+                            .color(Color::from_rgb8(0xd0, 0x30, 0x30))
+                            .into()
+                        }))
+                        .chain(plan.renames.iter().zip(&plan.name_warnings).map(
+                            |((old, new), warning)| {
+                                let change = format!("{} → {}", old.display(), new.display());
+                                match warning {
+                                    Some(warning) => Text::new(format!("{} ({})", change, warning))
+                                        .color(warning_color(*warning))
+                                        .into(),
+                                    None => Text::new(change).into(),
+                                }
+                            },
+                        ))
+                        .collect();
+
+                    Container::new(
+                        Column::with_children(vec![
+                            Text::new(plan.summary()).into(),
+                            Scrollable::new(&mut self.plan_scroll_state)
+                                .push(Column::with_children(changes).spacing(4))
+                                .height(Length::Fill)
+                                .into(),
+                            Row::with_children(buttons).spacing(8).into(),
+                        ])
+                        .spacing(16),
+                    )
+                    .height(Length::Fill)
+                    .padding(8)
+                }
+                (None, None, None, None) if self.show_settings => {
+                    let preferences = &self.preferences;
+                    let checkbox = |checked, label: &str, message: fn(bool) -> Message| {
+                        Element::from(Checkbox::new(checked, label, message).style(theme))
+                    };
+
+                    Container::new(
+                        Column::with_children(vec![
+                            Text::new("Settings").size(24).into(),
+                            Row::with_children(vec![
+                                PickList::new(
+                                    &mut self.theme_list_state,
+                                    &Theme::ALL[..],
+                                    Some(theme),
+                                    Message::ThemeChanged,
+                                )
+                                .style(theme)
+                                .into(),
+                                PickList::new(
+                                    &mut self.highlight_list_state,
+                                    &Highlight::ALL[..],
+                                    Some(preferences.highlight),
+                                    Message::HighlightChanged,
+                                )
+                                .style(theme)
+                                .into(),
+                            ])
+                            .spacing(8)
+                            .into(),
+                            checkbox(
+                                preferences.show_full_paths,
+                                "Show Full Paths",
+                                Message::ShowFullPathsToggled,
+                            ),
+                            checkbox(
+                                preferences.shorten_paths,
+                                "Shorten Paths to Fit",
+                                Message::ShortenPathsToggled,
+                            ),
+                            checkbox(
+                                preferences.show_details,
+                                "Show Size and Date",
+                                Message::ShowDetailsToggled,
+                            ),
+                            checkbox(
+                                preferences.show_diff,
+                                "Highlight Changes in Preview",
+                                Message::ShowDiffToggled,
+                            ),
+                            checkbox(
+                                preferences.compact_rows,
+                                "Compact Rows",
+                                Message::CompactRowsToggled,
+                            ),
+                            checkbox(
+                                preferences.wrap_names,
+                                "Wrap Long Names",
+                                Message::WrapNamesToggled,
+                            ),
+                            checkbox(
+                                preferences.filter_case_sensitive,
+                                "Filter Matches Case",
+                                Message::FilterCaseToggled,
+                            ),
+                            checkbox(
+                                preferences.delete_to_trash,
+                                "Delete Key Moves to Trash",
+                                Message::DeleteToTrashToggled,
+                            ),
+                            Row::with_children(vec![
+                                Text::new("Delete selected entries with").into(),
+                                PickList::new(
+                                    &mut self.delete_key_list_state,
+                                    &DeleteKey::ALL[..],
+                                    Some(preferences.delete_key),
+                                    Message::DeleteKeyChanged,
+                                )
+                                .style(theme)
+                                .into(),
+                            ])
+                            .spacing(8)
+                            .align_items(Alignment::Center)
+                            .into(),
+                            Row::with_children(vec![
+                                Text::new("Ask before removing more than").into(),
+                                TextInput::new(
+                                    &mut self.confirm_threshold_state,
+                                    "Entries",
+                                    &self.confirm_threshold_value,
+                                    Message::ConfirmThresholdChanged,
+                                )
+                                .padding(4)
+                                .style(theme)
+                                .width(Length::Units(60))
+                                .into(),
+                                Text::new("entries from the list").into(),
+                            ])
+                            .spacing(8)
+                            .align_items(Alignment::Center)
+                            .into(),
+                            Button::new(&mut self.close_settings_button_state, Text::new("Done"))
+                                .style(theme)
+                                .on_press(Message::SettingsToggled)
+                                .into(),
+                        ])
+                        .spacing(16),
+                    )
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .padding(16)
+                }
+                (None, None, None, None) => {
+                    Container::new(Scrollable::new(&mut self.scroll_state).push(list))
+                        .height(Length::Fill)
+                }
+            }
+            .padding(1)
+            .style(style::ListContainer(theme))
+            .into(),
+        ]);
+        if let Some(context_menu) = context_menu {
+            content = content.push(context_menu);
+        }
+
+        // The container carries the theme's text colour down to every widget that isn't styled otherwise
+        Container::new(content.push(Text::new(status)).padding(16).spacing(16))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .style(theme)
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The path of a file with the given name, in a folder that doesn't need to exist.
+    fn path(name: &str) -> PathBuf {
+        Path::new("folder").join(name)
+    }
+
+    /// An app listing files with the given names, as if they all existed, whose rule numbers them.
+    fn app(names: &[&str]) -> App {
+        let mut app = App::default();
+        app.entries = names
+            .iter()
+            .map(|name| Entry {
+                path: path(name),
+                text: path(name).to_string_lossy().into_owned(),
+                malformed: false,
+                missing: false,
+                size: None,
+                modified: None,
+                excluded: false,
+            })
+            .collect();
+        app.entry_paths = app.entries.iter().map(|e| e.path.clone()).collect();
+
+        let rule = &mut app.pipeline.rules_mut()[0];
+        rule.number = true;
+        rule.numbering.base = "file".to_owned();
+        app
+    }
+
+    #[test]
+    fn every_entry_is_renamed_without_selection_only() {
+        let mut app = app(&["a.txt", "b.txt", "c.txt"]);
+        app.listbox_state.set_selection(vec![false, true, false]);
+
+        let plan = app.plan_rename().unwrap();
+        assert_eq!(
+            plan.renames,
+            [
+                (path("a.txt"), path("file001.txt")),
+                (path("b.txt"), path("file002.txt")),
+                (path("c.txt"), path("file003.txt")),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_the_selection_is_renamed_and_numbered_with_selection_only() {
+        let mut app = app(&["a.txt", "b.txt", "c.txt", "d.txt"]);
+        app.selection_only = true;
+        app.listbox_state
+            .set_selection(vec![false, true, false, true]);
+
+        assert_eq!(app.scope(), [false, true, false, true]);
+        let plan = app.plan_rename().unwrap();
+        assert_eq!(
+            plan.renames,
+            [
+                (path("b.txt"), path("file001.txt")),
+                (path("d.txt"), path("file002.txt")),
+            ]
+        );
+        assert_eq!(plan.unchanged, 0);
+
+        // Entries out of scope keep their paths
+        assert_eq!(app.targets()[0], path("a.txt"));
+        assert_eq!(app.targets()[2], path("c.txt"));
+    }
+
+    #[test]
+    fn nothing_is_renamed_with_selection_only_and_nothing_selected() {
+        let mut app = app(&["a.txt", "b.txt"]);
+        app.selection_only = true;
+
+        assert_eq!(app.scope(), [false, false]);
+        assert!(app.plan_rename().unwrap().renames.is_empty());
+    }
+
+    #[test]
+    fn hidden_and_excluded_entries_are_never_in_the_selection() {
+        let mut app = app(&["a.txt", "b.log", "c.txt", "d.txt"]);
+        app.selection_only = true;
+        app.filter_input_value = ".txt".to_owned();
+        app.entries[3].excluded = true;
+        // The selection is of the visible entries, which are a.txt, c.txt and d.txt
+        app.listbox_state.set_selection(vec![false, true, true]);
+
+        assert_eq!(app.scope(), [false, false, true, false]);
+        let plan = app.plan_rename().unwrap();
+        assert_eq!(plan.renames, [(path("c.txt"), path("file001.txt"))]);
+    }
+
+    #[test]
+    fn deleted_selections_are_restored_while_waiting_for_confirmation() {
+        let mut app = app(&["a", "b", "c", "d", "e"]);
+        app.preferences.confirm_removal_threshold = 1;
+        app.listbox_state
+            .set_selection(vec![false, true, false, true, false]);
+
+        // The list forgets the deleted entries before the app hears about them
+        let positions = app.listbox_state.remove_selected();
+        app.remove_entries(&positions, true);
+        assert_eq!(app.entries.len(), 5);
+        assert_eq!(
+            app.pending_removal,
+            Some(HashSet::from([path("b"), path("d")]))
+        );
+        assert_eq!(app.listbox_state.selected_indices(), [1, 3]);
+
+        // Once they're confirmed, the rest of the selection stays with the remaining entries
+        app.forget_selection(&HashSet::from([path("b"), path("d")]));
+        assert!(app.listbox_state.selected_indices().is_empty());
+    }
+
+    #[test]
+    fn removing_one_entry_keeps_the_rest_of_the_selection_while_waiting_for_confirmation() {
+        let mut app = app(&["a", "b", "c", "d", "e"]);
+        app.preferences.confirm_removal_threshold = 0;
+        app.listbox_state
+            .set_selection(vec![true, false, true, true, false]);
+
+        // Removing an entry with its button takes it out of the selection, as the list does
+        app.listbox_state.remove_indices(&[2]);
+        app.remove_entries(&[2], false);
+        assert_eq!(app.pending_removal, Some(HashSet::from([path("c")])));
+        assert_eq!(app.listbox_state.selected_indices(), [0, 3]);
+
+        app.forget_selection(&HashSet::from([path("c")]));
+        assert_eq!(app.listbox_state.selected_indices(), [0, 2]);
+    }
+
+    #[test]
+    fn removed_entries_are_put_back_as_they_were() {
+        let mut app = app(&["a", "b", "c", "d"]);
+        app.entries[1].excluded = true;
+
+        let paths = HashSet::from([path("b"), path("d")]);
+        let removed = app.removed_entries(&paths);
+        app.retain_entries(|p| !paths.contains(p));
+        app.restore_entries(&removed);
+
+        let restored: Vec<_> = app.entries.iter().map(|e| (&e.path, e.excluded)).collect();
+        assert_eq!(
+            restored,
+            [
+                (&path("a"), false),
+                (&path("b"), true),
+                (&path("c"), false),
+                (&path("d"), false)
+            ]
+        );
+        assert_eq!(app.entry_paths.len(), 4);
+    }
+
+    #[test]
+    fn renamed_entries_are_found_even_if_the_list_changes_while_renaming() {
+        let mut app = app(&["a", "b", "c"]);
+        app.renaming = Some(Renaming {
+            renames: vec![
+                (path("c"), path("z")),
+                (path("a"), path("y")),
+                (path("z"), path("x")),
+                // Left unfinished, so that the renaming doesn't finish and save the session
+                (path("b"), path("w")),
+            ],
+            results: Vec::new(),
+            renamed: Vec::new(),
+            skipped: 0,
+            created_directories: Vec::new(),
+            emptied_directories: Vec::new(),
+            temporary: HashMap::new(),
+            positions: entry_positions(&app.entries),
+        });
+        let names = |app: &App| -> Vec<_> { app.entries.iter().map(|e| e.path.clone()).collect() };
+
+        let _ = app.record_renames(&[Ok(())]);
+        assert_eq!(names(&app), [path("a"), path("b"), path("z")]);
+
+        // Moving the entries leaves the recorded positions out of date
+        app.apply_order(&[2, 0, 1]);
+        let _ = app.record_renames(&[Ok(()), Ok(())]);
+        assert_eq!(names(&app), [path("x"), path("y"), path("b")]);
+        assert!(app.entry_paths.contains(&path("x")) && !app.entry_paths.contains(&path("z")));
+    }
+
+    #[test]
+    fn tab_moves_focus_from_the_list_through_the_buttons() {
+        let mut app = app(&["a.txt"]);
+        app.listbox_state.focus();
+
+        app.cycle_focus(true);
+        assert!(!app.listbox_state.is_focused());
+        assert_eq!(
+            app.keyboard_focused_button(),
+            Some(FocusableButton::OpenFiles)
+        );
+        app.cycle_focus(true);
+        app.cycle_focus(true);
+        assert_eq!(app.keyboard_focused_button(), Some(FocusableButton::Apply));
+
+        // Focus wraps around to the first input, and back again
+        app.cycle_focus(true);
+        assert_eq!(app.keyboard_focused_button(), None);
+        assert!(app.highlight_input_state.is_focused());
+        app.cycle_focus(false);
+        assert_eq!(app.keyboard_focused_button(), Some(FocusableButton::Apply));
+    }
+
+    #[test]
+    fn enter_only_confirms_renaming_when_the_confirmation_has_focus() {
+        let mut app = app(&["a.txt"]);
+        let _ = app.update(Message::EnterPressed);
+        assert!(app.plan.is_none());
+
+        let _ = app.update(Message::ApplyRename);
+        assert!(app.plan.is_some());
+        assert_eq!(
+            app.keyboard_focused_button(),
+            Some(FocusableButton::ConfirmRename)
+        );
+
+        // Typing in an input takes focus away from the confirmation
+        app.prefix_input_state.focus();
+        assert_eq!(app.keyboard_focused_button(), None);
+        let _ = app.update(Message::EnterPressed);
+        assert!(app.plan.is_some());
+
+        app.focus_button(FocusableButton::CancelRename);
+        let _ = app.update(Message::EnterPressed);
+        assert!(app.plan.is_none());
+    }
+
+    #[test]
+    fn selections_hidden_by_the_filter_are_kept_until_it_is_cleared() {
+        let mut app = app(&["a.txt", "b.log", "c.txt", "d.log"]);
+        app.listbox_state
+            .set_selection(vec![false, true, true, false]);
+
+        app.refilter(|app| app.filter_input_value = ".txt".to_owned());
+        assert_eq!(app.listbox_state.selected_indices(), [1]);
+        assert_eq!(app.hidden_selection, HashSet::from([path("b.log")]));
+
+        app.refilter(|app| app.filter_input_value.clear());
+        assert_eq!(app.listbox_state.selected_indices(), [1, 2]);
+        assert!(app.hidden_selection.is_empty());
+    }
+
+    #[test]
+    fn selecting_while_filtered_keeps_the_hidden_selection() {
+        let mut app = app(&["a.txt", "b.log", "c.txt", "d.log"]);
+        app.listbox_state
+            .set_selection(vec![false, false, true, true]);
+
+        app.refilter(|app| app.filter_input_value = ".txt".to_owned());
+        app.listbox_state.set_selection(vec![true, false]);
+        // Filtering again by something else carries the visible selection across too
+        app.refilter(|app| app.filter_input_value = "a".to_owned());
+        assert_eq!(app.listbox_state.selected_indices(), [0]);
+
+        app.refilter(|app| app.filter_input_value.clear());
+        assert_eq!(app.listbox_state.selected_indices(), [0, 3]);
+    }
+
+    #[test]
+    fn save_failures_are_kept_until_the_same_kind_saves() {
+        let mut app = app(&[]);
+        let failure = || Err(io::Error::new(io::ErrorKind::PermissionDenied, "denied"));
+
+        app.record_save("session", failure());
+        app.record_save("preferences", Ok(()));
+        assert!(matches!(app.save_error, Some(("session", _))));
+
+        app.record_save("preferences", failure());
+        assert!(matches!(app.save_error, Some(("preferences", _))));
+        app.record_save("preferences", Ok(()));
+        assert!(app.save_error.is_none());
+    }
+
+    #[test]
+    fn loading_a_plan_only_previews_it() {
+        let mut app = app(&["a.txt", "b.txt"]);
+        app.plan = app.plan_rename();
+        let mut pipeline = Pipeline::default();
+        pipeline.rules_mut()[0].prefix = "new_".to_owned();
+
+        app.use_saved_plan(SavedPlan::new("Prefix".to_owned(), pipeline, false, None));
+        assert!(app.plan.is_none());
+        assert_eq!(app.targets(), [path("new_a.txt"), path("new_b.txt")]);
+        assert_eq!(app.preset_name_value, "Prefix");
+    }
+
+    #[test]
+    fn plans_without_a_folder_never_move_files() {
+        let mut app = app(&["a.txt"]);
+        app.target_dir = Some(PathBuf::from("elsewhere"));
+
+        let plan = SavedPlan::new(String::new(), Pipeline::default(), true, None);
+        app.use_saved_plan(plan);
+        assert!(!app.move_to_dir);
+
+        let plan = SavedPlan::new(
+            String::new(),
+            Pipeline::default(),
+            true,
+            Some(PathBuf::from("moved")),
+        );
+        app.use_saved_plan(plan);
+        assert!(app.move_to_dir);
+        assert_eq!(app.target_dir, Some(PathBuf::from("moved")));
+    }
+
+    #[test]
+    fn the_preview_flags_the_same_names_as_the_plan() {
+        let mut app = app(&["notes.txt", "photo.jpg"]);
+        let rule = &mut app.pipeline.rules_mut()[0];
+        rule.number = false;
+        rule.set_find("notes".to_owned());
+
+        let targets = app.targets();
+        assert_eq!(
+            app.name_warnings(&targets, &app.scope()),
+            [Some(NameWarning::EmptyStem), None]
+        );
+        assert_eq!(
+            app.plan_rename().unwrap().name_warnings,
+            [Some(NameWarning::EmptyStem)]
+        );
+
+        // Entries out of scope aren't renamed, so there's nothing to flag
+        app.selection_only = true;
+        assert_eq!(app.name_warnings(&targets, &app.scope()), [None, None]);
+    }
+
+    #[test]
+    fn natural_order_compares_numbers_by_value() {
+        assert_eq!(natural_cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(natural_cmp("file10", "file9"), Ordering::Greater);
+        assert_eq!(natural_cmp("2 apples", "10 apples"), Ordering::Less);
+        assert_eq!(natural_cmp("a", "a1"), Ordering::Less);
+        assert_eq!(
+            natural_cmp("v99999999999999999999999", "v100000000000000000000000"),
+            Ordering::Less
+        );
+        // Leading zeroes don't change the value, but still give a consistent order
+        assert_eq!(natural_cmp("file02", "file3"), Ordering::Less);
+        assert_eq!(natural_cmp("file02", "file2"), Ordering::Less);
+        assert_eq!(natural_cmp("file2", "file02"), Ordering::Greater);
+        assert_eq!(natural_cmp("file2", "file2"), Ordering::Equal);
+    }
+
+    #[test]
+    fn lexicographic_order_compares_digit_by_digit() {
+        let a = Path::new("file10.txt");
+        let b = Path::new("file2.txt");
+        assert_eq!(compare_paths(a, b, false), Ordering::Less);
+        assert_eq!(compare_paths(a, b, true), Ordering::Greater);
+    }
+
+    #[test]
+    fn paths_are_compared_a_component_at_a_time() {
+        // As text, `-` comes before `/`, which would split up the files in the folder `a`
+        let (a, b) = (Path::new("a/z.txt"), Path::new("a-b/a.txt"));
+        assert_eq!(compare_paths(a, b, false), Ordering::Less);
+        assert_eq!(compare_paths(a, b, true), Ordering::Less);
+
+        let (a, b) = (Path::new("disc2/track10"), Path::new("disc10/track1"));
+        assert_eq!(compare_paths(a, b, true), Ordering::Less);
+        assert_eq!(compare_paths(a, b, false), Ordering::Greater);
+        assert_eq!(compare_paths(a, a, true), Ordering::Equal);
+    }
+
+    #[test]
+    fn scans_are_undone_separately_from_pastes() {
+        let mut app = app(&["a.txt"]);
+        app.scanning = Some(Scanning {
+            dir: path(""),
+            recursive: false,
+            found: 0,
+        });
+        let batch = |names: &[&str]| scan::ScanBatch {
+            files: names
+                .iter()
+                .map(|name| scan::FoundFile {
+                    path: path(name),
+                    metadata: None,
+                })
+                .collect(),
+            errors: Vec::new(),
+            // Directories are left to read, so that the scan doesn't finish and save the session
+            pending: vec![path("sub")],
+        };
+
+        let token = app.scan_token;
+        let _ = app.record_scan(token, &batch(&["a.txt", "b.txt"]));
+        app.record(ListEdit::Added(vec![path("pasted.txt")]));
+        let _ = app.record_scan(token, &batch(&["b.txt", "c.txt"]));
+
+        assert_eq!(app.entries.len(), 4);
+        assert_eq!(app.scanning.as_ref().map(|s| s.found), Some(2));
+        assert!(matches!(
+            &app.list_history[..],
+            [ListEdit::Scanned(t, scanned), ListEdit::Added(pasted)]
+                if *t == token
+                    && *scanned == [path("b.txt"), path("c.txt")]
+                    && *pasted == [path("pasted.txt")]
+        ));
+    }
+
+    #[test]
+    fn rules_that_change_nothing_rename_nothing() {
+        let mut app = app(&["a.txt", "b.txt", "c.txt"]);
+        app.pipeline.rules_mut()[0].number = false;
+
+        let plan = app.plan_rename().unwrap();
+        assert_eq!(plan.unchanged, 3);
+        assert!(plan.renames.is_empty());
+        assert!(plan.steps().is_empty());
+
+        let _ = app.apply_plan(plan);
+        assert!(app.renaming.is_none());
+        assert!(app.rename_results.is_empty());
+        assert_eq!(app.rename_skipped, 3);
+    }
+
+    #[test]
+    fn targets_differing_in_case_collide_where_the_platform_ignores_case() {
+        let app = app(&["a.txt", "b.txt"]);
+        let targets = [path("X.txt"), path("x.txt")];
+        let collisions = detect_collisions(
+            &app.entries,
+            &targets,
+            &Destination::SameDirectory,
+            &mut HashMap::new(),
+        );
+        assert_eq!(collisions, [plan::CASE_INSENSITIVE; 2]);
+    }
+
+    #[test]
+    fn collisions_only_check_the_disk_for_new_targets() {
+        let app = app(&["a.txt", "b.txt"]);
+        let targets = [path("c.txt"), path("d.txt")];
+        // The folder doesn't exist, so these only collide because of what's remembered about them
+        let mut existing = HashMap::from([(path("c.txt"), true), (path("old.txt"), true)]);
+        let collisions = detect_collisions(
+            &app.entries,
+            &targets,
+            &Destination::SameDirectory,
+            &mut existing,
+        );
+        assert_eq!(collisions, [true, false]);
+        assert_eq!(
+            existing,
+            HashMap::from([(path("c.txt"), true), (path("d.txt"), false)])
+        );
+    }
 }
@@ -0,0 +1,1587 @@
+//! Planning renames before they're applied.
+
+use crate::rename::{join_extension, split_extension};
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// A problem that stops a rename from being applied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Problem {
+    /// Another file in the plan would be renamed to the same path.
+    Duplicate,
+    /// A file that isn't being renamed away already exists at the target path.
+    Exists,
+    /// The directory that the file would be moved into doesn't exist.
+    MissingDirectory,
+    /// The target isn't a valid file name.
+    InvalidName(InvalidName),
+}
+
+/// Something about the new name of a rename that the user should check before it's applied, as found by
+/// [`name_warnings`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NameWarning {
+    /// Nothing would be left of the name but its extension, as when a replacement removes the whole stem
+    /// of `notes.txt` to leave `.txt`. Such names are valid, but almost never wanted, so they stop a plan
+    /// from being applied.
+    EmptyStem,
+    /// A file that keeps its name already has the same name, ignoring case, such as one in another folder.
+    /// Two files with the same name can't be in the same folder, which is reported as a [`Problem`]
+    /// instead, so these might well be intended, and don't stop a plan from being applied.
+    SharedName,
+}
+
+/// A reason that a file name isn't valid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidName {
+    /// The name is empty.
+    Empty,
+    /// The name is `.` or `..`, which refer to directories rather than naming a file.
+    Dots,
+    /// The name contains a path separator, which would move the file into another directory.
+    Separator,
+    /// The name contains a character that isn't allowed in file names on this platform.
+    IllegalChar(char),
+    /// The name is reserved for a device on this platform, such as `CON` or `NUL` on Windows.
+    ReservedName,
+    /// The name ends with a dot or a space, which this platform silently removes.
+    TrailingDotOrSpace,
+    /// The name is longer than [`MAX_NAME_LENGTH`], as measured by [`name_length`].
+    TooLong,
+}
+
+/// The longest that a file name can be on the usual filesystems, as measured by [`name_length`].
+pub const MAX_NAME_LENGTH: usize = 255;
+
+/// The length of the given file name as the usual filesystems on this platform limit it, which is in
+/// UTF-16 code units on Windows, and in bytes elsewhere. Either can be more than the number of
+/// characters, such as for names with accents or emoji.
+pub fn name_length(name: &str) -> usize {
+    if cfg!(windows) {
+        name.encode_utf16().count()
+    } else {
+        name.len()
+    }
+}
+
+/// Shortens the given file name to at most [`MAX_NAME_LENGTH`] by removing characters from the end of its
+/// stem. The extension is never shortened, so names whose extension alone is too long are left as they
+/// are, as are names that are already short enough.
+pub fn truncate_name(name: &str) -> Cow<'_, str> {
+    if name_length(name) <= MAX_NAME_LENGTH {
+        return Cow::Borrowed(name);
+    }
+
+    let (stem, extension) = split_extension(name);
+    let extension_length = extension.map_or(0, |e| name_length(e) + 1);
+    let budget = match MAX_NAME_LENGTH.checked_sub(extension_length) {
+        Some(budget) if budget > 0 => budget,
+        _ => return Cow::Borrowed(name),
+    };
+
+    // Whole characters are kept, so a multibyte character that doesn't fit is removed entirely
+    let mut length = 0;
+    let end = stem
+        .char_indices()
+        .find(|&(i, c)| {
+            length += name_length(&stem[i..i + c.len_utf8()]);
+            length > budget
+        })
+        .map_or(stem.len(), |(i, _)| i);
+    Cow::Owned(join_extension(&stem[..end], extension))
+}
+
+/// Gives each target that would collide a free name by appending ` (1)`, ` (2)` and so on to its stem, as
+/// file managers do when copying, given the path of each file and the target it would be renamed to.
+///
+/// A target collides if an earlier target, or the path of a file that isn't being renamed, is the same,
+/// or if a file that isn't being renamed away already exists there. Names that only differ in case are
+/// the same if the platform treats them that way, as described in [`CASE_INSENSITIVE`]. Targets are
+/// resolved in order, so the first file to claim a name keeps it, and the files after it that share the
+/// target are numbered in order. Targets whose file name isn't valid UTF-8 are left as they are.
+pub fn resolve_collisions(paths: &[PathBuf], targets: Vec<PathBuf>) -> Vec<PathBuf> {
+    // Files that are being renamed away don't collide with whatever's renamed to their old names
+    let moving: HashSet<PathBuf> = paths
+        .iter()
+        .zip(&targets)
+        .filter(|(path, target)| path != target)
+        .map(|(path, _)| collision_key(path))
+        .collect();
+    // Files that aren't being renamed keep their names, so those are taken before any others
+    let mut taken: HashSet<PathBuf> = paths
+        .iter()
+        .zip(&targets)
+        .filter(|(path, target)| path == target)
+        .map(|(path, _)| collision_key(path))
+        .collect();
+    // The next number to try for each target, so that each file sharing a target carries on counting from
+    // the one before it rather than starting again from 1
+    let mut counters = HashMap::<PathBuf, usize>::new();
+
+    paths
+        .iter()
+        .zip(targets)
+        .map(|(path, target)| {
+            if *path == target {
+                return target;
+            }
+
+            let counter = counters.entry(collision_key(&target)).or_insert(1);
+            let mut resolved = target.clone();
+            while taken.contains(&collision_key(&resolved))
+                || (target_exists(path, &resolved) && !moving.contains(&collision_key(&resolved)))
+            {
+                match numbered_path(&target, *counter) {
+                    Some(numbered) => resolved = numbered,
+                    None => break,
+                }
+                *counter += 1;
+            }
+            taken.insert(collision_key(&resolved));
+            resolved
+        })
+        .collect()
+}
+
+/// The given path as collisions between targets are found with, with its file name in lowercase if the
+/// platform treats names that only differ in case as the same.
+pub(crate) fn collision_key(path: &Path) -> PathBuf {
+    match lowercase_name(path) {
+        Some(name) if CASE_INSENSITIVE => path.with_file_name(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// The given path with ` (n)` appended to the stem of its file name, or `None` if it doesn't have a file
+/// name that's valid UTF-8.
+fn numbered_path(path: &Path, n: usize) -> Option<PathBuf> {
+    let (stem, extension) = split_extension(path.file_name()?.to_str()?);
+    Some(path.with_file_name(join_extension(&format!("{} ({})", stem, n), extension)))
+}
+
+/// Checks whether the given file name is valid. Separators, `NUL` and the `.` and `..` names are never
+/// valid, and on Windows, neither are the characters `<>:"|?*`, control characters, device names such as
+/// `CON`, or names ending with a dot or a space. Names longer than [`MAX_NAME_LENGTH`] aren't valid either.
+pub fn validate_filename(name: &str) -> Result<(), InvalidName> {
+    if name.is_empty() {
+        return Err(InvalidName::Empty);
+    }
+    if name_length(name) > MAX_NAME_LENGTH {
+        return Err(InvalidName::TooLong);
+    }
+    if name == "." || name == ".." {
+        return Err(InvalidName::Dots);
+    }
+    if name.contains('/') || (cfg!(windows) && name.contains('\\')) {
+        return Err(InvalidName::Separator);
+    }
+    if name.contains('\0') {
+        return Err(InvalidName::IllegalChar('\0'));
+    }
+
+    if cfg!(windows) {
+        if let Some(c) = name
+            .chars()
+            .find(|&c| matches!(c, '<' | '>' | ':' | '"' | '|' | '?' | '*') || c.is_control())
+        {
+            return Err(InvalidName::IllegalChar(c));
+        }
+        if name.ends_with('.') || name.ends_with(' ') {
+            return Err(InvalidName::TrailingDotOrSpace);
+        }
+
+        // Device names are reserved even with an extension, as in `nul.txt`
+        let stem = name.split('.').next().unwrap_or_default().trim_end();
+        let is_reserved = ["CON", "PRN", "AUX", "NUL"]
+            .iter()
+            .any(|r| stem.eq_ignore_ascii_case(r))
+            || ["COM", "LPT"].iter().any(|r| {
+                stem.len() == 4
+                    && stem.get(..3).map_or(false, |p| p.eq_ignore_ascii_case(r))
+                    && matches!(stem.as_bytes()[3], b'1'..=b'9')
+            });
+        if is_reserved {
+            return Err(InvalidName::ReservedName);
+        }
+    }
+
+    Ok(())
+}
+
+/// Where the files in a plan are allowed to be renamed to.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Destination {
+    /// Each file stays in the directory that it's already in.
+    SameDirectory,
+    /// Every file is moved into this directory.
+    Directory(PathBuf),
+    /// The names of the directories within this one can change too, which moves the files within them
+    /// into directories with the new names. Directories that don't exist yet are created as the plan is
+    /// applied.
+    RenamedDirectories(PathBuf),
+}
+
+/// Checks whether renaming the file at the given path to the given target would give it a valid name,
+/// and would keep it where the given destination allows.
+pub fn validate_target(
+    path: &Path,
+    target: &Path,
+    destination: &Destination,
+) -> Result<(), InvalidName> {
+    match destination {
+        Destination::SameDirectory if target.parent() != path.parent() => {
+            return Err(InvalidName::Separator)
+        }
+        Destination::Directory(directory) if target.parent() != Some(directory.as_path()) => {
+            return Err(InvalidName::Separator)
+        }
+        Destination::RenamedDirectories(base) => {
+            validate_directories(base, path.parent(), target.parent())?
+        }
+        _ => {}
+    }
+    match target.file_name() {
+        Some(name) => validate_filename(&name.to_string_lossy()),
+        None => Err(InvalidName::Empty),
+    }
+}
+
+/// Checks whether renaming the directories of the `old` directory below `base` would give them valid
+/// names, as the `new` directory. Both have to be within `base`, and at the same depth, so that renaming
+/// directories can't move files anywhere else.
+fn validate_directories(
+    base: &Path,
+    old: Option<&Path>,
+    new: Option<&Path>,
+) -> Result<(), InvalidName> {
+    let old = old.and_then(|d| d.strip_prefix(base).ok());
+    let new = new.and_then(|d| d.strip_prefix(base).ok());
+    let (old, new) = match (old, new) {
+        (Some(old), Some(new)) => (old, new),
+        _ => return Err(InvalidName::Separator),
+    };
+    if old.components().count() != new.components().count() {
+        return Err(InvalidName::Separator);
+    }
+    old.components()
+        .zip(new.components())
+        .filter(|(old, new)| old != new)
+        .try_for_each(|(_, new)| validate_filename(&new.as_os_str().to_string_lossy()))
+}
+
+/// Every change that applying a rule would make, computed before anything on disk is touched.
+///
+/// A plan is applied exactly as it was shown, rather than recomputing the targets, so that nothing
+/// that changed after it was made can sneak into the renames.
+#[derive(Clone, Debug, Default)]
+pub struct RenamePlan {
+    /// The `(old, new)` paths of every file that would be renamed, in the order they're renamed.
+    pub renames: Vec<(PathBuf, PathBuf)>,
+    /// How many files would keep their current name.
+    pub unchanged: usize,
+    /// The `(old, new)` paths of every rename that can't be applied, and why.
+    pub problems: Vec<(PathBuf, PathBuf, Problem)>,
+    /// The `(old, new)` paths of every directory that would be renamed, and how many files would be
+    /// moved from the old directory into the new one. See [`Destination::RenamedDirectories`].
+    pub renamed_directories: Vec<(PathBuf, PathBuf, usize)>,
+    /// The paths of every directory that doesn't exist yet, which would be created for the files moved
+    /// into it.
+    pub created_directories: Vec<PathBuf>,
+    /// Anything the user should check about the new name of each of the [`renames`](Self::renames), in
+    /// the same order.
+    pub name_warnings: Vec<Option<NameWarning>>,
+}
+
+impl RenamePlan {
+    /// Plans renaming each of the given paths to its paired target, which has to be wherever the given
+    /// destination allows. Pairs whose target is the same as their path are counted as unchanged.
+    pub fn new(
+        pairs: impl IntoIterator<Item = (PathBuf, PathBuf)>,
+        destination: &Destination,
+    ) -> Self {
+        let mut plan = Self::default();
+        let pairs: Vec<_> = pairs.into_iter().collect();
+
+        // Targets are counted as the platform compares them, so that names that only differ in case are
+        // duplicates where they'd name the same file
+        let mut counts = HashMap::<PathBuf, usize>::new();
+        for (_, target) in &pairs {
+            *counts.entry(collision_key(target)).or_default() += 1;
+        }
+
+        // A target can already exist if the file there is itself being renamed away
+        let moving: HashSet<PathBuf> = pairs
+            .iter()
+            .filter(|(path, target)| path != target)
+            .map(|(path, _)| collision_key(path))
+            .collect();
+        let is_missing = match destination {
+            Destination::Directory(directory) => !directory.is_dir(),
+            _ => false,
+        };
+        let warnings = name_warnings(pairs.iter().map(|(p, t)| (p.as_path(), t.as_path())));
+
+        for ((path, target), warning) in pairs.iter().zip(warnings) {
+            if path == target {
+                plan.unchanged += 1;
+                continue;
+            }
+
+            let problem = if let Err(e) = validate_target(path, target, destination) {
+                Some(Problem::InvalidName(e))
+            } else if is_missing {
+                Some(Problem::MissingDirectory)
+            } else if counts[&collision_key(target)] > 1 {
+                Some(Problem::Duplicate)
+            } else if target_exists(path, target) && !moving.contains(&collision_key(target)) {
+                Some(Problem::Exists)
+            } else {
+                None
+            };
+
+            match problem {
+                Some(problem) => plan.problems.push((path.clone(), target.clone(), problem)),
+                None => {
+                    plan.renames.push((path.clone(), target.clone()));
+                    plan.name_warnings.push(warning);
+                }
+            }
+        }
+
+        if let Destination::RenamedDirectories(_) = destination {
+            plan.find_renamed_directories();
+        }
+        plan
+    }
+
+    /// Fills in the directories that the renames would rename or create, when directories are renamed.
+    /// Several files in the same directory share a single entry, so that it's clear that they're all
+    /// moved together.
+    fn find_renamed_directories(&mut self) {
+        let mut counts = HashMap::<(&Path, &Path), usize>::new();
+        for (old, new) in &self.renames {
+            if let (Some(old), Some(new)) = (old.parent(), new.parent()) {
+                if old != new {
+                    *counts.entry((old, new)).or_default() += 1;
+                }
+            }
+        }
+
+        let mut renamed: Vec<_> = counts
+            .into_iter()
+            .map(|((old, new), count)| (old.to_path_buf(), new.to_path_buf(), count))
+            .collect();
+        renamed.sort();
+        let mut created: Vec<_> = renamed
+            .iter()
+            .map(|(_, new, _)| new.clone())
+            .filter(|new| !new.is_dir())
+            .collect();
+        created.sort();
+        created.dedup();
+
+        self.renamed_directories = renamed;
+        self.created_directories = created;
+    }
+
+    /// The old directories that renaming directories would move every file out of, deepest first, which
+    /// are each renamed directory along with those of its parents that are renamed too. They're only left
+    /// empty if they don't hold anything else, such as files that weren't loaded.
+    pub fn emptied_directories(&self) -> Vec<PathBuf> {
+        let mut emptied: Vec<_> = self
+            .renamed_directories
+            .iter()
+            .flat_map(|(old, new, _)| {
+                old.ancestors()
+                    .zip(new.ancestors())
+                    .take_while(|(old, new)| old != new)
+                    .map(|(old, _)| old.to_path_buf())
+            })
+            .collect();
+        emptied.sort_by(|a, b| {
+            let depth = |d: &PathBuf| d.components().count();
+            depth(b).cmp(&depth(a)).then_with(|| a.cmp(b))
+        });
+        emptied.dedup();
+        emptied
+    }
+
+    /// Whether any problems stop this plan from being applied, including renames that would leave a file
+    /// with an empty name, as described in [`NameWarning::EmptyStem`].
+    pub fn is_blocked(&self) -> bool {
+        !self.problems.is_empty() || self.name_warnings.contains(&Some(NameWarning::EmptyStem))
+    }
+
+    /// The `(old, new)` paths of every rename needed to apply this plan, in an order where no file is
+    /// renamed onto another that hasn't been moved out of the way yet.
+    ///
+    /// Renames that form a cycle, such as swapping two names, can't be ordered like that, so one file in
+    /// each cycle is first renamed to a temporary name and then renamed to its target after the rest of
+    /// the cycle. Renames that only change the case of a name also go through a temporary name, as
+    /// described in [`rename_steps`]. Plans without either never use temporary names.
+    pub fn steps(&self) -> Vec<(PathBuf, PathBuf)> {
+        // Each rename waits for the file at its target to be renamed away first, so the renames form chains
+        // that are applied from their free end back, and cycles that have no free end at all
+        let sources: HashSet<&Path> = self.renames.iter().map(|(old, _)| old.as_path()).collect();
+        let targets: HashMap<&Path, usize> = self
+            .renames
+            .iter()
+            .enumerate()
+            .map(|(i, (_, new))| (new.as_path(), i))
+            .collect();
+        let mut done = vec![false; self.renames.len()];
+        let mut steps = Vec::with_capacity(self.renames.len());
+
+        for (i, (_, new)) in self.renames.iter().enumerate() {
+            if !sources.contains(new.as_path()) {
+                self.follow_chain(Some(i), &targets, &mut done, &mut steps);
+            }
+        }
+
+        // Everything left is in a cycle, so each cycle is broken by moving one of its files out of the way
+        for (i, (old, new)) in self.renames.iter().enumerate() {
+            if done[i] {
+                continue;
+            }
+            done[i] = true;
+            let temp = temporary_path(old);
+            steps.push((old.clone(), temp.clone()));
+            self.follow_chain(
+                targets.get(old.as_path()).copied(),
+                &targets,
+                &mut done,
+                &mut steps,
+            );
+            steps.push((temp, new.clone()));
+        }
+
+        steps
+            .into_iter()
+            .flat_map(|(old, new)| rename_steps(old, new))
+            .collect()
+    }
+
+    /// Adds the rename at the given index to the given steps, followed by the rename waiting for its old
+    /// name, and so on back along the chain until a rename that's already done, given the index of the
+    /// rename to each target.
+    fn follow_chain(
+        &self,
+        mut next: Option<usize>,
+        targets: &HashMap<&Path, usize>,
+        done: &mut [bool],
+        steps: &mut Vec<(PathBuf, PathBuf)>,
+    ) {
+        while let Some(i) = next.filter(|&i| !done[i]) {
+            done[i] = true;
+            let (old, new) = &self.renames[i];
+            steps.push((old.clone(), new.clone()));
+            next = targets.get(old.as_path()).copied();
+        }
+    }
+
+    /// The positions in [`renames`](Self::renames) of every rename that would leave a file with an empty
+    /// name, as described in [`NameWarning::EmptyStem`].
+    pub fn empty_name_indices(&self) -> Vec<usize> {
+        self.warning_indices(NameWarning::EmptyStem)
+    }
+
+    /// The positions in [`renames`](Self::renames) of every rename that would give a file the same name
+    /// as a file that's kept, as described in [`NameWarning::SharedName`].
+    pub fn shared_name_indices(&self) -> Vec<usize> {
+        self.warning_indices(NameWarning::SharedName)
+    }
+
+    /// The positions in [`renames`](Self::renames) of every rename with the given warning.
+    fn warning_indices(&self, warning: NameWarning) -> Vec<usize> {
+        self.name_warnings
+            .iter()
+            .enumerate()
+            .filter(|&(_, &w)| w == Some(warning))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Describes this plan in a single line, such as for a heading above the list of changes.
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} files will be renamed, {} unchanged",
+            self.renames.len(),
+            self.unchanged
+        );
+        if !self.problems.is_empty() {
+            summary += &format!(", {} with problems", self.problems.len());
+        }
+        let empty = self.empty_name_indices().len();
+        if empty > 0 {
+            summary += &format!(", {} with empty names", empty);
+        }
+        let duplicates = self.shared_name_indices().len();
+        if duplicates > 0 {
+            summary += &format!(", {} sharing a name with an unchanged file", duplicates);
+        }
+        if !self.renamed_directories.is_empty() {
+            summary += &format!(
+                ", moving files between {} folders",
+                self.renamed_directories.len()
+            );
+        }
+        summary
+    }
+}
+
+/// Finds anything the user should check about the new names of the given `(path, target)` pairs, as
+/// described in [`NameWarning`], giving the warning for each pair in order. Pairs whose target is the same
+/// as their path keep their name, so they're never warned about, but other names are checked against
+/// theirs.
+pub fn name_warnings<'a>(
+    pairs: impl IntoIterator<Item = (&'a Path, &'a Path)>,
+) -> Vec<Option<NameWarning>> {
+    let pairs: Vec<_> = pairs.into_iter().collect();
+    let kept: HashSet<String> = pairs
+        .iter()
+        .filter(|(path, target)| path == target)
+        .filter_map(|(path, _)| lowercase_name(path))
+        .collect();
+
+    pairs
+        .iter()
+        .map(|&(path, target)| {
+            if path == target {
+                None
+            } else if has_empty_stem(path, target) {
+                Some(NameWarning::EmptyStem)
+            } else if lowercase_name(target).map_or(false, |n| kept.contains(&n)) {
+                Some(NameWarning::SharedName)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Whether renaming the file at `path` to `target` would leave nothing of its name but an extension. Names
+/// that already started with a dot, such as `.gitignore`, or that don't have an extension to be left
+/// with, aren't counted.
+fn has_empty_stem(path: &Path, target: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let target_name = target.file_name().unwrap_or_default().to_string_lossy();
+    target_name.is_empty()
+        || (target_name.starts_with('.') && !name.starts_with('.') && name.contains('.'))
+}
+
+/// The file name of the given path in lowercase, for comparing names regardless of case.
+fn lowercase_name(path: &Path) -> Option<String> {
+    path.file_name().map(|n| n.to_string_lossy().to_lowercase())
+}
+
+/// Whether the usual filesystems on this platform treat names that only differ in case as the same name.
+pub const CASE_INSENSITIVE: bool = cfg!(any(windows, target_os = "macos"));
+
+/// Whether renaming the file at `path` to `target` would only change the case of its name, as in
+/// `File.txt` to `file.txt`.
+pub fn is_case_only(path: &Path, target: &Path) -> bool {
+    path != target
+        && path.parent() == target.parent()
+        && match (path.file_name(), target.file_name()) {
+            (Some(a), Some(b)) => {
+                a.to_string_lossy().to_lowercase() == b.to_string_lossy().to_lowercase()
+            }
+            _ => false,
+        }
+}
+
+/// Whether a file other than the one at `path` already exists at `target`.
+///
+/// On platforms whose filesystems ignore case, a target that only differs from the path in case refers
+/// to the same file, so it doesn't count.
+pub fn target_exists(path: &Path, target: &Path) -> bool {
+    is_other_file(path, target, target.exists())
+}
+
+/// Whether a file other than the one at `path` is at `target`, as [`target_exists`] checks, given whether
+/// anything exists at `target`, such as when that was already checked.
+pub fn is_other_file(path: &Path, target: &Path, exists: bool) -> bool {
+    exists && !(CASE_INSENSITIVE && is_case_only(path, target))
+}
+
+/// The `(old, new)` paths of the renames needed to rename the file at `old` to `new`.
+///
+/// This is usually just the one rename, but on platforms whose filesystems ignore case, renaming a file
+/// to a name that only differs in case can fail or do nothing, so it's renamed to a temporary name first
+/// and then to its new name.
+pub fn rename_steps(old: PathBuf, new: PathBuf) -> Vec<(PathBuf, PathBuf)> {
+    if CASE_INSENSITIVE && is_case_only(&old, &new) {
+        let temp = temporary_path(&old);
+        vec![(old, temp.clone()), (temp, new)]
+    } else {
+        vec![(old, new)]
+    }
+}
+
+/// Renames the file at `old` to `new` through whichever steps it needs, as described in
+/// [`rename_steps`].
+///
+/// If a step fails, the steps before it are undone, so that the file isn't left behind under a temporary
+/// name. If even that fails, the error says where the file was left.
+pub fn rename_through_steps(old: &Path, new: &Path) -> io::Result<()> {
+    apply_steps(&rename_steps(old.to_path_buf(), new.to_path_buf()))
+}
+
+/// Applies the given `(old, new)` renames in order, undoing the ones already applied if any of them fails.
+fn apply_steps(steps: &[(PathBuf, PathBuf)]) -> io::Result<()> {
+    for (applied, (old, new)) in steps.iter().enumerate() {
+        if let Err(e) = rename_file(old, new) {
+            for (old, new) in steps[..applied].iter().rev() {
+                if rename_file(new, old).is_err() {
+                    return Err(io::Error::new(
+                        e.kind(),
+                        format!("{} (the file was left at {})", e, new.display()),
+                    ));
+                }
+            }
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Finds an unused name in the same directory as the given path, to move it out of the way temporarily.
+fn temporary_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    (0..)
+        .map(|n| path.with_file_name(format!(".{}.{}.tmp", name, n)))
+        .find(|p| !p.exists())
+        .unwrap()
+}
+
+/// Renames the file at `old` to `new`, unless a file already exists at `new`.
+///
+/// Unlike [`std::fs::rename`], this never replaces an existing file, so that if a rename in the middle of
+/// a plan fails, the renames after it can't overwrite a file that hasn't been moved out of the way.
+///
+/// Files can't be renamed onto a different filesystem, so those are moved with [`copy_and_delete`]
+/// instead. The directory that the file is renamed into has to exist already, as the directories of a
+/// plan are created by [`create_directories`] before any file is renamed.
+pub fn rename_file(old: &Path, new: &Path) -> io::Result<()> {
+    if new.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "a file with that name already exists",
+        ));
+    }
+    match fs::rename(old, new) {
+        Err(e) if e.raw_os_error() == Some(CROSS_DEVICE_ERROR) => copy_and_delete(old, new),
+        result => result,
+    }
+}
+
+/// Creates each of the given directories, along with any of their parents that don't exist yet, giving
+/// every directory that was created, with parents before the directories within them.
+///
+/// If a directory can't be created, the ones that were already created are removed again.
+pub fn create_directories(directories: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut created = Vec::new();
+    for directory in directories {
+        let mut missing: Vec<_> = directory
+            .ancestors()
+            .take_while(|d| !d.as_os_str().is_empty() && !d.is_dir())
+            .collect();
+        missing.reverse();
+        for directory in missing {
+            if let Err(e) = fs::create_dir(directory) {
+                remove_empty_directories(created.iter().rev());
+                return Err(e);
+            }
+            created.push(directory.to_path_buf());
+        }
+    }
+    Ok(created)
+}
+
+/// Removes each of the given directories that's empty, in order, giving every directory that was removed.
+/// Directories that aren't empty are left as they are.
+pub fn remove_empty_directories<'a>(
+    directories: impl IntoIterator<Item = &'a PathBuf>,
+) -> Vec<PathBuf> {
+    directories
+        .into_iter()
+        .filter(|d| fs::remove_dir(d).is_ok())
+        .cloned()
+        .collect()
+}
+
+/// The error code for renaming a file onto a different filesystem, which is `EXDEV` on Unix and
+/// `ERROR_NOT_SAME_DEVICE` on Windows.
+const CROSS_DEVICE_ERROR: i32 = if cfg!(windows) { 17 } else { 18 };
+
+/// Moves the file at `old` to `new` by copying it and then deleting the original, for when it can't be
+/// renamed because they're on different filesystems.
+///
+/// The copy never replaces an existing file, and if anything fails, whatever was copied is removed again,
+/// so that the file is left where it was rather than being partly copied or kept in both places.
+/// Directories can't be moved like this, and are rejected.
+fn copy_and_delete(old: &Path, new: &Path) -> io::Result<()> {
+    let mut source = fs::File::open(old)?;
+    let metadata = source.metadata()?;
+    if metadata.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "folders can't be moved onto another drive",
+        ));
+    }
+
+    let mut target = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(new)?;
+    let copied = io::copy(&mut source, &mut target)
+        .and_then(|_| target.set_permissions(metadata.permissions()));
+    drop(target);
+    copied.and_then(|()| fs::remove_file(old)).inspect_err(|_| {
+        let _ = fs::remove_file(new);
+    })
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Duplicate => f.write_str("another file would get the same name"),
+            Problem::Exists => f.write_str("a file with that name already exists"),
+            Problem::MissingDirectory => f.write_str("the folder doesn't exist"),
+            Problem::InvalidName(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl fmt::Display for NameWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NameWarning::EmptyStem => f.write_str("only the extension would be left"),
+            NameWarning::SharedName => f.write_str("an unchanged file already has this name"),
+        }
+    }
+}
+
+impl fmt::Display for InvalidName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidName::Empty => f.write_str("the name is empty"),
+            InvalidName::Dots => f.write_str("the name refers to a directory"),
+            InvalidName::Separator => f.write_str("the name contains a path separator"),
+            InvalidName::IllegalChar(c) => {
+                write!(f, "the name contains the illegal character {:?}", c)
+            }
+            InvalidName::ReservedName => f.write_str("the name is reserved by the system"),
+            InvalidName::TrailingDotOrSpace => f.write_str("the name ends with a dot or space"),
+            InvalidName::TooLong => write!(
+                f,
+                "the name is longer than {} {}",
+                MAX_NAME_LENGTH,
+                if cfg!(windows) { "characters" } else { "bytes" }
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    /// A directory of files for a test, which is deleted along with them once it's dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        /// Creates an empty directory, named after the test it's for, holding files with the given names,
+        /// each of which contains its own name.
+        fn with_files(test: &str, names: &[&str]) -> Self {
+            let dir = std::env::temp_dir().join(format!("mass_renamer_{}_{}", test, process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            for name in names {
+                fs::write(dir.join(name), name).unwrap();
+            }
+            Self(dir)
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+
+        /// The `(name, contents)` of every file in the directory, sorted by name.
+        fn files(&self) -> Vec<(String, String)> {
+            let mut files: Vec<_> = fs::read_dir(&self.0)
+                .unwrap()
+                .map(|e| {
+                    let path = e.unwrap().path();
+                    let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                    (name, fs::read_to_string(&path).unwrap())
+                })
+                .collect();
+            files.sort();
+            files
+        }
+
+        /// Plans renaming each of the given files to its paired name within the directory.
+        fn plan(&self, renames: &[(&str, &str)]) -> RenamePlan {
+            let pairs = renames
+                .iter()
+                .map(|(old, new)| (self.path(old), self.path(new)));
+            RenamePlan::new(pairs, &Destination::SameDirectory)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// Applies the given steps in order, stopping at the first that fails.
+    fn apply(steps: &[(PathBuf, PathBuf)]) -> io::Result<()> {
+        steps
+            .iter()
+            .try_for_each(|(old, new)| rename_file(old, new))
+    }
+
+    fn file(name: &str, contents: &str) -> (String, String) {
+        (name.to_owned(), contents.to_owned())
+    }
+
+    #[test]
+    fn reserved_names_are_only_invalid_on_windows() {
+        for name in ["CON", "con", "nul.txt", "com1.txt", "LPT9", "aux .tar.gz"] {
+            let expected = if cfg!(windows) {
+                Err(InvalidName::ReservedName)
+            } else {
+                Ok(())
+            };
+            assert_eq!(validate_filename(name), expected, "{}", name);
+        }
+
+        // Only the device names themselves are reserved
+        for name in ["CONSOLE", "com0.txt", "com10", "nul_file.txt", "my con.txt"] {
+            assert_eq!(validate_filename(name), Ok(()), "{}", name);
+        }
+    }
+
+    #[test]
+    fn trailing_dots_and_spaces_are_only_invalid_on_windows() {
+        for name in ["file.", "file ", "file.txt.", "file.txt "] {
+            let expected = if cfg!(windows) {
+                Err(InvalidName::TrailingDotOrSpace)
+            } else {
+                Ok(())
+            };
+            assert_eq!(validate_filename(name), expected, "{}", name);
+        }
+
+        assert_eq!(validate_filename(" file.txt"), Ok(()));
+        assert_eq!(validate_filename(".hidden"), Ok(()));
+    }
+
+    #[test]
+    fn universally_invalid_names_are_caught_everywhere() {
+        assert_eq!(validate_filename(""), Err(InvalidName::Empty));
+        assert_eq!(validate_filename("."), Err(InvalidName::Dots));
+        assert_eq!(validate_filename(".."), Err(InvalidName::Dots));
+        assert_eq!(validate_filename("a/b"), Err(InvalidName::Separator));
+        assert_eq!(
+            validate_filename("a\0b"),
+            Err(InvalidName::IllegalChar('\0'))
+        );
+        assert_eq!(
+            validate_filename(&"a".repeat(MAX_NAME_LENGTH + 1)),
+            Err(InvalidName::TooLong)
+        );
+        assert_eq!(validate_filename(&"a".repeat(MAX_NAME_LENGTH)), Ok(()));
+    }
+
+    #[test]
+    fn windows_characters_are_only_invalid_on_windows() {
+        for (name, c) in [
+            ("a:b", ':'),
+            ("what?", '?'),
+            ("a*b", '*'),
+            ("tab\tname", '\t'),
+        ] {
+            let expected = if cfg!(windows) {
+                Err(InvalidName::IllegalChar(c))
+            } else {
+                Ok(())
+            };
+            assert_eq!(validate_filename(name), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn swapping_two_names_goes_through_a_temporary_name() {
+        let dir = TempDir::with_files("swap", &["a", "b"]);
+        let plan = dir.plan(&[("a", "b"), ("b", "a")]);
+        assert!(!plan.is_blocked());
+
+        let steps = plan.steps();
+        assert_eq!(steps.len(), 3);
+        apply(&steps).unwrap();
+        assert_eq!(dir.files(), [file("a", "b"), file("b", "a")]);
+    }
+
+    #[test]
+    fn rotating_three_names_goes_through_a_temporary_name() {
+        let dir = TempDir::with_files("rotation", &["a", "b", "c"]);
+        let plan = dir.plan(&[("a", "b"), ("b", "c"), ("c", "a")]);
+
+        let steps = plan.steps();
+        assert_eq!(steps.len(), 4);
+        apply(&steps).unwrap();
+        assert_eq!(
+            dir.files(),
+            [file("a", "c"), file("b", "a"), file("c", "b")]
+        );
+    }
+
+    #[test]
+    fn chains_are_ordered_without_temporary_names() {
+        let dir = TempDir::with_files("chain", &["a", "b"]);
+        let plan = dir.plan(&[("a", "b"), ("b", "c")]);
+
+        let steps = plan.steps();
+        assert_eq!(
+            steps,
+            [
+                (dir.path("b"), dir.path("c")),
+                (dir.path("a"), dir.path("b")),
+            ]
+        );
+        apply(&steps).unwrap();
+        assert_eq!(dir.files(), [file("b", "a"), file("c", "b")]);
+    }
+
+    #[test]
+    fn failing_part_way_through_loses_nothing() {
+        let renames = [("a", "b"), ("b", "c"), ("c", "a")];
+        let count = TempDir::with_files("failure_steps", &["a", "b", "c"])
+            .plan(&renames)
+            .steps()
+            .len();
+
+        // However many steps succeed before one fails, every file is still somewhere
+        for succeeded in 0..count {
+            let dir = TempDir::with_files("failure", &["a", "b", "c"]);
+            apply(&dir.plan(&renames).steps()[..succeeded]).unwrap();
+
+            let mut contents: Vec<_> = dir.files().into_iter().map(|(_, c)| c).collect();
+            contents.sort();
+            assert_eq!(contents, ["a", "b", "c"], "after {} steps", succeeded);
+        }
+    }
+
+    #[test]
+    fn renaming_onto_an_existing_file_fails_without_replacing_it() {
+        let dir = TempDir::with_files("existing", &["a", "b"]);
+        let error = rename_file(&dir.path("a"), &dir.path("b")).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(dir.files(), [file("a", "a"), file("b", "b")]);
+    }
+
+    /// Plans moving each of the given files in the directory into the directory's `moved` subdirectory.
+    fn plan_moves(dir: &TempDir, names: &[&str], destination: &Destination) -> RenamePlan {
+        let pairs = names
+            .iter()
+            .map(|name| (dir.path(name), dir.path("moved").join(name)));
+        RenamePlan::new(pairs, destination)
+    }
+
+    #[test]
+    fn same_directory_plans_keep_files_where_they_are() {
+        let dir = TempDir::with_files("same_directory_plans", &["a.txt", "b.txt"]);
+        let plan = dir.plan(&[("a.txt", "c.txt")]);
+        assert_eq!(plan.renames, [(dir.path("a.txt"), dir.path("c.txt"))]);
+        assert!(plan.problems.is_empty());
+
+        fs::create_dir(dir.path("moved")).unwrap();
+        let plan = plan_moves(&dir, &["b.txt"], &Destination::SameDirectory);
+        assert!(plan.renames.is_empty());
+        assert_eq!(
+            plan.problems,
+            [(
+                dir.path("b.txt"),
+                dir.path("moved").join("b.txt"),
+                Problem::InvalidName(InvalidName::Separator)
+            )]
+        );
+    }
+
+    #[test]
+    fn directory_plans_move_files_into_the_directory() {
+        let dir = TempDir::with_files("directory_plans", &["a.txt", "b.txt"]);
+        fs::create_dir(dir.path("moved")).unwrap();
+        let destination = Destination::Directory(dir.path("moved"));
+
+        let plan = plan_moves(&dir, &["a.txt", "b.txt"], &destination);
+        assert!(plan.problems.is_empty());
+        assert_eq!(plan.renames.len(), 2);
+
+        // Files can't be renamed within their own directory when they're meant to be moved
+        let plan = RenamePlan::new([(dir.path("a.txt"), dir.path("c.txt"))], &destination);
+        assert_eq!(
+            plan.problems,
+            [(
+                dir.path("a.txt"),
+                dir.path("c.txt"),
+                Problem::InvalidName(InvalidName::Separator)
+            )]
+        );
+
+        apply(&plan_moves(&dir, &["a.txt"], &destination).steps()).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path("moved").join("a.txt")).unwrap(),
+            "a.txt"
+        );
+        assert!(!dir.path("a.txt").exists());
+    }
+
+    #[test]
+    fn directory_plans_report_missing_directories() {
+        let dir = TempDir::with_files("missing_directory_plans", &["a.txt"]);
+        let destination = Destination::Directory(dir.path("moved"));
+        let plan = plan_moves(&dir, &["a.txt"], &destination);
+        assert!(plan.renames.is_empty());
+        assert_eq!(plan.problems[0].2, Problem::MissingDirectory);
+
+        fs::create_dir(dir.path("moved")).unwrap();
+        assert!(plan_moves(&dir, &["a.txt"], &destination)
+            .problems
+            .is_empty());
+    }
+
+    #[test]
+    fn directory_plans_report_files_already_in_the_directory() {
+        let dir = TempDir::with_files("existing_directory_plans", &["a.txt"]);
+        fs::create_dir(dir.path("moved")).unwrap();
+        fs::write(dir.path("moved").join("a.txt"), "other").unwrap();
+        let destination = Destination::Directory(dir.path("moved"));
+        let plan = plan_moves(&dir, &["a.txt"], &destination);
+        assert_eq!(plan.problems[0].2, Problem::Exists);
+    }
+
+    #[test]
+    fn copying_moves_the_file() {
+        let dir = TempDir::with_files("copying_moves", &["a.txt"]);
+        copy_and_delete(&dir.path("a.txt"), &dir.path("b.txt")).unwrap();
+        assert_eq!(dir.files(), [("b.txt".to_owned(), "a.txt".to_owned())]);
+    }
+
+    #[test]
+    fn copying_never_replaces_an_existing_file() {
+        let dir = TempDir::with_files("copying_never_replaces", &["a.txt", "b.txt"]);
+        let e = copy_and_delete(&dir.path("a.txt"), &dir.path("b.txt")).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(
+            dir.files(),
+            [
+                ("a.txt".to_owned(), "a.txt".to_owned()),
+                ("b.txt".to_owned(), "b.txt".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn copying_rejects_directories() {
+        let dir = TempDir::with_files("copying_rejects_directories", &[]);
+        fs::create_dir(dir.path("folder")).unwrap();
+        assert!(copy_and_delete(&dir.path("folder"), &dir.path("moved")).is_err());
+        assert!(dir.path("folder").is_dir());
+        assert!(!dir.path("moved").exists());
+    }
+
+    #[test]
+    fn case_only_changes_are_detected() {
+        assert!(is_case_only(
+            Path::new("a/File.txt"),
+            Path::new("a/file.txt")
+        ));
+        assert!(is_case_only(
+            Path::new("a/FILE.TXT"),
+            Path::new("a/File.txt")
+        ));
+        assert!(!is_case_only(
+            Path::new("a/file.txt"),
+            Path::new("a/file.txt")
+        ));
+        assert!(!is_case_only(
+            Path::new("a/File.txt"),
+            Path::new("a/other.txt")
+        ));
+        // Moving a file into another folder isn't only a change of case
+        assert!(!is_case_only(
+            Path::new("a/File.txt"),
+            Path::new("b/file.txt")
+        ));
+    }
+
+    #[test]
+    fn case_only_renames_only_go_through_a_temporary_name_where_case_is_ignored() {
+        let dir = TempDir::with_files("case_only_steps", &["File.txt"]);
+        let steps = dir.plan(&[("File.txt", "file.txt")]).steps();
+        if cfg!(any(windows, target_os = "macos")) {
+            assert_eq!(steps.len(), 2);
+            assert_eq!(steps[0].0, dir.path("File.txt"));
+            assert_eq!(steps[0].1, steps[1].0);
+            assert_eq!(steps[1].1, dir.path("file.txt"));
+        } else {
+            assert_eq!(steps, [(dir.path("File.txt"), dir.path("file.txt"))]);
+        }
+
+        rename_through_steps(&dir.path("File.txt"), &dir.path("file.txt")).unwrap();
+        assert_eq!(
+            dir.files(),
+            [("file.txt".to_owned(), "File.txt".to_owned())]
+        );
+    }
+
+    #[test]
+    fn case_only_targets_exist_only_where_case_matters() {
+        let dir = TempDir::with_files("case_only_exists", &["File.txt"]);
+        let plan = dir.plan(&[("File.txt", "file.txt")]);
+        assert!(plan.problems.is_empty());
+        assert_eq!(plan.renames.len(), 1);
+    }
+
+    #[test]
+    fn failed_steps_are_rolled_back() {
+        let dir = TempDir::with_files("rolled_back_steps", &["a", "b"]);
+        let steps = [
+            (dir.path("a"), dir.path(".a.0.tmp")),
+            (dir.path(".a.0.tmp"), dir.path("b")),
+        ];
+        let e = apply_steps(&steps).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(
+            dir.files(),
+            [
+                ("a".to_owned(), "a".to_owned()),
+                ("b".to_owned(), "b".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn names_left_with_only_an_extension_block_the_plan() {
+        let plan = RenamePlan::new(
+            [
+                (PathBuf::from("notes.txt"), PathBuf::from(".txt")),
+                (PathBuf::from("photo.jpg"), PathBuf::from("holiday.jpg")),
+                // Names that were already hidden, or never had an extension, aren't emptied
+                (PathBuf::from(".gitignore"), PathBuf::from(".ignore")),
+                (PathBuf::from("README"), PathBuf::from(".README")),
+            ],
+            &Destination::SameDirectory,
+        );
+        assert_eq!(
+            plan.name_warnings,
+            [Some(NameWarning::EmptyStem), None, None, None]
+        );
+        assert_eq!(plan.empty_name_indices(), [0]);
+        assert!(plan.shared_name_indices().is_empty());
+        assert!(plan.problems.is_empty());
+        assert!(plan.is_blocked());
+        assert!(plan.summary().contains("1 with empty names"));
+    }
+
+    #[test]
+    fn names_shared_with_unchanged_files_only_warn() {
+        let plan = RenamePlan::new(
+            [
+                (PathBuf::from("a/Photo.jpg"), PathBuf::from("a/Photo.jpg")),
+                (PathBuf::from("b/img.jpg"), PathBuf::from("b/photo.jpg")),
+                (PathBuf::from("b/other.jpg"), PathBuf::from("b/other2.jpg")),
+            ],
+            &Destination::SameDirectory,
+        );
+        assert_eq!(plan.unchanged, 1);
+        assert_eq!(plan.name_warnings, [Some(NameWarning::SharedName), None]);
+        assert_eq!(plan.shared_name_indices(), [0]);
+        assert!(plan.empty_name_indices().is_empty());
+        assert!(!plan.is_blocked());
+        assert!(plan
+            .summary()
+            .contains("1 sharing a name with an unchanged file"));
+    }
+
+    #[test]
+    fn warned_renames_are_found_by_their_position() {
+        let plan = RenamePlan::new(
+            [
+                (PathBuf::from("x/a.txt"), PathBuf::from("x/a.txt")),
+                (PathBuf::from("y/b.txt"), PathBuf::from("y/c.txt")),
+                (PathBuf::from("y/d.txt"), PathBuf::from("y/.txt")),
+                (PathBuf::from("y/e.txt"), PathBuf::from("y/A.txt")),
+                (PathBuf::from("y/f.log"), PathBuf::from("y/.log")),
+            ],
+            &Destination::SameDirectory,
+        );
+        assert_eq!(plan.empty_name_indices(), [1, 3]);
+        assert_eq!(plan.shared_name_indices(), [2]);
+        assert_eq!(plan.renames[2].1, PathBuf::from("y/A.txt"));
+        assert!(plan
+            .summary()
+            .ends_with("2 with empty names, 1 sharing a name with an unchanged file"));
+    }
+
+    #[test]
+    fn warnings_follow_the_order_of_their_pairs() {
+        let paths = [Path::new("a.txt"), Path::new("b.txt"), Path::new("c.txt")];
+        let targets = [Path::new("a.txt"), Path::new("A.TXT"), Path::new(".txt")];
+        assert_eq!(
+            name_warnings(paths.into_iter().zip(targets)),
+            [
+                None,
+                Some(NameWarning::SharedName),
+                Some(NameWarning::EmptyStem)
+            ]
+        );
+    }
+
+    /// A name with the given extension whose stem is ASCII padding followed by `emoji` emoji, with enough
+    /// padding that the name is exactly as long as a name can be.
+    fn name_at_limit(emoji: usize, extension: &str) -> String {
+        let emoji = "😀".repeat(emoji);
+        let padding = MAX_NAME_LENGTH - name_length(&emoji) - name_length(extension) - 1;
+        format!("{}{}.{}", "a".repeat(padding), emoji, extension)
+    }
+
+    #[test]
+    fn names_that_fit_are_not_truncated() {
+        let name = name_at_limit(50, "txt");
+        assert_eq!(name_length(&name), MAX_NAME_LENGTH);
+        assert!(matches!(truncate_name(&name), Cow::Borrowed(n) if n == name));
+        assert!(matches!(
+            truncate_name("short.txt"),
+            Cow::Borrowed("short.txt")
+        ));
+    }
+
+    #[test]
+    fn truncation_removes_whole_characters() {
+        // One unit over the limit, where the last character of the stem is an emoji that's longer than one
+        // unit on every platform, so it has to be removed entirely rather than split
+        let name = format!("a{}", name_at_limit(50, "txt"));
+        let truncated = truncate_name(&name);
+        let kept = name.strip_suffix("😀.txt").unwrap();
+        assert_eq!(truncated, format!("{}.txt", kept));
+        assert!(name_length(&truncated) < MAX_NAME_LENGTH);
+        assert_eq!(
+            name_length(&truncated) + name_length("😀"),
+            MAX_NAME_LENGTH + 1
+        );
+    }
+
+    #[test]
+    fn truncation_keeps_multibyte_extensions() {
+        let name = format!("{}.é😀", "é".repeat(MAX_NAME_LENGTH));
+        let truncated = truncate_name(&name);
+        assert!(truncated.ends_with(".é😀"));
+        assert!(name_length(&truncated) <= MAX_NAME_LENGTH);
+        assert!(name_length(&truncated) + name_length("é") > MAX_NAME_LENGTH);
+        assert!(truncated.trim_end_matches(".é😀").chars().all(|c| c == 'é'));
+    }
+
+    #[test]
+    fn names_without_room_for_a_stem_are_not_truncated() {
+        let name = format!("a.{}", "😀".repeat(MAX_NAME_LENGTH));
+        assert!(matches!(truncate_name(&name), Cow::Borrowed(n) if n == name));
+
+        let name = "😀".repeat(MAX_NAME_LENGTH);
+        let truncated = truncate_name(&name);
+        assert!(name_length(&truncated) <= MAX_NAME_LENGTH);
+        assert!(name_length(&truncated) + name_length("😀") > MAX_NAME_LENGTH);
+    }
+
+    /// A directory holding the given files, whose names can include the directories they're in.
+    fn with_nested_files(test: &str, names: &[&str]) -> TempDir {
+        let dir = TempDir::with_files(test, &[]);
+        for name in names {
+            let path = dir.path(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, name).unwrap();
+        }
+        dir
+    }
+
+    fn plan_directories(dir: &TempDir, renames: &[(&str, &str)]) -> RenamePlan {
+        let pairs = renames
+            .iter()
+            .map(|(old, new)| (dir.path(old), dir.path(new)));
+        RenamePlan::new(pairs, &Destination::RenamedDirectories(dir.0.clone()))
+    }
+
+    #[test]
+    fn files_in_a_renamed_directory_share_it() {
+        let dir = with_nested_files(
+            "files_in_a_renamed_directory",
+            &["old/a.txt", "old/b.txt", "other/c.txt"],
+        );
+        let plan = plan_directories(
+            &dir,
+            &[
+                ("old/a.txt", "new/a.txt"),
+                ("old/b.txt", "new/b.txt"),
+                ("other/c.txt", "other/c.txt"),
+            ],
+        );
+        assert!(plan.problems.is_empty());
+        assert_eq!(plan.renames.len(), 2);
+        assert_eq!(plan.unchanged, 1);
+        assert_eq!(
+            plan.renamed_directories,
+            [(dir.path("old"), dir.path("new"), 2)]
+        );
+        assert_eq!(plan.created_directories, [dir.path("new")]);
+        assert_eq!(plan.emptied_directories(), [dir.path("old")]);
+    }
+
+    #[test]
+    fn nested_directory_renames_create_and_empty_every_level() {
+        let dir = with_nested_files(
+            "nested_directory_renames",
+            &["a/b/f.txt", "a/c/g.txt", "a/c/h.txt"],
+        );
+        let plan = plan_directories(
+            &dir,
+            &[
+                ("a/b/f.txt", "x/y/f.txt"),
+                ("a/c/g.txt", "x/c/g.txt"),
+                ("a/c/h.txt", "x/c/h.txt"),
+            ],
+        );
+        assert!(plan.problems.is_empty());
+        assert_eq!(
+            plan.renamed_directories,
+            [
+                (dir.path("a/b"), dir.path("x/y"), 1),
+                (dir.path("a/c"), dir.path("x/c"), 2),
+            ]
+        );
+        assert_eq!(plan.created_directories, [dir.path("x/c"), dir.path("x/y")]);
+        assert_eq!(
+            plan.emptied_directories(),
+            [dir.path("a/b"), dir.path("a/c"), dir.path("a")]
+        );
+    }
+
+    #[test]
+    fn renaming_into_an_existing_directory_creates_nothing() {
+        let dir = with_nested_files(
+            "renaming_into_an_existing_directory",
+            &["a/f.txt", "b/g.txt"],
+        );
+        let plan = plan_directories(&dir, &[("a/f.txt", "b/f.txt")]);
+        assert!(plan.problems.is_empty());
+        assert_eq!(
+            plan.renamed_directories,
+            [(dir.path("a"), dir.path("b"), 1)]
+        );
+        assert!(plan.created_directories.is_empty());
+    }
+
+    #[test]
+    fn files_sharing_a_renamed_directory_can_still_collide() {
+        let dir = with_nested_files("files_sharing_a_renamed_directory", &["a/f.txt", "b/f.txt"]);
+        let plan = plan_directories(&dir, &[("a/f.txt", "c/f.txt"), ("b/f.txt", "c/f.txt")]);
+        assert!(plan.renames.is_empty());
+        assert!(plan
+            .problems
+            .iter()
+            .all(|(_, _, problem)| *problem == Problem::Duplicate));
+    }
+
+    #[test]
+    fn created_directories_are_removed_only_when_empty() {
+        let dir = TempDir::with_files("created_directories_are_removed", &[]);
+        let created = create_directories(&[dir.path("x/y"), dir.path("x/z")]).unwrap();
+        assert_eq!(created, [dir.path("x"), dir.path("x/y"), dir.path("x/z")]);
+
+        fs::write(dir.path("x/z/f.txt"), "f").unwrap();
+        let removed = remove_empty_directories(created.iter().rev());
+        assert_eq!(removed, [dir.path("x/y")]);
+        assert!(dir.path("x/z/f.txt").exists());
+    }
+
+    #[test]
+    fn renaming_never_creates_directories() {
+        let dir = TempDir::with_files("renaming_never_creates_directories", &["a.txt"]);
+        assert!(rename_file(&dir.path("a.txt"), &dir.path("missing/a.txt")).is_err());
+        assert!(!dir.path("missing").exists());
+        assert_eq!(dir.files(), [("a.txt".to_owned(), "a.txt".to_owned())]);
+    }
+
+    #[test]
+    fn unchanged_names_are_only_counted() {
+        let dir = TempDir::with_files("unchanged_names_are_only_counted", &["a.txt", "b.txt"]);
+        let plan = dir.plan(&[("a.txt", "a.txt"), ("b.txt", "b.txt")]);
+        assert_eq!(plan.unchanged, 2);
+        assert!(plan.renames.is_empty());
+        assert!(plan.problems.is_empty());
+        assert!(!plan.is_blocked());
+        assert!(plan.steps().is_empty());
+    }
+
+    /// Resolves the collisions between the given `(old, new)` names of files in the given directory,
+    /// giving the resolved names.
+    fn resolve(dir: &TempDir, renames: &[(&str, &str)]) -> Vec<String> {
+        let (paths, targets) = renames
+            .iter()
+            .map(|(old, new)| (dir.path(old), dir.path(new)))
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+        resolve_collisions(&paths, targets)
+            .iter()
+            .map(|t| t.file_name().unwrap().to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn files_sharing_a_target_are_numbered_in_order() {
+        let dir = TempDir::with_files("files_sharing_a_target", &["p", "q", "r", "s"]);
+        assert_eq!(
+            resolve(
+                &dir,
+                &[
+                    ("p", "a.txt"),
+                    ("q", "a.txt"),
+                    ("r", "a.txt"),
+                    ("s", "a.txt")
+                ]
+            ),
+            ["a.txt", "a (1).txt", "a (2).txt", "a (3).txt"]
+        );
+    }
+
+    #[test]
+    fn interleaved_collisions_are_numbered_separately() {
+        let dir = TempDir::with_files("interleaved_collisions", &["p", "q", "r", "s", "t"]);
+        assert_eq!(
+            resolve(
+                &dir,
+                &[
+                    ("p", "a.txt"),
+                    ("q", "b.txt"),
+                    ("r", "a.txt"),
+                    ("s", "b.txt"),
+                    ("t", "a.txt"),
+                ]
+            ),
+            ["a.txt", "b.txt", "a (1).txt", "b (1).txt", "a (2).txt"]
+        );
+    }
+
+    #[test]
+    fn numbering_skips_names_that_are_taken() {
+        // `a (1).txt` exists but isn't being renamed, and `a (3).txt` is claimed by a file of its own
+        let dir = TempDir::with_files("numbering_skips_names", &["p", "q", "r", "s", "a (1).txt"]);
+        assert_eq!(
+            resolve(
+                &dir,
+                &[
+                    ("p", "a.txt"),
+                    ("q", "a (3).txt"),
+                    ("r", "a.txt"),
+                    ("s", "a.txt"),
+                ]
+            ),
+            ["a.txt", "a (3).txt", "a (2).txt", "a (4).txt"]
+        );
+    }
+
+    #[test]
+    fn unchanged_and_moving_files_are_resolved_around() {
+        let dir = TempDir::with_files("unchanged_and_moving_files", &["a.txt", "b.txt", "p", "q"]);
+        // `a.txt` keeps its name even though it comes last, while `b.txt` is being renamed away, so its
+        // name is free for another file
+        assert_eq!(
+            resolve(
+                &dir,
+                &[
+                    ("p", "a.txt"),
+                    ("b.txt", "c.txt"),
+                    ("q", "b.txt"),
+                    ("a.txt", "a.txt"),
+                ]
+            ),
+            ["a (1).txt", "c.txt", "b.txt", "a.txt"]
+        );
+    }
+
+    #[test]
+    fn names_differing_in_case_collide_where_the_platform_ignores_case() {
+        let dir = TempDir::with_files("names_differing_in_case", &["p", "q"]);
+        let resolved = resolve(&dir, &[("p", "A.txt"), ("q", "a.txt")]);
+        if CASE_INSENSITIVE {
+            assert_eq!(resolved, ["A.txt", "a (1).txt"]);
+        } else {
+            assert_eq!(resolved, ["A.txt", "a.txt"]);
+        }
+    }
+
+    /// Plans renaming each of the given number of numbered files in a directory that doesn't exist to the
+    /// next number up, with the last renamed to the first if `cycle` is set.
+    fn renumbering(count: usize, cycle: bool) -> RenamePlan {
+        let dir = std::env::temp_dir().join("mass_renamer_renumbering_missing");
+        let name = |i: usize| dir.join(format!("file_{:05}", i));
+        let pairs = (0..count).map(|i| {
+            let next = if cycle { (i + 1) % count } else { i + 1 };
+            (name(i), name(next))
+        });
+        RenamePlan::new(pairs, &Destination::SameDirectory)
+    }
+
+    #[test]
+    fn long_chains_are_ordered_from_their_free_end() {
+        let plan = renumbering(10_000, false);
+        assert!(!plan.is_blocked());
+
+        let steps = plan.steps();
+        assert_eq!(steps.len(), 10_000);
+        assert_eq!(steps[0], plan.renames[9_999]);
+        assert_eq!(steps[9_999], plan.renames[0]);
+        // Every file is renamed after the file at its target has been renamed away
+        let sources: HashSet<_> = plan.renames.iter().map(|(old, _)| old).collect();
+        let mut renamed = HashSet::new();
+        for (old, new) in &steps {
+            assert!(!sources.contains(new) || renamed.contains(new));
+            renamed.insert(old.clone());
+        }
+    }
+
+    #[test]
+    fn long_cycles_need_a_single_temporary_name() {
+        let plan = renumbering(10_000, true);
+        let steps = plan.steps();
+        assert_eq!(steps.len(), 10_001);
+        assert_eq!(steps[0].0, plan.renames[0].0);
+        assert_eq!(steps[10_000].1, plan.renames[0].1);
+    }
+
+    #[test]
+    fn targets_differing_in_case_are_duplicates_where_the_platform_ignores_case() {
+        let dir = TempDir::with_files("targets_differing_in_case", &["p", "q"]);
+        let plan = dir.plan(&[("p", "A.txt"), ("q", "a.txt")]);
+        if CASE_INSENSITIVE {
+            assert!(plan.renames.is_empty());
+            assert_eq!(
+                plan.problems,
+                [
+                    (dir.path("p"), dir.path("A.txt"), Problem::Duplicate),
+                    (dir.path("q"), dir.path("a.txt"), Problem::Duplicate),
+                ]
+            );
+        } else {
+            assert!(plan.problems.is_empty());
+            assert_eq!(plan.renames.len(), 2);
+        }
+    }
+}
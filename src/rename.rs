@@ -0,0 +1,1372 @@
+//! Computing new names for files.
+
+use crate::{
+    plan::MAX_NAME_LENGTH,
+    template::{Template, TemplateError},
+};
+use regex::{Captures, Regex, RegexBuilder};
+use serde::{
+    de::{Error as _, IgnoredAny},
+    Deserialize, Deserializer, Serialize,
+};
+use std::{
+    borrow::Cow,
+    cell::OnceCell,
+    fmt,
+    ops::Range,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// A transformation to apply to the names of files.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rule {
+    find: String,
+    regex: bool,
+    case_insensitive: bool,
+    /// Whether [`find`](Self::find) only matches whole words, as described in [`is_whole_word`].
+    pub whole_word: bool,
+    /// The compiled form of [`find`](Self::find), which is compiled when it is first needed.
+    #[serde(skip)]
+    pattern: OnceCell<Result<Regex, regex::Error>>,
+    /// Whether to replace each file name with one formatted from [`template`](Self::template).
+    pub use_template: bool,
+    template: String,
+    /// The parsed form of [`template`](Self::template), which is parsed when it is first needed.
+    #[serde(skip)]
+    parsed_template: OnceCell<Result<Template, TemplateError>>,
+    /// The text to replace every match of [`find`](Self::find) with. In regex mode, this may refer to
+    /// capture groups, such as with `$1`.
+    pub replace: String,
+    /// Whether to replace the stem of each file name with a sequential number.
+    pub number: bool,
+    /// How to number files when [`number`](Self::number) is set.
+    pub numbering: Numbering,
+    /// Whether to remove characters from the stem of each file name, as described in [`remove_range`].
+    pub remove: bool,
+    /// The position of the first character to remove when [`remove`](Self::remove) is set.
+    pub remove_start: usize,
+    /// How many characters to remove when [`remove`](Self::remove) is set.
+    pub remove_count: usize,
+    /// Whether to remove a trailing counter from the stem of each file name, as described in
+    /// [`strip_counter`].
+    pub strip_counter: bool,
+    /// Whether to remove whitespace from the start and end of the stem of each file name.
+    pub trim: bool,
+    /// Whether to replace every run of whitespace in the stem of each file name with a single space.
+    pub collapse_whitespace: bool,
+    /// What to replace the spaces in the stem of each file name with.
+    pub spaces: SpaceReplacement,
+    /// How to change the case of the stem of each file name.
+    pub case: CaseMode,
+    /// The text to insert at the start of the stem of each file name.
+    pub prefix: String,
+    /// The text to insert at the end of the stem of each file name.
+    pub suffix: String,
+    /// Whether to change the extension of each file name, as described in [`change_extension`].
+    pub change_extension: bool,
+    /// The extension to replace when [`change_extension`](Self::change_extension) is set. If empty, the
+    /// extension is instead added to file names without one.
+    pub extension_from: String,
+    /// The extension to change to when [`change_extension`](Self::change_extension) is set. If empty,
+    /// the extension is removed.
+    pub extension_to: String,
+}
+
+impl Rule {
+    /// The text to search for in file names.
+    pub fn find(&self) -> &str {
+        &self.find
+    }
+
+    /// Sets the text to search for in file names.
+    pub fn set_find(&mut self, find: String) {
+        self.find = find;
+        self.pattern = OnceCell::new();
+    }
+
+    /// Whether [`find`](Self::find) is treated as a regular expression rather than as plain text.
+    pub fn is_regex(&self) -> bool {
+        self.regex
+    }
+
+    /// Sets whether [`find`](Self::find) is treated as a regular expression rather than as plain text.
+    pub fn set_regex(&mut self, regex: bool) {
+        self.regex = regex;
+        self.pattern = OnceCell::new();
+    }
+
+    /// Whether [`find`](Self::find) matches text regardless of its case.
+    pub fn is_case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+
+    /// Sets whether [`find`](Self::find) matches text regardless of its case.
+    pub fn set_case_insensitive(&mut self, case_insensitive: bool) {
+        self.case_insensitive = case_insensitive;
+        self.pattern = OnceCell::new();
+    }
+
+    /// The error from compiling [`find`](Self::find) as a regular expression, if it isn't valid.
+    pub fn pattern_error(&self) -> Option<&regex::Error> {
+        self.pattern().as_ref().err()
+    }
+
+    /// Returns the byte ranges of every match of [`find`](Self::find) in the given text.
+    ///
+    /// The ranges always refer to the given text as it is, even when matching case-insensitively.
+    pub fn matches<'a>(&'a self, text: &'a str) -> impl Iterator<Item = Range<usize>> + 'a {
+        self.pattern()
+            .as_ref()
+            .ok()
+            .filter(|_| !self.find.is_empty())
+            .into_iter()
+            .flat_map(move |p| p.find_iter(text).map(|m| m.range()))
+            .filter(move |range| !self.whole_word || is_whole_word(text, range))
+    }
+
+    /// The template that file names are formatted from when [`use_template`](Self::use_template) is set,
+    /// as described in [`Template`].
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// Sets the template that file names are formatted from.
+    pub fn set_template(&mut self, template: String) {
+        self.template = template;
+        self.parsed_template = OnceCell::new();
+    }
+
+    /// The error from parsing [`template`](Self::template), if it's in use and isn't valid.
+    pub fn template_error(&self) -> Option<&TemplateError> {
+        self.use_template
+            .then(|| self.parsed_template().as_ref().err())
+            .flatten()
+    }
+
+    /// Whether numbering the given number of files would give any of them a number too large to represent,
+    /// as described in [`Numbering::number`].
+    pub fn numbering_overflows(&self, count: usize) -> bool {
+        self.number && count > 0 && self.numbering.number(count - 1).is_none()
+    }
+
+    fn parsed_template(&self) -> &Result<Template, TemplateError> {
+        self.parsed_template
+            .get_or_init(|| Template::parse(&self.template))
+    }
+
+    fn pattern(&self) -> &Result<Regex, regex::Error> {
+        self.pattern.get_or_init(|| {
+            let source = if self.regex {
+                Cow::Borrowed(self.find.as_str())
+            } else {
+                Cow::Owned(regex::escape(&self.find))
+            };
+            RegexBuilder::new(&source)
+                .case_insensitive(self.case_insensitive)
+                .build()
+        })
+    }
+
+    /// Applies this rule to the given file name, which is at the given position in the list of files
+    /// being renamed and was last modified at the given time.
+    pub fn apply(&self, name: &str, index: usize, modified: Option<SystemTime>) -> String {
+        let name = self.replace_matches(name);
+
+        let name = match self.parsed_template() {
+            Ok(template) if self.use_template => {
+                let (stem, extension) = split_extension(&name);
+                template.format(stem, extension, index, modified)
+            }
+            _ => name,
+        };
+
+        // Numbers that are too large are reported by `numbering_overflows` instead
+        let name = match apply_numbering(&name, index, &self.numbering) {
+            Some(numbered) if self.number => numbered,
+            _ => name,
+        };
+
+        let (stem, extension) = split_extension(&name);
+        let stem = if self.strip_counter {
+            Cow::Owned(strip_counter(stem))
+        } else {
+            Cow::Borrowed(stem)
+        };
+        let stem = if self.remove {
+            Cow::Owned(remove_range(&stem, self.remove_start, self.remove_count))
+        } else {
+            stem
+        };
+        let stem = self.clean_up(&stem);
+        let stem = add_affixes(&self.case.transform(&stem), &self.prefix, &self.suffix);
+        let name = join_extension(&stem, extension);
+
+        if self.change_extension {
+            let from = Some(self.extension_from.as_str()).filter(|f| !f.is_empty());
+            change_extension(&name, from, &self.extension_to)
+        } else {
+            name
+        }
+    }
+
+    /// Replaces every match of [`find`](Self::find) in the given text with [`replace`](Self::replace),
+    /// without making any of this rule's other changes.
+    pub fn replace_matches(&self, text: &str) -> String {
+        match self.pattern() {
+            Ok(pattern) if !self.find.is_empty() => pattern
+                .replace_all(text, |captures: &Captures| {
+                    let found = captures.get(0).unwrap();
+                    if self.whole_word && !is_whole_word(text, &found.range()) {
+                        found.as_str().to_owned()
+                    } else if self.regex {
+                        let mut replacement = String::new();
+                        captures.expand(&self.replace, &mut replacement);
+                        replacement
+                    } else {
+                        self.replace.clone()
+                    }
+                })
+                .into_owned(),
+            _ => text.to_owned(),
+        }
+    }
+
+    /// Applies the whitespace clean up options to the given stem.
+    fn clean_up<'a>(&self, stem: &'a str) -> Cow<'a, str> {
+        let mut stem = Cow::Borrowed(if self.trim {
+            trim_whitespace(stem)
+        } else {
+            stem
+        });
+        if self.collapse_whitespace {
+            stem = Cow::Owned(collapse_whitespace(&stem));
+        }
+        if let Some(replacement) = self.spaces.replacement() {
+            stem = Cow::Owned(replace_spaces(&stem, replacement));
+        }
+        stem
+    }
+}
+
+/// A sequence of [`Rule`]s, each applied to the name produced by the one before it. There is always at
+/// least one rule.
+///
+/// Pipelines are saved with a version number and with each rule tagged by its kind, so that a pipeline
+/// saved by a later version of the app can still be loaded, skipping any rules that aren't understood.
+/// A single [`Rule`] saved on its own, as older versions did, is loaded as a pipeline of just that rule,
+/// as long as it has at least one of the rule's settings and nothing else.
+/// Anything that didn't load exactly as it was saved is reported by [`load_warnings`](Self::load_warnings).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "SavedPipeline", into = "VersionedPipeline")]
+pub struct Pipeline {
+    rules: Vec<Rule>,
+    /// Everything that didn't load exactly as it was saved, if this pipeline was loaded.
+    load_warnings: Vec<LoadWarning>,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::from_rules(vec![Rule::default()])
+    }
+}
+
+impl Pipeline {
+    fn from_rules(rules: Vec<Rule>) -> Self {
+        Self {
+            rules,
+            load_warnings: Vec::new(),
+        }
+    }
+
+    /// Takes everything that didn't load exactly as it was saved when this pipeline was loaded, such as
+    /// rules that were skipped, so that they can be reported. Pipelines that weren't loaded, or that
+    /// loaded exactly, don't have any.
+    pub fn take_load_warnings(&mut self) -> Vec<LoadWarning> {
+        std::mem::take(&mut self.load_warnings)
+    }
+
+    /// The rules in this pipeline, in the order they're applied.
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// The rules in this pipeline, in the order they're applied, for changing or reordering them.
+    pub fn rules_mut(&mut self) -> &mut [Rule] {
+        &mut self.rules
+    }
+
+    /// Inserts the given rule at the given position in the pipeline, shifting the rules after it along.
+    pub fn insert(&mut self, index: usize, rule: Rule) {
+        self.rules.insert(index.min(self.rules.len()), rule);
+    }
+
+    /// Removes the rule at the given position in the pipeline. The last remaining rule is never removed.
+    pub fn remove(&mut self, index: usize) {
+        if self.rules.len() > 1 && index < self.rules.len() {
+            self.rules.remove(index);
+        }
+    }
+
+    /// The error from compiling the find text of the first rule that doesn't have a valid pattern.
+    pub fn pattern_error(&self) -> Option<&regex::Error> {
+        self.rules.iter().find_map(Rule::pattern_error)
+    }
+
+    /// The error from parsing the template of the first rule that uses a template that isn't valid.
+    pub fn template_error(&self) -> Option<&TemplateError> {
+        self.rules.iter().find_map(Rule::template_error)
+    }
+
+    /// Whether any rule would number the given number of files past the largest number it can represent.
+    pub fn numbering_overflows(&self, count: usize) -> bool {
+        self.rules.iter().any(|r| r.numbering_overflows(count))
+    }
+
+    /// Applies every rule in turn to the given file name, which is at the given position in the list of
+    /// files being renamed and was last modified at the given time.
+    pub fn apply(&self, name: &str, index: usize, modified: Option<SystemTime>) -> String {
+        self.rules.iter().fold(name.to_owned(), |name, rule| {
+            rule.apply(&name, index, modified)
+        })
+    }
+
+    /// Replaces the matches of every rule in turn in the given text, as described in
+    /// [`Rule::replace_matches`], without making any of their other changes. This suits names that
+    /// numbering, templates and the like don't make sense for, such as the names of directories.
+    pub fn replace_matches(&self, text: &str) -> String {
+        self.rules
+            .iter()
+            .fold(text.to_owned(), |text, rule| rule.replace_matches(&text))
+    }
+
+    /// Computes the path that the file at the given path and position in the list of files being renamed,
+    /// which was last modified at the given time, would be renamed to.
+    ///
+    /// Only the file name is changed, never the directory portion. Paths without a file name or whose
+    /// file name isn't valid UTF-8 are returned unchanged.
+    pub fn target(&self, path: &Path, index: usize, modified: Option<SystemTime>) -> PathBuf {
+        match path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => path.with_file_name(self.apply(name, index, modified)),
+            None => path.to_path_buf(),
+        }
+    }
+}
+
+/// The version of the format that [`Pipeline`]s are saved in.
+const PIPELINE_VERSION: u32 = 1;
+
+/// A [`Rule`] tagged with its kind when saved.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind")]
+enum TaggedRule {
+    Rule(Rule),
+}
+
+/// A saved rule, which might be of a kind that this version doesn't know about.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedRule {
+    Known(TaggedRule),
+    Malformed(MalformedRule),
+    Unknown(IgnoredAny),
+}
+
+/// The kinds of rule that this version knows about.
+#[derive(Deserialize)]
+enum RuleKind {
+    Rule,
+}
+
+/// A saved rule of a kind that this version knows about, but whose settings aren't valid for it.
+#[derive(Deserialize)]
+struct MalformedRule {
+    #[serde(rename = "kind")]
+    _kind: RuleKind,
+}
+
+/// A single [`Rule`] saved on its own, as older versions did.
+///
+/// Unlike a rule in a [`VersionedPipeline`], this must have at least one of the rule's settings and
+/// nothing else, so that any other object isn't mistaken for a rule of all default settings.
+struct LegacyRule(Rule);
+
+impl<'de> Deserialize<'de> for LegacyRule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = serde_json::Map::deserialize(deserializer)?;
+        if fields.is_empty() {
+            return Err(D::Error::custom(
+                "a saved rule must have at least one setting",
+            ));
+        }
+        let known = serde_json::to_value(Rule::default()).map_err(D::Error::custom)?;
+        if let Some(unknown) = fields.keys().find(|field| known.get(field).is_none()) {
+            return Err(D::Error::unknown_field(unknown, &[]));
+        }
+        Rule::deserialize(serde_json::Value::Object(fields))
+            .map(Self)
+            .map_err(D::Error::custom)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct VersionedPipeline<R = TaggedRule> {
+    version: u32,
+    rules: Vec<R>,
+}
+
+/// Any form that a [`Pipeline`] might have been saved in.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedPipeline {
+    Versioned(VersionedPipeline<SavedRule>),
+    Single(LegacyRule),
+}
+
+impl From<SavedPipeline> for Pipeline {
+    fn from(saved: SavedPipeline) -> Self {
+        let saved = match saved {
+            SavedPipeline::Versioned(saved) => saved,
+            SavedPipeline::Single(LegacyRule(rule)) => return Self::from_rules(vec![rule]),
+        };
+
+        let mut load_warnings = Vec::new();
+        if saved.version > PIPELINE_VERSION {
+            load_warnings.push(LoadWarning::NewerVersion(saved.version));
+        }
+
+        let mut rules = Vec::new();
+        let (mut malformed, mut unknown) = (0, 0);
+        for rule in saved.rules {
+            match rule {
+                SavedRule::Known(TaggedRule::Rule(rule)) => rules.push(rule),
+                SavedRule::Malformed(_) => malformed += 1,
+                SavedRule::Unknown(_) => unknown += 1,
+            }
+        }
+        if malformed > 0 {
+            load_warnings.push(LoadWarning::MalformedRules(malformed));
+        }
+        if unknown > 0 {
+            load_warnings.push(LoadWarning::SkippedRules(unknown));
+        }
+
+        let mut pipeline = if rules.is_empty() {
+            Self::default()
+        } else {
+            Self::from_rules(rules)
+        };
+        pipeline.load_warnings = load_warnings;
+        pipeline
+    }
+}
+
+/// Something that stopped a [`Pipeline`] from loading exactly as it was saved, without stopping it from
+/// loading at all.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadWarning {
+    /// The pipeline was saved in this newer version of the format, so parts of it might not have been
+    /// understood.
+    NewerVersion(u32),
+    /// This many rules were of kinds that aren't known, so they were skipped.
+    SkippedRules(usize),
+    /// This many rules were of known kinds but had settings that aren't valid, so they were skipped.
+    MalformedRules(usize),
+}
+
+impl From<Pipeline> for VersionedPipeline {
+    fn from(pipeline: Pipeline) -> Self {
+        Self {
+            version: PIPELINE_VERSION,
+            rules: pipeline.rules.into_iter().map(TaggedRule::Rule).collect(),
+        }
+    }
+}
+
+/// Settings for naming files after their position in a sequence.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Numbering {
+    /// The text that comes before the number.
+    pub base: String,
+    /// The number given to the first file.
+    pub start: u64,
+    /// How much the number increases by for each file.
+    pub step: u64,
+    /// The minimum number of digits in the number, which is padded with leading zeroes to fit.
+    pub width: usize,
+}
+
+impl Numbering {
+    /// The number for the file at the given position in the sequence, or [`None`] if it's too large to
+    /// represent, such as with a very large [`start`](Self::start) or [`step`](Self::step).
+    pub fn number(&self, index: usize) -> Option<u64> {
+        let index = u64::try_from(index).ok()?;
+        self.step.checked_mul(index)?.checked_add(self.start)
+    }
+}
+
+impl Default for Numbering {
+    fn default() -> Self {
+        Self {
+            base: String::new(),
+            start: 1,
+            step: 1,
+            width: 3,
+        }
+    }
+}
+
+/// A way of changing the case of the stem of a file name.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaseMode {
+    /// Leaves the case as it is.
+    #[default]
+    Unchanged,
+    /// Converts every character to lowercase.
+    Lower,
+    /// Converts every character to uppercase.
+    Upper,
+    /// Capitalises the first character of each word and lowercases the rest, keeping the separators.
+    Title,
+    /// Capitalises the first character of every word but the first and lowercases the rest, removing
+    /// the separators.
+    Camel,
+}
+
+impl CaseMode {
+    /// Every [`CaseMode`], in the order they should be displayed.
+    pub const ALL: [CaseMode; 5] = [
+        CaseMode::Unchanged,
+        CaseMode::Lower,
+        CaseMode::Upper,
+        CaseMode::Title,
+        CaseMode::Camel,
+    ];
+
+    /// Changes the case of the given stem. Words are separated by spaces, underscores and hyphens.
+    pub fn transform(&self, stem: &str) -> String {
+        match self {
+            CaseMode::Unchanged => stem.to_owned(),
+            CaseMode::Lower => stem.to_lowercase(),
+            CaseMode::Upper => stem.to_uppercase(),
+            CaseMode::Title => stem
+                .split_inclusive(is_word_separator)
+                .map(capitalise)
+                .collect(),
+            CaseMode::Camel => stem
+                .split(is_word_separator)
+                .filter(|w| !w.is_empty())
+                .enumerate()
+                .map(|(i, w)| {
+                    if i == 0 {
+                        w.to_lowercase()
+                    } else {
+                        capitalise(w)
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// What to replace the spaces in the stem of a file name with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpaceReplacement {
+    /// Leaves the spaces as they are.
+    #[default]
+    Keep,
+    /// Replaces every space with an underscore.
+    Underscore,
+    /// Replaces every space with a hyphen.
+    Hyphen,
+}
+
+impl SpaceReplacement {
+    /// Every [`SpaceReplacement`], in the order they should be displayed.
+    pub const ALL: [SpaceReplacement; 3] = [
+        SpaceReplacement::Keep,
+        SpaceReplacement::Underscore,
+        SpaceReplacement::Hyphen,
+    ];
+
+    /// The character that spaces are replaced with, if any.
+    pub fn replacement(&self) -> Option<char> {
+        match self {
+            SpaceReplacement::Keep => None,
+            SpaceReplacement::Underscore => Some('_'),
+            SpaceReplacement::Hyphen => Some('-'),
+        }
+    }
+}
+
+impl fmt::Display for LoadWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadWarning::NewerVersion(version) => write!(
+                f,
+                "The rules were saved by a newer version of the app (format version {}), so some of \
+                 them might not have loaded",
+                version
+            ),
+            LoadWarning::SkippedRules(count) => {
+                write!(f, "{} rules of unknown kinds were skipped", count)
+            }
+            LoadWarning::MalformedRules(count) => {
+                write!(f, "{} rules with settings that aren't valid were skipped", count)
+            }
+        }
+    }
+}
+
+impl fmt::Display for SpaceReplacement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SpaceReplacement::Keep => "Keep Spaces",
+            SpaceReplacement::Underscore => "Spaces To _",
+            SpaceReplacement::Hyphen => "Spaces To -",
+        })
+    }
+}
+
+impl fmt::Display for CaseMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CaseMode::Unchanged => "Unchanged",
+            CaseMode::Lower => "lowercase",
+            CaseMode::Upper => "UPPERCASE",
+            CaseMode::Title => "Title Case",
+            CaseMode::Camel => "camelCase",
+        })
+    }
+}
+
+fn is_word_separator(c: char) -> bool {
+    matches!(c, ' ' | '_' | '-')
+}
+
+/// Titlecases the first character of the given word and lowercases the rest.
+fn capitalise(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => {
+            let mut word = titlecase(first);
+            word.extend(chars.flat_map(char::to_lowercase));
+            word
+        }
+        None => String::new(),
+    }
+}
+
+/// Converts the given character to titlecase. This is the same as uppercase, except for the characters
+/// that stand for two letters, such as the digraph `ǆ`, whose titlecase form `ǅ` only capitalises the
+/// first of them, and `ß`, which becomes `Ss` rather than `SS`.
+fn titlecase(c: char) -> String {
+    match c {
+        'Ǆ' | 'ǅ' | 'ǆ' => "ǅ".to_owned(),
+        'Ǉ' | 'ǈ' | 'ǉ' => "ǈ".to_owned(),
+        'Ǌ' | 'ǋ' | 'ǌ' => "ǋ".to_owned(),
+        'Ǳ' | 'ǲ' | 'ǳ' => "ǲ".to_owned(),
+        'ß' => "Ss".to_owned(),
+        _ => c.to_uppercase().collect(),
+    }
+}
+
+/// Whether the given byte range of the given text is a whole word, rather than part of a longer one.
+///
+/// Where the range starts or ends with a letter or digit, the character next to it on that side must not
+/// be one, so that `cat` matches in `cat.txt` and `a cat`, but not in `category`. Edges of the range that
+/// are already punctuation or whitespace, as in `-cat`, always count as a boundary.
+pub fn is_whole_word(text: &str, range: &Range<usize>) -> bool {
+    let found = &text[range.clone()];
+    let is_joined = |inside: Option<char>, outside: Option<char>| {
+        inside.map_or(false, char::is_alphanumeric) && outside.map_or(false, char::is_alphanumeric)
+    };
+    let before = text[..range.start].chars().next_back();
+    let after = text[range.end..].chars().next();
+    !is_joined(found.chars().next(), before) && !is_joined(found.chars().next_back(), after)
+}
+
+/// Removes `count` characters from the given stem, starting from the character at position `start`.
+///
+/// Positions count characters rather than bytes. If `start` is past the end of the stem, nothing is
+/// removed, and if there are fewer than `count` characters after `start`, only those are removed.
+pub fn remove_range(stem: &str, start: usize, count: usize) -> String {
+    stem.chars()
+        .enumerate()
+        .filter(|&(i, _)| i < start || i - start >= count)
+        .map(|(_, c)| c)
+        .collect()
+}
+
+/// Removes the number from the end of the given stem, along with a single space, underscore, hyphen or
+/// dot separating it from the rest of the stem, as in `photo_004` or `track 12`.
+///
+/// Stems that don't end with a number, or that are nothing but a number and its separator, are left
+/// unchanged.
+pub fn strip_counter(stem: &str) -> String {
+    let without_digits = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+    if without_digits.len() == stem.len() {
+        return stem.to_owned();
+    }
+
+    let without_separator = without_digits
+        .strip_suffix(|c| matches!(c, ' ' | '_' | '-' | '.'))
+        .unwrap_or(without_digits);
+    if without_separator.is_empty() {
+        stem.to_owned()
+    } else {
+        without_separator.to_owned()
+    }
+}
+
+/// Removes whitespace, including tabs and newlines, from the start and end of the given stem.
+pub fn trim_whitespace(stem: &str) -> &str {
+    stem.trim()
+}
+
+/// Replaces every run of whitespace, including tabs and newlines, in the given stem with a single space.
+pub fn collapse_whitespace(stem: &str) -> String {
+    let mut collapsed = String::with_capacity(stem.len());
+    let mut in_whitespace = false;
+    for c in stem.chars() {
+        if c.is_whitespace() {
+            if !in_whitespace {
+                collapsed.push(' ');
+            }
+            in_whitespace = true;
+        } else {
+            collapsed.push(c);
+            in_whitespace = false;
+        }
+    }
+    collapsed
+}
+
+/// Replaces every space in the given stem with the given character.
+pub fn replace_spaces(stem: &str, replacement: char) -> String {
+    stem.replace(' ', replacement.encode_utf8(&mut [0; 4]))
+}
+
+/// Inserts the given prefix and suffix around the given stem.
+pub fn add_affixes(stem: &str, prefix: &str, suffix: &str) -> String {
+    [prefix, stem, suffix].concat()
+}
+
+/// Splits a file name into its stem and extension, not including the separating dot.
+///
+/// A leading dot, as in `.gitignore`, is part of the stem rather than an extension separator.
+pub fn split_extension(name: &str) -> (&str, Option<&str>) {
+    match name.rfind('.') {
+        Some(i) if i > 0 => (&name[..i], Some(&name[i + 1..])),
+        _ => (name, None),
+    }
+}
+
+/// Joins a stem and an optional extension back into a file name.
+pub fn join_extension(stem: &str, extension: Option<&str>) -> String {
+    match extension {
+        Some(extension) => format!("{}.{}", stem, extension),
+        None => stem.to_owned(),
+    }
+}
+
+/// Changes the extension of a file name from `from` to `to`, leaving names without that extension
+/// unchanged. `from` is compared case-insensitively and may span several dots, as in `tar.gz`. If `from`
+/// is [`None`], `to` is instead added to names without an extension. If `to` is empty, the extension is
+/// removed.
+///
+/// As with [`split_extension`], a leading dot is never an extension separator.
+pub fn change_extension(name: &str, from: Option<&str>, to: &str) -> String {
+    let stem = match from {
+        Some(from) => {
+            let stem_len = name.len().saturating_sub(from.len() + 1);
+            let matches = stem_len > 0
+                && name.is_char_boundary(stem_len)
+                && name[stem_len..].starts_with('.')
+                && name[stem_len + 1..].eq_ignore_ascii_case(from);
+            if !matches {
+                return name.to_owned();
+            }
+            &name[..stem_len]
+        }
+        None if split_extension(name).1.is_none() => name,
+        None => return name.to_owned(),
+    };
+
+    join_extension(stem, Some(to).filter(|t| !t.is_empty()))
+}
+
+/// Replaces the stem of a file name with the [`Numbering::base`] followed by the number for the given
+/// position in the sequence, keeping the extension.
+///
+/// Numbers that have more digits than [`Numbering::width`] are never truncated, but numbers are never
+/// padded past [`MAX_NAME_LENGTH`], which no file name can be longer than anyway. Returns [`None`] if the
+/// number is too large to represent, as described in [`Numbering::number`].
+pub fn apply_numbering(name: &str, index: usize, numbering: &Numbering) -> Option<String> {
+    let stem = format!(
+        "{}{:0width$}",
+        numbering.base,
+        numbering.number(index)?,
+        width = numbering.width.min(MAX_NAME_LENGTH)
+    );
+    Some(join_extension(&stem, split_extension(name).1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbering(base: &str, start: u64, step: u64, width: usize) -> Numbering {
+        Numbering {
+            base: base.to_owned(),
+            start,
+            step,
+            width,
+        }
+    }
+
+    #[test]
+    fn numbering_pads_and_keeps_the_extension() {
+        let numbering = numbering("photo_", 1, 1, 3);
+        assert_eq!(
+            apply_numbering("IMG_4821.jpg", 0, &numbering).as_deref(),
+            Some("photo_001.jpg")
+        );
+        assert_eq!(
+            apply_numbering("IMG_4822.jpg", 1, &numbering).as_deref(),
+            Some("photo_002.jpg")
+        );
+        assert_eq!(
+            apply_numbering("notes", 9, &numbering).as_deref(),
+            Some("photo_010")
+        );
+    }
+
+    #[test]
+    fn numbering_follows_the_start_and_step() {
+        let numbering = numbering("", 10, 5, 0);
+        assert_eq!(
+            apply_numbering("a.txt", 0, &numbering).as_deref(),
+            Some("10.txt")
+        );
+        assert_eq!(
+            apply_numbering("b.txt", 3, &numbering).as_deref(),
+            Some("25.txt")
+        );
+    }
+
+    #[test]
+    fn numbering_never_truncates_wide_numbers() {
+        let numbering = numbering("n", 1234, 1, 2);
+        assert_eq!(
+            apply_numbering("a.txt", 0, &numbering).as_deref(),
+            Some("n1234.txt")
+        );
+    }
+
+    #[test]
+    fn numbering_reports_numbers_too_large_to_represent() {
+        let near_the_end = numbering("", u64::MAX - 1, 1, 0);
+        assert_eq!(near_the_end.number(1), Some(u64::MAX));
+        assert_eq!(near_the_end.number(2), None);
+        assert_eq!(apply_numbering("a.txt", 2, &near_the_end), None);
+
+        let huge_step = numbering("", 0, u64::MAX, 0);
+        assert_eq!(huge_step.number(1), Some(u64::MAX));
+        assert_eq!(huge_step.number(2), None);
+    }
+
+    #[test]
+    fn numbering_padding_is_limited() {
+        let numbering = numbering("", 1, 1, usize::MAX);
+        let name = apply_numbering("a.txt", 0, &numbering).unwrap();
+        assert_eq!(name.len(), MAX_NAME_LENGTH + ".txt".len());
+    }
+
+    #[test]
+    fn rules_report_numbering_that_overflows() {
+        let mut rule = Rule {
+            number: true,
+            numbering: numbering("", u64::MAX, 1, 0),
+            ..Default::default()
+        };
+        assert!(!rule.numbering_overflows(0));
+        assert!(!rule.numbering_overflows(1));
+        assert!(rule.numbering_overflows(2));
+        // The name is left alone rather than given a wrapped number
+        assert_eq!(rule.apply("a.txt", 1, None), "a.txt");
+
+        rule.number = false;
+        assert!(!rule.numbering_overflows(2));
+    }
+
+    /// A rule that only finds and replaces, with the find text treated as a regular expression if
+    /// `regex` is set.
+    fn find_replace(find: &str, replace: &str, regex: bool) -> Rule {
+        let mut rule = Rule {
+            replace: replace.to_owned(),
+            ..Default::default()
+        };
+        rule.set_find(find.to_owned());
+        rule.set_regex(regex);
+        rule
+    }
+
+    #[test]
+    fn regex_replacements_substitute_capture_groups() {
+        let rule = find_replace(r"(\d{4})-(\d{2})-(\d{2})", "$3.$2.$1", true);
+        assert_eq!(
+            rule.apply("log 2022-03-07.txt", 0, None),
+            "log 07.03.2022.txt"
+        );
+
+        let rule = find_replace(r"(?P<word>[a-z]+)_", "${word}-", true);
+        assert_eq!(
+            rule.apply("one_two_three.txt", 0, None),
+            "one-two-three.txt"
+        );
+    }
+
+    #[test]
+    fn plain_text_finds_regex_syntax_literally() {
+        let rule = find_replace("(1)", "$1", false);
+        assert!(rule.pattern_error().is_none());
+        assert_eq!(rule.apply("copy (1).txt", 0, None), "copy $1.txt");
+    }
+
+    #[test]
+    fn invalid_patterns_are_reported_and_leave_names_alone() {
+        let rule = find_replace("(unclosed", "x", true);
+        assert!(rule.pattern_error().is_some());
+        assert_eq!(rule.apply("(unclosed.txt", 0, None), "(unclosed.txt");
+        assert_eq!(rule.matches("(unclosed.txt").count(), 0);
+
+        let pipeline = Pipeline::from_rules(vec![rule]);
+        assert!(pipeline.pattern_error().is_some());
+    }
+
+    #[test]
+    fn case_modes_only_change_case() {
+        let stem = "the quick_brown-FOX";
+        assert_eq!(CaseMode::Unchanged.transform(stem), "the quick_brown-FOX");
+        assert_eq!(CaseMode::Lower.transform(stem), "the quick_brown-fox");
+        assert_eq!(CaseMode::Upper.transform(stem), "THE QUICK_BROWN-FOX");
+        assert_eq!(CaseMode::Title.transform(stem), "The Quick_Brown-Fox");
+        assert_eq!(CaseMode::Camel.transform(stem), "theQuickBrownFox");
+    }
+
+    #[test]
+    fn case_modes_leave_already_cased_input_alone() {
+        // Camel case removes the separators between words, so it can't find them again
+        for mode in CaseMode::ALL.into_iter().filter(|&m| m != CaseMode::Camel) {
+            let once = mode.transform("Straße Über_Ärger-ÉTÉ");
+            assert_eq!(mode.transform(&once), once, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn sharp_s_changes_length_when_uppercased() {
+        assert_eq!(CaseMode::Upper.transform("straße"), "STRASSE");
+        assert_eq!(CaseMode::Lower.transform("STRASSE"), "strasse");
+        assert_eq!(CaseMode::Title.transform("ßtraße"), "Sstraße");
+    }
+
+    #[test]
+    fn dotted_and_dotless_i_use_the_default_case_mappings() {
+        // Case is changed the same way regardless of language, so the Turkish letters keep their dots
+        assert_eq!(CaseMode::Lower.transform("İSTANBUL"), "i\u{307}stanbul");
+        assert_eq!(CaseMode::Upper.transform("ılık"), "ILIK");
+        assert_eq!(CaseMode::Title.transform("ılık"), "Ilık");
+    }
+
+    #[test]
+    fn digraphs_are_titlecased_rather_than_uppercased() {
+        assert_eq!(CaseMode::Title.transform("ǆungla ǉubav"), "ǅungla ǈubav");
+        assert_eq!(CaseMode::Title.transform("ǄUNGLA"), "ǅungla");
+        assert_eq!(CaseMode::Camel.transform("velika ǌiva"), "velikaǋiva");
+        assert_eq!(CaseMode::Upper.transform("ǆungla"), "ǄUNGLA");
+    }
+
+    /// A rule that finds the given text, regardless of case if `case_insensitive` is set.
+    fn finding(find: &str, case_insensitive: bool) -> Rule {
+        let mut rule = Rule::default();
+        rule.set_find(find.to_owned());
+        rule.set_case_insensitive(case_insensitive);
+        rule
+    }
+
+    #[test]
+    fn case_insensitive_matches_cover_the_original_text() {
+        let text = "README and readme.md";
+        let rule = finding("readme", true);
+        let matches: Vec<_> = rule.matches(text).collect();
+        assert_eq!(matches, [0..6, 11..17]);
+        assert_eq!(&text[matches[0].clone()], "README");
+
+        let rule = finding("readme", false);
+        assert_eq!(rule.matches(text).collect::<Vec<_>>(), [11..17]);
+    }
+
+    #[test]
+    fn case_insensitive_matches_of_accented_text_use_byte_offsets() {
+        // `É` and `é` are both two bytes long, so the match ends on a character boundary either way
+        let text = "CAFÉ café";
+        let rule = finding("café", true);
+        let matches: Vec<_> = rule.matches(text).collect();
+        assert_eq!(matches, [0..5, 6..11]);
+        assert_eq!(&text[matches[0].clone()], "CAFÉ");
+        assert_eq!(&text[matches[1].clone()], "café");
+
+        // `İ` lowercases to two characters, but the match still refers to the text as it is
+        let text = "İstanbul";
+        let rule = finding("STANBUL", true);
+        let matches: Vec<_> = rule.matches(text).collect();
+        assert_eq!(matches, [2..9]);
+        assert_eq!(&text[matches[0].clone()], "stanbul");
+    }
+
+    #[test]
+    fn extensions_change_after_the_last_dot() {
+        assert_eq!(
+            change_extension("photo.jpeg", Some("jpeg"), "jpg"),
+            "photo.jpg"
+        );
+        assert_eq!(
+            change_extension("photo.JPEG", Some("jpeg"), "jpg"),
+            "photo.jpg"
+        );
+        assert_eq!(
+            change_extension("photo.png", Some("jpeg"), "jpg"),
+            "photo.png"
+        );
+        assert_eq!(change_extension("photo.jpeg", Some("jpeg"), ""), "photo");
+    }
+
+    #[test]
+    fn extensions_with_several_dots_can_be_changed_together() {
+        assert_eq!(
+            change_extension("archive.tar.gz", Some("gz"), "tgz"),
+            "archive.tar.tgz"
+        );
+        assert_eq!(
+            change_extension("archive.tar.gz", Some("tar.gz"), "tgz"),
+            "archive.tgz"
+        );
+        assert_eq!(
+            change_extension("archive.tar.gz", Some("tar"), "zip"),
+            "archive.tar.gz"
+        );
+        assert_eq!(
+            split_extension("archive.tar.gz"),
+            ("archive.tar", Some("gz"))
+        );
+    }
+
+    #[test]
+    fn extensions_are_only_added_to_names_without_one() {
+        assert_eq!(change_extension("README", None, "md"), "README.md");
+        assert_eq!(change_extension("notes.txt", None, "md"), "notes.txt");
+    }
+
+    #[test]
+    fn leading_dots_are_not_extension_separators() {
+        assert_eq!(split_extension(".gitignore"), (".gitignore", None));
+        assert_eq!(
+            change_extension(".gitignore", None, "bak"),
+            ".gitignore.bak"
+        );
+        assert_eq!(
+            change_extension(".gitignore", Some("gitignore"), "txt"),
+            ".gitignore"
+        );
+        assert_eq!(split_extension(".config.json"), (".config", Some("json")));
+    }
+
+    #[test]
+    fn whitespace_clean_up_handles_tabs_and_newlines() {
+        assert_eq!(trim_whitespace("\t my file \n"), "my file");
+        assert_eq!(collapse_whitespace("my\t\tfile\n name"), "my file name");
+        assert_eq!(replace_spaces("my file name", '_'), "my_file_name");
+
+        let mut rule = Rule::default();
+        rule.trim = true;
+        rule.collapse_whitespace = true;
+        rule.spaces = SpaceReplacement::Hyphen;
+        assert_eq!(rule.apply(" my \t file\n.txt", 0, None), "my-file.txt");
+    }
+
+    #[test]
+    fn whitespace_clean_up_is_idempotent() {
+        for stem in ["my file", "my  file ", "\tmy\nfile", "", "   "] {
+            let trimmed = trim_whitespace(stem);
+            assert_eq!(trim_whitespace(trimmed), trimmed);
+            let collapsed = collapse_whitespace(stem);
+            assert_eq!(collapse_whitespace(&collapsed), collapsed);
+            let replaced = replace_spaces(stem, '_');
+            assert_eq!(replace_spaces(&replaced, '_'), replaced);
+        }
+        assert_eq!(collapse_whitespace("tidy_name"), "tidy_name");
+    }
+
+    #[test]
+    fn remove_range_counts_characters_rather_than_bytes() {
+        assert_eq!(remove_range("héllo wörld", 1, 4), "h wörld");
+        assert_eq!(remove_range("日本語のファイル", 3, 1), "日本語ファイル");
+        assert_eq!(remove_range("🎉party🎉", 0, 1), "party🎉");
+        assert_eq!(remove_range("🎉party🎉", 6, 1), "🎉party");
+    }
+
+    #[test]
+    fn remove_range_is_limited_to_the_stem() {
+        assert_eq!(remove_range("héllo", 5, 2), "héllo");
+        assert_eq!(remove_range("héllo", 9, 2), "héllo");
+        assert_eq!(remove_range("héllo", 3, 100), "hél");
+        assert_eq!(remove_range("héllo", 0, 0), "héllo");
+        assert_eq!(remove_range("", 0, 3), "");
+    }
+
+    #[test]
+    fn pipelines_apply_their_rules_in_order() {
+        let lowercase = Rule {
+            case: CaseMode::Lower,
+            ..Default::default()
+        };
+        let suffix = Rule {
+            suffix: "_v2".to_owned(),
+            ..Default::default()
+        };
+        let mut pipeline = Pipeline::default();
+        pipeline.rules_mut()[0] = find_replace("Draft", "Final", false);
+        pipeline.insert(1, lowercase);
+        pipeline.insert(2, suffix);
+
+        assert_eq!(
+            pipeline.apply("Report Draft.TXT", 0, None),
+            "report final_v2.TXT"
+        );
+        assert_eq!(
+            pipeline.target(Path::new("docs/Draft.md"), 0, None),
+            Path::new("docs/final_v2.md")
+        );
+
+        // Lowercasing first means the find text no longer matches
+        pipeline.rules_mut().swap(0, 1);
+        assert_eq!(
+            pipeline.apply("Report Draft.TXT", 0, None),
+            "report draft_v2.TXT"
+        );
+    }
+
+    /// Saves the given pipeline and loads it back.
+    fn round_trip(pipeline: &Pipeline) -> Pipeline {
+        serde_json::from_str(&serde_json::to_string(pipeline).unwrap()).unwrap()
+    }
+
+    /// The saved form of every rule in the given pipeline, for comparing rules.
+    fn saved_rules(pipeline: &Pipeline) -> Vec<serde_json::Value> {
+        pipeline
+            .rules()
+            .iter()
+            .map(|r| serde_json::to_value(r).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn pipelines_of_every_kind_of_rule_survive_a_round_trip() {
+        let mut pipeline = Pipeline::from_rules(Vec::new());
+        for case in CaseMode::ALL {
+            pipeline.rules.push(Rule {
+                case,
+                ..Default::default()
+            });
+        }
+        for spaces in SpaceReplacement::ALL {
+            pipeline.rules.push(Rule {
+                spaces,
+                ..Default::default()
+            });
+        }
+        let mut rule = Rule {
+            use_template: true,
+            number: true,
+            numbering: numbering("photo", 5, 2, 4),
+            remove: true,
+            remove_start: 1,
+            remove_count: 2,
+            strip_counter: true,
+            trim: true,
+            collapse_whitespace: true,
+            prefix: "a".to_owned(),
+            suffix: "b".to_owned(),
+            change_extension: true,
+            extension_from: "jpeg".to_owned(),
+            extension_to: "jpg".to_owned(),
+            ..find_replace(r"(\d+)", "#$1", true)
+        };
+        rule.set_template("{counter}_{name}".to_owned());
+        pipeline.rules.push(rule);
+
+        let mut loaded = round_trip(&pipeline);
+        assert!(loaded.take_load_warnings().is_empty());
+        assert_eq!(saved_rules(&loaded), saved_rules(&pipeline));
+    }
+
+    #[test]
+    fn rules_are_saved_with_their_kind_and_a_version() {
+        let saved = serde_json::to_value(Pipeline::default()).unwrap();
+        assert_eq!(saved["version"], PIPELINE_VERSION);
+        assert_eq!(saved["rules"][0]["kind"], "Rule");
+    }
+
+    #[test]
+    fn unknown_rules_and_newer_versions_are_reported() {
+        let saved = r#"{
+            "version": 99,
+            "rules": [
+                { "kind": "Rule", "prefix": "a_" },
+                { "kind": "Teleport", "destination": "moon" },
+                { "kind": "Rule", "suffix": "_b" }
+            ]
+        }"#;
+        let mut pipeline: Pipeline = serde_json::from_str(saved).unwrap();
+        assert_eq!(
+            pipeline.take_load_warnings(),
+            [LoadWarning::NewerVersion(99), LoadWarning::SkippedRules(1)]
+        );
+        assert_eq!(pipeline.rules().len(), 2);
+        assert_eq!(pipeline.apply("name.txt", 0, None), "a_name_b.txt");
+        // Warnings are only reported once
+        assert!(pipeline.take_load_warnings().is_empty());
+    }
+
+    #[test]
+    fn pipelines_of_only_unknown_rules_load_as_the_default() {
+        let saved = r#"{ "version": 1, "rules": [{ "kind": "Teleport" }] }"#;
+        let mut pipeline: Pipeline = serde_json::from_str(saved).unwrap();
+        assert_eq!(
+            pipeline.take_load_warnings(),
+            [LoadWarning::SkippedRules(1)]
+        );
+        assert_eq!(pipeline.rules().len(), 1);
+        assert_eq!(pipeline.apply("name.txt", 0, None), "name.txt");
+    }
+
+    #[test]
+    fn malformed_rules_are_reported_apart_from_unknown_ones() {
+        let saved = r#"{
+            "version": 1,
+            "rules": [
+                { "kind": "Rule", "prefix": 5 },
+                { "kind": "Teleport" },
+                { "kind": "Rule", "suffix": "_b" }
+            ]
+        }"#;
+        let mut pipeline: Pipeline = serde_json::from_str(saved).unwrap();
+        assert_eq!(
+            pipeline.take_load_warnings(),
+            [LoadWarning::MalformedRules(1), LoadWarning::SkippedRules(1)]
+        );
+        assert_eq!(pipeline.apply("name.txt", 0, None), "name_b.txt");
+    }
+
+    #[test]
+    fn single_rules_saved_by_older_versions_still_load() {
+        let mut pipeline: Pipeline = serde_json::from_str(r#"{ "prefix": "a_" }"#).unwrap();
+        assert!(pipeline.take_load_warnings().is_empty());
+        assert_eq!(pipeline.apply("name.txt", 0, None), "a_name.txt");
+    }
+
+    #[test]
+    fn objects_that_are_not_rules_fail_to_load() {
+        for saved in [
+            "{}",
+            r#"{ "version": 1, "rules": {} }"#,
+            r#"{ "width": 800, "height": 600 }"#,
+            r#"{ "prefix": "a_", "colour": "red" }"#,
+        ] {
+            assert!(
+                serde_json::from_str::<Pipeline>(saved).is_err(),
+                "{}",
+                saved
+            );
+        }
+    }
+
+    #[test]
+    fn counters_are_stripped_with_their_separator() {
+        assert_eq!(strip_counter("photo_004"), "photo");
+        assert_eq!(strip_counter("track 12"), "track");
+        assert_eq!(strip_counter("scan-0001"), "scan");
+        assert_eq!(strip_counter("page.7"), "page");
+        assert_eq!(strip_counter("IMG0042"), "IMG");
+        // Only a single separator is removed
+        assert_eq!(strip_counter("photo__004"), "photo_");
+    }
+
+    #[test]
+    fn stems_without_a_counter_are_unchanged() {
+        assert_eq!(strip_counter("photo"), "photo");
+        assert_eq!(strip_counter("2021_holiday"), "2021_holiday");
+        assert_eq!(strip_counter("004"), "004");
+        assert_eq!(strip_counter("_004"), "_004");
+        assert_eq!(strip_counter(""), "");
+    }
+
+    #[test]
+    fn stripping_counters_keeps_the_extension() {
+        let rule = Rule {
+            strip_counter: true,
+            suffix: "_final".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(rule.apply("photo_004.jpg", 0, None), "photo_final.jpg");
+        assert_eq!(rule.apply("notes.2021.txt", 0, None), "notes_final.txt");
+    }
+
+    /// Whether the first occurrence of `word` in `text` is a whole word.
+    fn is_whole(text: &str, word: &str) -> bool {
+        let start = text.find(word).unwrap();
+        is_whole_word(text, &(start..start + word.len()))
+    }
+
+    #[test]
+    fn words_at_the_start_and_end_are_whole() {
+        assert!(is_whole("cat", "cat"));
+        assert!(is_whole("cat food", "cat"));
+        assert!(is_whole("black cat", "cat"));
+        assert!(!is_whole("category", "cat"));
+        assert!(!is_whole("bobcat", "cat"));
+    }
+
+    #[test]
+    fn punctuation_separates_words() {
+        assert!(is_whole("cat.txt", "cat"));
+        assert!(is_whole("my-cat_photo", "cat"));
+        assert!(is_whole("(cat)", "cat"));
+        assert!(is_whole("cat's", "cat"));
+        // Underscores are punctuation too, unlike in regex word boundaries
+        assert!(is_whole("a_cat_b", "cat"));
+        assert!(!is_whole("cat2", "cat"));
+        assert!(!is_whole("écat", "cat"));
+    }
+
+    #[test]
+    fn matches_that_include_punctuation_are_whole_at_that_edge() {
+        assert!(is_whole("a-cat", "-cat"));
+        assert!(is_whole("xx-cat", "-cat"));
+        assert!(!is_whole("a-cats", "-cat"));
+        assert!(is_whole("a cat b", " cat "));
+    }
+
+    #[test]
+    fn whole_word_rules_skip_parts_of_words() {
+        let mut rule = find_replace("cat", "dog", false);
+        rule.whole_word = true;
+        assert_eq!(
+            rule.apply("cat category bobcat cat.txt", 0, None),
+            "dog category bobcat dog.txt"
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Collecting the files within directories.
+
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// A file found by a scan.
+#[derive(Debug)]
+pub struct FoundFile {
+    /// The path of the file.
+    pub path: PathBuf,
+    /// The metadata of the file, if it could be read. It's read as part of the scan, so that listing a
+    /// large number of files doesn't have to wait for each of them to be read.
+    pub metadata: Option<fs::Metadata>,
+}
+
+/// The files found by reading some of the directories in a scan, along with the directories still left
+/// to read.
+#[derive(Debug, Default)]
+pub struct ScanBatch {
+    /// The files that were found, sorted by path.
+    pub files: Vec<FoundFile>,
+    /// The errors from reading directories that couldn't be read.
+    pub errors: Vec<io::Error>,
+    /// The directories that haven't been read yet.
+    pub pending: Vec<PathBuf>,
+}
+
+/// Reads up to `max_dirs` of the given directories, collecting the files within them. If `recursive` is
+/// set, their subdirectories are added to the directories left to read. Hidden files and directories,
+/// whose names start with a dot, are skipped.
+///
+/// Reading a directory tree a batch at a time lets a large scan report its progress, and be abandoned
+/// part of the way through. Directories that can't be read are skipped rather than aborting the whole
+/// scan, and the errors from reading them are returned alongside the files that were found.
+pub fn scan_dirs(mut pending: Vec<PathBuf>, recursive: bool, max_dirs: usize) -> ScanBatch {
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+
+    for _ in 0..max_dirs {
+        let dir = match pending.pop() {
+            Some(dir) => dir,
+            None => break,
+        };
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+
+        for entry in entries {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    errors.push(e);
+                    continue;
+                }
+            };
+
+            if is_hidden(&entry.file_name()) {
+                continue;
+            }
+
+            match entry.file_type() {
+                Ok(t) if t.is_dir() => {
+                    if recursive {
+                        pending.push(entry.path());
+                    }
+                }
+                Ok(_) => {
+                    let path = entry.path();
+                    // Links are followed, as they are for files that are opened directly
+                    let metadata = fs::metadata(&path).ok();
+                    files.push(FoundFile { path, metadata });
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    ScanBatch {
+        files,
+        errors,
+        pending,
+    }
+}
+
+/// Expands the given command line arguments into paths. Arguments that are existing paths are kept as
+/// they are, so that arguments already expanded by a shell still work, and the rest are treated as glob
+/// patterns, such as `*.jpg`.
+///
+/// Patterns that match nothing are kept as literal paths. Patterns that aren't valid, and matches that
+/// can't be read, are skipped with a warning rather than stopping the rest from loading.
+pub fn expand_args(args: impl IntoIterator<Item = String>) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for arg in args {
+        if Path::new(&arg).exists() {
+            paths.push(PathBuf::from(arg));
+            continue;
+        }
+
+        let matches = match glob::glob(&arg) {
+            Ok(matches) => matches,
+            Err(e) => {
+                eprintln!("Skipping invalid pattern '{}': {}", arg, e);
+                continue;
+            }
+        };
+
+        let len = paths.len();
+        for m in matches {
+            match m {
+                Ok(path) => paths.push(path),
+                Err(e) => eprintln!("Skipping unreadable match of '{}': {}", arg, e),
+            }
+        }
+        if paths.len() == len {
+            paths.push(PathBuf::from(arg));
+        }
+    }
+    paths
+}
+
+fn is_hidden(name: &std::ffi::OsStr) -> bool {
+    name.to_string_lossy().starts_with('.')
+}
+
+/// Finds the paths of existing files in the given text, such as a list copied from a file manager or a
+/// terminal, with one path on each line.
+///
+/// Whitespace and quotes around each path are ignored. Lines that aren't the path of an existing file
+/// are skipped, and paths that appear more than once are only included once.
+pub fn paths_from_text(text: &str) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    text.lines()
+        .map(|line| line.trim().trim_matches(|c| c == '"' || c == '\''))
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .filter(|path| path.is_file() && seen.insert(path.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, process};
+
+    #[test]
+    fn pasted_text_is_parsed_into_existing_files() {
+        let dir = std::env::temp_dir().join(format!("mass_renamer_pasted_{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("folder")).unwrap();
+        let a = dir.join("a.txt");
+        let b = dir.join("b c.txt");
+        fs::write(&a, "").unwrap();
+        fs::write(&b, "").unwrap();
+
+        let text = format!(
+            "{a}\n  \"{b}\"  \r\n\n'{a}'\nnot a path\n{missing}\n{folder}\n",
+            a = a.display(),
+            b = b.display(),
+            missing = dir.join("missing.txt").display(),
+            folder = dir.join("folder").display(),
+        );
+        let paths = paths_from_text(&text);
+        fs::remove_dir_all(&dir).unwrap();
+
+        // Duplicates, blank lines, missing files, folders and other text are all skipped
+        assert_eq!(paths, [a, b]);
+    }
+
+    #[test]
+    fn text_without_paths_is_ignored() {
+        assert!(paths_from_text("").is_empty());
+        assert!(paths_from_text("hello\nworld").is_empty());
+    }
+
+    #[test]
+    fn scans_find_sorted_files_with_their_metadata() {
+        let dir = std::env::temp_dir().join(format!("mass_renamer_scan_{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("b.txt"), "bb").unwrap();
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::write(dir.join(".hidden"), "").unwrap();
+        fs::write(dir.join("sub").join("c.txt"), "ccc").unwrap();
+
+        let batch = scan_dirs(vec![dir.clone()], true, 1);
+        let found: Vec<_> = batch
+            .files
+            .iter()
+            .map(|f| (f.path.clone(), f.metadata.as_ref().map(|m| m.len())))
+            .collect();
+        assert_eq!(
+            found,
+            [(dir.join("a.txt"), Some(1)), (dir.join("b.txt"), Some(2))]
+        );
+        assert_eq!(batch.pending, [dir.join("sub")]);
+
+        let batch = scan_dirs(batch.pending, true, 1);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(batch.files[0].path, dir.join("sub").join("c.txt"));
+        assert!(batch.pending.is_empty());
+    }
+}
@@ -0,0 +1,396 @@
+//! Styling the app according to the chosen theme.
+
+use crate::listbox;
+use iced::{
+    button, checkbox, container, pick_list, progress_bar, radio, text_input, Color, Vector,
+};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The colour scheme of the whole app.
+///
+/// Each widget is styled by passing the [`Theme`] itself as its style sheet, except where a widget needs
+/// more than one styling, such as [`ListContainer`] and [`InvalidInput`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    /// Dark text on a light background.
+    #[default]
+    Light,
+    /// Light text on a dark background.
+    Dark,
+}
+
+impl Theme {
+    /// Every [`Theme`], in the order they should be displayed.
+    pub const ALL: [Theme; 2] = [Theme::Light, Theme::Dark];
+
+    /// The colour behind every widget.
+    pub fn background(&self) -> Color {
+        match self {
+            Theme::Light => Color::WHITE,
+            Theme::Dark => Color::from_rgb8(0x20, 0x22, 0x25),
+        }
+    }
+
+    /// The colour of text that isn't styled otherwise.
+    pub fn text(&self) -> Color {
+        match self {
+            Theme::Light => Color::BLACK,
+            Theme::Dark => Color::from_rgb8(0xe8, 0xe8, 0xe8),
+        }
+    }
+
+    /// The [`ListBox`](listbox::ListBox) styling that matches this theme.
+    pub fn listbox(&self) -> listbox::Style {
+        match self {
+            Theme::Light => listbox::Style::light(true),
+            Theme::Dark => listbox::Style::dark(true),
+        }
+    }
+
+    /// The colour behind the parts of names that match the find text, unless chosen otherwise.
+    pub fn highlight(&self) -> Color {
+        match self {
+            Theme::Light => Color::from_rgb8(0xff, 0xc0, 0xcb),
+            Theme::Dark => Color::from_rgb8(0x8a, 0x3a, 0x5c),
+        }
+    }
+
+    /// The colour behind characters that renaming inserts into a name.
+    pub fn inserted(&self) -> Color {
+        match self {
+            Theme::Light => Color::from_rgb8(0xb4, 0xe8, 0xb4),
+            Theme::Dark => Color::from_rgb8(0x2e, 0x6b, 0x3a),
+        }
+    }
+
+    /// The colour behind characters that renaming removes from a name.
+    pub fn removed(&self) -> Color {
+        match self {
+            Theme::Light => Color::from_rgb8(0xf4, 0xb4, 0xb4),
+            Theme::Dark => Color::from_rgb8(0x7a, 0x2e, 0x32),
+        }
+    }
+
+    /// The colour behind interactive widgets, such as buttons and text inputs.
+    fn surface(&self) -> Color {
+        match self {
+            Theme::Light => Color::from_rgb8(0xf2, 0xf3, 0xf5),
+            Theme::Dark => Color::from_rgb8(0x2f, 0x32, 0x36),
+        }
+    }
+
+    /// The colour of interactive widgets while the cursor is over them.
+    fn surface_hovered(&self) -> Color {
+        match self {
+            Theme::Light => Color::from_rgb8(0xe4, 0xe6, 0xea),
+            Theme::Dark => Color::from_rgb8(0x3a, 0x3e, 0x43),
+        }
+    }
+
+    fn border(&self) -> Color {
+        match self {
+            Theme::Light => Color::from_rgb8(0xbe, 0xbe, 0xbe),
+            Theme::Dark => Color::from_rgb8(0x5a, 0x5d, 0x61),
+        }
+    }
+
+    fn accent(&self) -> Color {
+        match self {
+            Theme::Light => Color::from_rgb8(0x30, 0x8e, 0xc9),
+            Theme::Dark => Color::from_rgb8(0x5a, 0xa0, 0xf0),
+        }
+    }
+
+    fn placeholder(&self) -> Color {
+        match self {
+            Theme::Light => Color::from_rgb(0.7, 0.7, 0.7),
+            Theme::Dark => Color::from_rgb(0.45, 0.45, 0.45),
+        }
+    }
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Theme::Light => "Light Theme",
+            Theme::Dark => "Dark Theme",
+        })
+    }
+}
+
+/// The colour behind the parts of names that match the find text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Highlight {
+    /// Whichever colour suits the current [`Theme`].
+    #[default]
+    Theme,
+    /// A light pink, whatever the theme.
+    Pink,
+    /// A light yellow, whatever the theme.
+    Yellow,
+    /// A light green, whatever the theme.
+    Green,
+    /// A light blue, whatever the theme.
+    Blue,
+}
+
+impl Highlight {
+    /// Every [`Highlight`], in the order they should be displayed.
+    pub const ALL: [Highlight; 5] = [
+        Highlight::Theme,
+        Highlight::Pink,
+        Highlight::Yellow,
+        Highlight::Green,
+        Highlight::Blue,
+    ];
+
+    /// The colour of this highlight when the app is styled with the given theme.
+    pub fn color(&self, theme: Theme) -> Color {
+        match self {
+            Highlight::Theme => theme.highlight(),
+            Highlight::Pink => Color::from_rgb8(0xff, 0xc0, 0xcb),
+            Highlight::Yellow => Color::from_rgb8(0xff, 0xe0, 0x66),
+            Highlight::Green => Color::from_rgb8(0x9f, 0xe0, 0x9f),
+            Highlight::Blue => Color::from_rgb8(0x9c, 0xc8, 0xf5),
+        }
+    }
+}
+
+impl fmt::Display for Highlight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Highlight::Theme => "Theme Highlight",
+            Highlight::Pink => "Pink Highlight",
+            Highlight::Yellow => "Yellow Highlight",
+            Highlight::Green => "Green Highlight",
+            Highlight::Blue => "Blue Highlight",
+        })
+    }
+}
+
+impl container::StyleSheet for Theme {
+    fn style(&self) -> container::Style {
+        container::Style {
+            text_color: Some(self.text()),
+            background: Some(self.background().into()),
+            ..Default::default()
+        }
+    }
+}
+
+impl button::StyleSheet for Theme {
+    fn active(&self) -> button::Style {
+        button::Style {
+            shadow_offset: Vector::default(),
+            background: Some(self.surface().into()),
+            border_radius: 2.0,
+            border_width: 1.0,
+            border_color: self.border(),
+            text_color: self.text(),
+        }
+    }
+
+    fn hovered(&self) -> button::Style {
+        button::Style {
+            background: Some(self.surface_hovered().into()),
+            ..button::StyleSheet::active(self)
+        }
+    }
+
+    fn disabled(&self) -> button::Style {
+        let active = button::StyleSheet::active(self);
+        button::Style {
+            text_color: Color {
+                a: 0.5,
+                ..active.text_color
+            },
+            ..active
+        }
+    }
+}
+
+impl checkbox::StyleSheet for Theme {
+    fn active(&self, _is_checked: bool) -> checkbox::Style {
+        checkbox::Style {
+            background: self.surface().into(),
+            checkmark_color: self.accent(),
+            text_color: Some(self.text()),
+            border_radius: 5.0,
+            border_width: 1.0,
+            border_color: self.border(),
+        }
+    }
+
+    fn hovered(&self, is_checked: bool) -> checkbox::Style {
+        checkbox::Style {
+            background: self.surface_hovered().into(),
+            ..checkbox::StyleSheet::active(self, is_checked)
+        }
+    }
+}
+
+impl radio::StyleSheet for Theme {
+    fn active(&self) -> radio::Style {
+        radio::Style {
+            background: self.surface().into(),
+            dot_color: self.accent(),
+            border_width: 1.0,
+            border_color: self.border(),
+            text_color: Some(self.text()),
+        }
+    }
+
+    fn hovered(&self) -> radio::Style {
+        radio::Style {
+            background: self.surface_hovered().into(),
+            ..radio::StyleSheet::active(self)
+        }
+    }
+}
+
+impl text_input::StyleSheet for Theme {
+    fn active(&self) -> text_input::Style {
+        text_input::Style {
+            background: self.background().into(),
+            border_radius: 5.0,
+            border_width: 1.0,
+            border_color: self.border(),
+        }
+    }
+
+    fn focused(&self) -> text_input::Style {
+        text_input::Style {
+            border_color: self.accent(),
+            ..text_input::StyleSheet::active(self)
+        }
+    }
+
+    fn placeholder_color(&self) -> Color {
+        self.placeholder()
+    }
+
+    fn value_color(&self) -> Color {
+        self.text()
+    }
+
+    fn selection_color(&self) -> Color {
+        Color {
+            a: 0.3,
+            ..self.accent()
+        }
+    }
+}
+
+impl pick_list::StyleSheet for Theme {
+    fn menu(&self) -> pick_list::Menu {
+        pick_list::Menu {
+            text_color: self.text(),
+            background: self.surface().into(),
+            border_width: 1.0,
+            border_color: self.border(),
+            selected_text_color: Color::WHITE,
+            selected_background: self.accent().into(),
+        }
+    }
+
+    fn active(&self) -> pick_list::Style {
+        pick_list::Style {
+            text_color: self.text(),
+            placeholder_color: self.placeholder(),
+            background: self.surface().into(),
+            border_radius: 2.0,
+            border_width: 1.0,
+            border_color: self.border(),
+            icon_size: 0.7,
+        }
+    }
+
+    fn hovered(&self) -> pick_list::Style {
+        pick_list::Style {
+            background: self.surface_hovered().into(),
+            ..pick_list::StyleSheet::active(self)
+        }
+    }
+}
+
+impl progress_bar::StyleSheet for Theme {
+    fn style(&self) -> progress_bar::Style {
+        progress_bar::Style {
+            background: self.surface().into(),
+            bar: self.accent().into(),
+            border_radius: 5.0,
+        }
+    }
+}
+
+/// The border around the list of files.
+pub struct ListContainer(pub Theme);
+
+impl container::StyleSheet for ListContainer {
+    fn style(&self) -> container::Style {
+        container::Style {
+            border_width: 1.0,
+            border_color: self.0.border(),
+            ..Default::default()
+        }
+    }
+}
+
+/// A button that has keyboard focus.
+pub struct FocusedButton(pub Theme);
+
+impl button::StyleSheet for FocusedButton {
+    fn active(&self) -> button::Style {
+        button::Style {
+            border_width: 2.0,
+            border_color: self.0.accent(),
+            ..button::StyleSheet::active(&self.0)
+        }
+    }
+
+    fn hovered(&self) -> button::Style {
+        button::Style {
+            background: Some(self.0.surface_hovered().into()),
+            ..self.active()
+        }
+    }
+
+    fn disabled(&self) -> button::Style {
+        button::Style {
+            text_color: button::StyleSheet::disabled(&self.0).text_color,
+            ..self.active()
+        }
+    }
+}
+
+/// A text input whose contents aren't valid.
+pub struct InvalidInput(pub Theme);
+
+impl text_input::StyleSheet for InvalidInput {
+    fn active(&self) -> text_input::Style {
+        text_input::Style {
+            border_color: Color::from_rgb8(0xe0, 0x40, 0x40),
+            ..text_input::StyleSheet::active(&self.0)
+        }
+    }
+
+    fn focused(&self) -> text_input::Style {
+        text_input::Style {
+            border_color: Color::from_rgb8(0xc0, 0x20, 0x20),
+            ..self.active()
+        }
+    }
+
+    fn placeholder_color(&self) -> Color {
+        self.0.placeholder_color()
+    }
+
+    fn value_color(&self) -> Color {
+        self.0.value_color()
+    }
+
+    fn selection_color(&self) -> Color {
+        self.0.selection_color()
+    }
+}
@@ -0,0 +1,247 @@
+//! Naming files after a template, such as `{name}_{counter:03}{ext}`.
+
+use crate::plan::MAX_NAME_LENGTH;
+use chrono::{DateTime, Local};
+use std::{fmt, time::SystemTime};
+
+/// A parsed template for the names of files.
+///
+/// Templates are plain text with tokens in braces, which are replaced for each file:
+///
+/// - `{name}` is the stem of the file name.
+/// - `{ext}` is the extension of the file name, including its dot, or nothing if there isn't one.
+/// - `{counter}` is the position of the file in the list of files being renamed, counting from 1. It can
+///   be padded with leading zeroes to a minimum number of digits, as in `{counter:03}`, up to
+///   [`MAX_NAME_LENGTH`] digits.
+/// - `{date}` is the date the file was last modified, as in `2022-07-31`, or nothing if it isn't known.
+///
+/// Literal braces are written by doubling them, as in `{{` and `}}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    tokens: Vec<Token>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Text(String),
+    Name,
+    Extension,
+    Counter { width: usize },
+    Date,
+}
+
+/// A reason that a template couldn't be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TemplateError {
+    /// A token isn't one of the known tokens.
+    UnknownToken(String),
+    /// A token was opened with `{` but never closed.
+    Unclosed,
+    /// A `}` doesn't close a token, and isn't doubled to be a literal brace.
+    UnmatchedClose,
+    /// The padding of a counter isn't a number, as in `{counter:abc}`, or is wider than any file name
+    /// can be, as described in [`Template`].
+    InvalidWidth(String),
+}
+
+impl Template {
+    /// Parses the given template, as described in [`Template`].
+    pub fn parse(source: &str) -> Result<Self, TemplateError> {
+        let mut tokens = Vec::new();
+        let mut text = String::new();
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => return Err(TemplateError::UnmatchedClose),
+                '{' => {
+                    let mut token = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => token.push(c),
+                            None => return Err(TemplateError::Unclosed),
+                        }
+                    }
+                    if !text.is_empty() {
+                        tokens.push(Token::Text(std::mem::take(&mut text)));
+                    }
+                    tokens.push(Token::parse(&token)?);
+                }
+                c => text.push(c),
+            }
+        }
+
+        if !text.is_empty() {
+            tokens.push(Token::Text(text));
+        }
+        Ok(Self { tokens })
+    }
+
+    /// Formats a file name from this template, for a file with the given stem and extension at the given
+    /// position in the list of files being renamed, which was last modified at the given time.
+    pub fn format(
+        &self,
+        stem: &str,
+        extension: Option<&str>,
+        index: usize,
+        modified: Option<SystemTime>,
+    ) -> String {
+        let mut name = String::new();
+        for token in &self.tokens {
+            match token {
+                Token::Text(text) => name += text,
+                Token::Name => name += stem,
+                Token::Extension => {
+                    if let Some(extension) = extension {
+                        name.push('.');
+                        name += extension;
+                    }
+                }
+                Token::Counter { width } => {
+                    name += &format!("{:0width$}", index + 1, width = width);
+                }
+                Token::Date => {
+                    if let Some(modified) = modified {
+                        name += &DateTime::<Local>::from(modified)
+                            .format("%Y-%m-%d")
+                            .to_string();
+                    }
+                }
+            }
+        }
+        name
+    }
+}
+
+impl Token {
+    /// Parses the contents of a token, between its braces.
+    fn parse(token: &str) -> Result<Self, TemplateError> {
+        let (name, argument) = match token.split_once(':') {
+            Some((name, argument)) => (name, Some(argument)),
+            None => (token, None),
+        };
+
+        match (name.trim(), argument) {
+            ("name", None) => Ok(Token::Name),
+            ("ext", None) => Ok(Token::Extension),
+            ("date", None) => Ok(Token::Date),
+            ("counter", None) => Ok(Token::Counter { width: 0 }),
+            ("counter", Some(width)) => match width.trim().parse() {
+                Ok(width) if width <= MAX_NAME_LENGTH => Ok(Token::Counter { width }),
+                _ => Err(TemplateError::InvalidWidth(width.to_owned())),
+            },
+            _ => Err(TemplateError::UnknownToken(token.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::UnknownToken(token) => write!(f, "unknown token {{{}}}", token),
+            TemplateError::Unclosed => f.write_str("a token is missing its closing }"),
+            TemplateError::UnmatchedClose => {
+                f.write_str("a } doesn't close a token (write }} for a literal brace)")
+            }
+            TemplateError::InvalidWidth(width) => {
+                write!(
+                    f,
+                    "the counter padding {:?} isn't a number up to {}",
+                    width, MAX_NAME_LENGTH
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(template: &str, stem: &str, extension: Option<&str>, index: usize) -> String {
+        Template::parse(template)
+            .unwrap()
+            .format(stem, extension, index, None)
+    }
+
+    #[test]
+    fn tokens_are_replaced() {
+        assert_eq!(
+            format("{name}_{counter:03}{ext}", "photo", Some("jpg"), 4),
+            "photo_005.jpg"
+        );
+        assert_eq!(format("{counter}-{name}", "notes", None, 11), "12-notes");
+        assert_eq!(format("plain", "photo", Some("jpg"), 0), "plain");
+        assert_eq!(format("", "photo", Some("jpg"), 0), "");
+    }
+
+    #[test]
+    fn tokens_can_have_spaces_around_them() {
+        assert_eq!(format("{ name }{ counter : 2 }", "a", None, 0), "a01");
+    }
+
+    #[test]
+    fn counters_are_never_truncated() {
+        assert_eq!(format("{counter:2}", "a", None, 999), "1000");
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(format("{{{name}}}", "a", None, 0), "{a}");
+        assert_eq!(format("}}{{", "a", None, 0), "}{");
+    }
+
+    #[test]
+    fn unknown_dates_are_left_out() {
+        assert_eq!(format("{name}{date}", "a", None, 0), "a");
+        let name = Template::parse("{date}")
+            .unwrap()
+            .format("a", None, 0, Some(SystemTime::now()));
+        assert_eq!(name, Local::now().format("%Y-%m-%d").to_string());
+    }
+
+    #[test]
+    fn malformed_templates_are_rejected() {
+        assert_eq!(
+            Template::parse("{nmae}"),
+            Err(TemplateError::UnknownToken("nmae".to_owned()))
+        );
+        assert_eq!(
+            Template::parse("{name:03}"),
+            Err(TemplateError::UnknownToken("name:03".to_owned()))
+        );
+        assert_eq!(
+            Template::parse("{}"),
+            Err(TemplateError::UnknownToken(String::new()))
+        );
+        assert_eq!(Template::parse("{name"), Err(TemplateError::Unclosed));
+        assert_eq!(Template::parse("name}"), Err(TemplateError::UnmatchedClose));
+        assert_eq!(
+            Template::parse("{counter:abc}"),
+            Err(TemplateError::InvalidWidth("abc".to_owned()))
+        );
+        assert_eq!(
+            Template::parse("{counter:-1}"),
+            Err(TemplateError::InvalidWidth("-1".to_owned()))
+        );
+    }
+
+    #[test]
+    fn counter_widths_are_limited() {
+        let widest = format!("{{counter:{}}}", MAX_NAME_LENGTH);
+        assert_eq!(format(&widest, "a", None, 0).len(), MAX_NAME_LENGTH);
+        assert_eq!(
+            Template::parse("{counter:99999999999}"),
+            Err(TemplateError::InvalidWidth("99999999999".to_owned()))
+        );
+    }
+}